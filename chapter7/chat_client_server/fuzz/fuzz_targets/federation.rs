@@ -0,0 +1,47 @@
+// fuzz/fuzz_targets/federation.rs
+// 연합 링크로 받은 줄(링크 키로 복호화한 평문) "<종류> <출발 서버> <ID> ..." 퍼징
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rand::rngs::OsRng; // ecdhkey 모듈이 crate::OsRng 로 사용
+
+#[path = "../../src/ecdh/ecdhkey.rs"]
+#[allow(dead_code)] // link 모듈의 핸드셰이크가 사용
+mod ecdhkey;
+#[path = "../../src/ecdh/secret.rs"]
+#[allow(dead_code)] // link 모듈의 핸드셰이크가 사용
+mod secret;
+#[path = "../../src/proto/packet.rs"]
+#[allow(dead_code)] // LineConn이 사용
+mod packet;
+#[path = "../../src/proto/conn.rs"]
+#[allow(dead_code)] // link 모듈의 핸드셰이크가 사용
+mod conn;
+#[path = "../../src/proto/frame.rs"]
+mod frame;
+#[path = "../../src/proto/noise.rs"]
+#[allow(dead_code)] // LineConn이 쓰는 Noise 전송 상태 타입만 필요
+mod noise;
+#[path = "../../src/proto/transcript.rs"]
+#[allow(dead_code)] // LineConn이 쓰는 기록기 타입만 필요
+mod transcript;
+#[path = "../../src/auth/accounts.rs"]
+#[allow(dead_code)] // link 모듈의 이름 확인(valid_nick)만 필요
+mod accounts;
+#[path = "../../src/pake/spake2.rs"]
+#[allow(dead_code)] // 계정 모듈이 사용
+mod spake2;
+#[path = "../../src/federation/link.rs"]
+#[allow(dead_code)] // 메시지 해석과 다시 만들기만 사용
+mod link;
+
+use link::FedMessage;
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    if let Some(message) = FedMessage::parse(&text) {
+        // 해석한 메시지를 다시 줄로 만들면 같은 메시지가 나와야 함 (다른 서버로 다시 보낼 때 사용)
+        assert_eq!(FedMessage::parse(&message.to_line()), Some(message));
+    }
+});
@@ -0,0 +1,36 @@
+// fuzz/fuzz_targets/frame.rs
+// 소켓에서 받은 바이트를 줄(프레임)로 나누는 FrameReader 퍼징 (chatserver와 chatclient가 모든 연결에서 사용)
+// 첫 두 바이트로 최대 줄 길이와 한 번에 읽는 양을 바꿔서, 줄이 읽기 경계에 걸치는 경우도 만듦
+
+#![no_main]
+
+use std::sync::LazyLock;
+
+use libfuzzer_sys::fuzz_target;
+use tokio::io::BufReader;
+use tokio::runtime::Runtime;
+
+#[path = "../../src/proto/frame.rs"]
+mod frame;
+
+use frame::{Frame, FrameReader};
+
+static RUNTIME: LazyLock<Runtime> =
+    LazyLock::new(|| tokio::runtime::Builder::new_current_thread().build().expect("tokio 런타임 생성"));
+
+fuzz_target!(|data: &[u8]| {
+    let [max_len, capacity, input @ ..] = data else { return };
+    let max_len = *max_len as usize;
+    let mut reader = BufReader::with_capacity(*capacity as usize + 1, input);
+    let mut frames = FrameReader::new(max_len);
+
+    RUNTIME.block_on(async {
+        loop {
+            match frames.next(&mut reader).await.expect("메모리에서 읽기는 실패하지 않음") {
+                Frame::Line(line) => assert!(!line.contains('\n')),
+                Frame::TooLong(len) => assert!(len > max_len && len <= input.len()),
+                Frame::Eof => break,
+            }
+        }
+    });
+});
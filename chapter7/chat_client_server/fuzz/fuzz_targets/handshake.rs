@@ -0,0 +1,84 @@
+// fuzz/fuzz_targets/handshake.rs
+// 핸드셰이크에서 상대가 보낸 공개값을 해석하는 코드 퍼징 (모두 공격자가 정할 수 있는 바이트)
+//   ECDH 공개키 (PublicKey::from_sec1_bytes),  ML-KEM 캡슐화 키와 암호문,  SPAKE2 공유값과 키 확인 값,
+//   신원 공지, 키 패키지, prekey의 공개키/서명,  DM 세션을 시작할 때의 prekey ECDH
+// 첫 바이트로 어떤 디코더에 넣을지 고름
+
+#![no_main]
+
+use std::sync::LazyLock;
+
+use libfuzzer_sys::fuzz_target;
+use rand::rngs::OsRng; // ecdhkey 모듈이 crate::OsRng 로 사용
+
+#[path = "../../src/ecdh/ecdhkey.rs"]
+#[allow(dead_code)] // 세션 키 섞기는 쓰지 않음
+mod ecdhkey;
+#[path = "../../src/ecdh/secret.rs"]
+#[allow(dead_code)] // 유도한 키는 버림
+mod secret;
+#[path = "../../src/proto/packet.rs"]
+#[allow(dead_code)] // secret 모듈이 사용
+mod packet;
+#[path = "../../src/pq/mlkem.rs"]
+#[allow(dead_code)] // 고정 키와 고정 메시지를 쓰는 결정적 함수만 사용
+mod mlkem;
+#[path = "../../src/pake/spake2.rs"]
+#[allow(dead_code)] // 비밀번호에서 w 유도(Argon2)는 느리므로 쓰지 않음
+mod spake2;
+#[path = "../../src/identity/identity.rs"]
+#[allow(dead_code)] // 서명 만들기와 키 파일은 쓰지 않음
+mod identity;
+
+use ecdhkey::EcdhKey;
+use identity::PreKey;
+use spake2::{Role, Spake2};
+
+static DECAPS_KEY: LazyLock<mlkem::DecapsKey> = LazyLock::new(|| mlkem::keygen_internal(&[1; 32], &[2; 32]).0);
+static PREKEY: LazyLock<PreKey> = LazyLock::new(PreKey::generate);
+
+fuzz_target!(|data: &[u8]| {
+    let [selector, input @ ..] = data else { return };
+    match selector % 8 {
+        // 서버/클라이언트/연합 링크: 상대 ECDH 공개키
+        0 => {
+            let ecdh = EcdhKey::from_secret_bytes(&[3; 32]).expect("고정 비밀키");
+            let _ = ecdh.derive_aes_key(input);
+        }
+        1 => {
+            let ecdh = EcdhKey::from_secret_bytes(&[3; 32]).expect("고정 비밀키");
+            let _ = ecdh.derive_hybrid_aes_key(input, &[4; mlkem::SHARED_LEN]);
+        }
+        // 서버: 클라이언트의 ML-KEM 캡슐화 키 ("mlkem768=<캡슐화 키>")
+        2 => {
+            if let Ok((_, ct)) = mlkem::encapsulate_internal(input, &[5; 32]) {
+                assert_eq!(ct.len(), mlkem::CT_LEN);
+            }
+        }
+        // 클라이언트: 서버의 ML-KEM 암호문
+        3 => {
+            let _ = DECAPS_KEY.decapsulate(input);
+        }
+        // SPAKE2 로그인: 상대 공유값 ("SHARE <X>")과 키 확인 값 ("CONFIRM <cA>")
+        4 | 5 => {
+            let role = if selector % 8 == 4 { Role::Server } else { Role::Client };
+            let w = spake2::reduce_to_w(&[6; 32]);
+            let spake = Spake2::start(role, &w, b"alice", b"chatserver", b"aad").expect("SPAKE2 시작");
+            let (share, confirmation) = input.split_at(input.len().min(65));
+            if let Ok(keys) = spake.finish(share) {
+                assert!(keys.verify_peer(confirmation).is_err());
+            }
+        }
+        // 신원 공지 "<공개키> <서명>", 키 패키지와 prekey
+        6 => {
+            let (public_key, signature) = input.split_at(input.len().min(33));
+            let _ = identity::verify_announcement("alice", public_key, signature);
+            let _ = identity::verify_key_package("alice", public_key, signature, signature);
+            let _ = identity::verify_prekey("alice", public_key, signature, signature);
+        }
+        // DM 세션 시작: 상대 prekey와 ECDH
+        _ => {
+            let _ = PREKEY.diffie_hellman(input);
+        }
+    }
+});
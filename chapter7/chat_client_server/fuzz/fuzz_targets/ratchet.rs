@@ -0,0 +1,57 @@
+// fuzz/fuzz_targets/ratchet.rs
+// chatclient가 받은 귓속말 "@<보낸 사람> <Double Ratchet 메시지>" 퍼징
+//   헤더(래칫 공개키, 메시지 번호)는 복호화 전에 해석되고, 새 래칫 키면 DH 래칫과 건너뛴 키 계산까지 진행됨
+
+#![no_main]
+
+use std::sync::LazyLock;
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../src/ui/screen.rs"]
+#[macro_use]
+#[allow(dead_code)] // sessions 모듈의 say! 만 사용
+mod screen;
+#[path = "../../src/ecdh/secret.rs"]
+#[allow(dead_code)] // double_ratchet 모듈이 사용
+mod secret;
+#[path = "../../src/proto/packet.rs"]
+#[allow(dead_code)] // secret 모듈이 사용
+mod packet;
+#[path = "../../src/identity/identity.rs"]
+#[allow(dead_code)] // 서명과 키 파일은 쓰지 않음
+mod identity;
+#[path = "../../src/ratchet/double_ratchet.rs"]
+#[allow(dead_code)] // 저장된 세션 읽기/쓰기는 sessions 모듈을 거쳐서만 사용
+mod double_ratchet;
+#[path = "../../src/ratchet/sessions.rs"]
+#[allow(dead_code)] // 신원 공지에 싣는 prekey 공개키는 쓰지 않음
+mod sessions;
+
+use identity::{IdentityKey, PreKey};
+use sessions::DmSessions;
+
+static ALICE: LazyLock<IdentityKey> = LazyLock::new(IdentityKey::generate);
+static BOB: LazyLock<IdentityKey> = LazyLock::new(IdentityKey::generate);
+static ALICE_PREKEY: LazyLock<Vec<u8>> = LazyLock::new(|| PreKey::from_secret_bytes(&[1; 32]).expect("고정 prekey").public_key_bytes());
+static BOB_PREKEY: LazyLock<Vec<u8>> = LazyLock::new(|| PreKey::from_secret_bytes(&[2; 32]).expect("고정 prekey").public_key_bytes());
+
+fn sessions(nick: &str, identity: &IdentityKey, prekey: u8) -> DmSessions {
+    let prekey = PreKey::from_secret_bytes(&[prekey; 32]).expect("고정 prekey");
+    DmSessions::load(None, nick, identity.public_key_bytes(), prekey).expect("메모리 세션")
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = double_ratchet::Header::parse(data);
+
+    // 세션이 없는 상태(상대가 대화를 시작하는 경우)와, 이미 한 번 주고받은 세션 양쪽에서 받음
+    let alice_public = ALICE.public_key_bytes();
+    let mut fresh = sessions("bob", &BOB, 2);
+    let _ = fresh.decrypt("alice", &alice_public, Some(&ALICE_PREKEY), data);
+
+    let mut alice = sessions("alice", &ALICE, 1);
+    let mut bob = sessions("bob", &BOB, 2);
+    let first = alice.encrypt("bob", &BOB.public_key_bytes(), Some(&BOB_PREKEY), b"hi").expect("암호화");
+    bob.decrypt("alice", &alice_public, Some(&ALICE_PREKEY), &first).expect("정상 메시지 복호화");
+    let _ = bob.decrypt("alice", &alice_public, Some(&ALICE_PREKEY), data);
+});
@@ -0,0 +1,63 @@
+// fuzz/fuzz_targets/room_line.rs
+// chatclient가 서버에서 받은 방 메시지 "[<보낸 사람>]: <패킷> t=<시각>"과 세션 키로 암호화된 줄을 해석하는 코드 퍼징
+//   방 메시지 → 패킷 → CipherState 복호화 → 패딩 제거 → 봉투(JSON)
+//   세션 키로 암호화된 한 줄(로그인 응답, Room Key 전달)
+// 복호화는 임의의 입력으로 거의 통과하지 못하므로, 복호화 뒤의 디코더(패딩, 봉투)에는 입력을 직접 넣음
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zeroize::Zeroizing;
+
+#[path = "../../src/ecdh/secret.rs"]
+#[allow(dead_code)] // 고정 키로 만든 암호화 객체와 키 풀기(unwrap)만 사용
+mod secret;
+#[path = "../../src/proto/packet.rs"]
+#[allow(dead_code)] // 암호화(seal)는 쓰지 않음
+mod packet;
+#[path = "../../src/proto/cipherstate.rs"]
+#[allow(dead_code)] // 복호화(open)만 사용
+mod cipherstate;
+#[path = "../../src/proto/padding.rs"]
+#[allow(dead_code)] // 패딩 추가는 쓰지 않음
+mod padding;
+#[path = "../../src/proto/envelope.rs"]
+#[allow(dead_code)] // 봉투 생성과 시각 만들기는 쓰지 않음
+mod envelope;
+
+use cipherstate::CipherState;
+use envelope::Envelope;
+use padding::Padding;
+use secret::AesKey;
+
+fuzz_target!(|data: &[u8]| {
+    let key = AesKey::from_bytes(Zeroizing::new([7u8; 32]));
+    let cipher = key.cipher();
+    let text = String::from_utf8_lossy(data);
+
+    // 방 메시지
+    if let Some((_sender, content)) = envelope::parse_room_line(&text) {
+        let (packet, _ts) = envelope::split_timestamp(content);
+        let mut room = CipherState::new(&key, 1, 16).expect("고정 키로 상태 생성");
+        let _ = room.open(packet);
+    }
+    let _ = envelope::split_timestamp(&text);
+
+    // 세션 키로 암호화된 줄
+    let _ = packet::decode(&text);
+    let _ = packet::open(&cipher, &text);
+    let _ = AesKey::unwrap(&cipher, &text);
+
+    // 로그인 응답의 패딩 정책과, 복호화 뒤의 평문
+    if let Ok(policy) = text.parse::<Padding>() {
+        assert_eq!(policy.to_string().parse::<Padding>(), Ok(policy));
+    }
+    for policy in [Padding::None, Padding::PowerOfTwo, Padding::Block(16)] {
+        if let Ok(plaintext) = policy.unpad(data.to_vec()) {
+            assert!(plaintext.len() <= data.len());
+        }
+    }
+    if let Some(envelope) = Envelope::from_bytes(data) {
+        assert_eq!(Envelope::from_bytes(&envelope.to_bytes()), Some(envelope));
+    }
+});
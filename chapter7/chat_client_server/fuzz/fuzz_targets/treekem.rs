@@ -0,0 +1,68 @@
+// fuzz/fuzz_targets/treekem.rs
+// chatclient가 받은 그룹 메시지 "#<그룹> <TreeKEM 메시지>" 퍼징
+//   종류 확인(peek) → Welcome이면 그룹 참가, Commit이면 새 epoch 적용, 대화 메시지면 복호화
+// 초대 키는 공개되어 있고 멤버십 태그는 그룹 멤버라면 누구나 붙일 수 있으므로,
+// 첫 바이트에 따라 입력을 Welcome 내용으로 암호화하거나 Commit 본문에 태그를 붙여서 암호 검사 뒤의 해석까지 퍼징함
+
+#![no_main]
+
+use std::sync::LazyLock;
+
+use libfuzzer_sys::fuzz_target;
+use p256::SecretKey;
+
+#[path = "../../src/ecdh/secret.rs"]
+#[allow(dead_code)] // treekem 모듈이 사용
+mod secret;
+#[path = "../../src/proto/packet.rs"]
+#[allow(dead_code)] // secret 모듈이 사용
+mod packet;
+#[path = "../../src/group/treekem.rs"]
+#[allow(dead_code)] // 받는 쪽 함수만 사용
+mod treekem;
+
+use treekem::{Group, Peek, Proposal};
+
+// bob의 초대 키와, alice가 bob을 추가한 뒤 bob이 참가한 그룹 (실행마다 복사해서 사용)
+static INIT_KEY: LazyLock<SecretKey> = LazyLock::new(|| SecretKey::from_slice(&[9; 32]).expect("고정 초대 키"));
+static BOB_GROUP: LazyLock<Group> = LazyLock::new(|| {
+    let alice = Group::create("dev", "alice");
+    let add = Proposal::Add { nick: "bob".to_string(), init_key: treekem::public_bytes(&INIT_KEY) };
+    let output = alice.commit(&[add]).expect("멤버 추가");
+    Group::join(&output.welcomes[0].1, "bob", &INIT_KEY).expect("Welcome으로 참가")
+});
+
+fuzz_target!(|data: &[u8]| {
+    let [selector, input @ ..] = data else { return };
+    let mut group = BOB_GROUP.clone();
+    match selector % 3 {
+        // 받은 메시지 그대로
+        0 => {
+            match treekem::peek(input) {
+                Some(Peek::Welcome { .. }) => {
+                    let _ = Group::join(input, "bob", &INIT_KEY);
+                }
+                Some(Peek::Commit { .. }) => {
+                    let _ = group.process_commit(input);
+                }
+                Some(Peek::App) => {
+                    let _ = group.open("alice", input);
+                }
+                None => {}
+            }
+        }
+        // 초대 키로 암호화된 Welcome 내용
+        1 => {
+            let welcome = treekem::seal_welcome("bob", &treekem::public_bytes(&INIT_KEY), input).expect("암호화");
+            // 참가에 성공했으면, 받은 트리로 내 Commit도 만들 수 있어야 함 (/group rekey)
+            if let Ok(joined) = Group::join(&welcome, "bob", &INIT_KEY) {
+                let _ = joined.commit(&[]);
+            }
+        }
+        // 멤버십 태그가 맞는 Commit 본문
+        _ => {
+            let commit = group.tag_commit(input);
+            let _ = group.process_commit(&commit);
+        }
+    }
+});
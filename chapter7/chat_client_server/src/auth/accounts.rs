@@ -0,0 +1,158 @@
+// src/auth/accounts.rs
+// 이 모듈은 닉네임별 비밀번호(Argon2id 해시)를 저장하는 계정 파일의 관리를 담당합니다.
+// 파일 형식: 한 줄에 `닉네임:$argon2id$...` (PHC 문자열), '#'으로 시작하는 줄은 주석
+// PAKE 로그인 계정은 `닉네임:spake2$<솔트>$<w>` (Base64) 형식으로 SPAKE2 검증값만 저장
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use argon2::{
+    password_hash::{rand_core::{OsRng, RngCore}, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use base64::{engine::general_purpose, Engine as _};
+use zeroize::Zeroizing;
+
+use crate::spake2;
+
+const MAX_NICK_LEN: usize = 32;
+const PAKE_PREFIX: &str = "spake2$";
+
+// 닉네임 규칙: 1~32자의 영문/숫자/'_'/'-'
+pub fn valid_nick(nick: &str) -> bool {
+    !nick.is_empty()
+        && nick.len() <= MAX_NICK_LEN
+        && nick.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+pub struct AccountStore {
+    path: PathBuf,
+    accounts: BTreeMap<String, String>,
+}
+
+impl AccountStore {
+    // 계정 파일을 읽어옴. 파일이 없으면 빈 저장소로 시작
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut accounts = BTreeMap::new();
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("계정 파일을 읽을 수 없습니다: {}", e)),
+        };
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (nick, hash) = line
+                .split_once(':')
+                .ok_or_else(|| format!("계정 파일 {}번째 줄의 형식이 잘못되었습니다.", i + 1))?;
+            accounts.insert(nick.to_string(), hash.to_string());
+        }
+
+        Ok(Self { path: path.to_path_buf(), accounts })
+    }
+
+    // 임시 파일에 쓴 뒤 이름을 바꿔서 중간에 깨진 파일이 남지 않도록 저장
+    pub fn save(&self) -> Result<(), String> {
+        let mut text = String::from("# chatserver accounts (nick:argon2id-hash 또는 nick:spake2$salt$w)\n");
+        for (nick, hash) in &self.accounts {
+            text.push_str(&format!("{}:{}\n", nick, hash));
+        }
+
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, text).map_err(|e| format!("계정 파일 저장 실패: {}", e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600));
+        }
+        std::fs::rename(&tmp, &self.path).map_err(|e| format!("계정 파일 저장 실패: {}", e))
+    }
+
+    pub fn is_registered(&self, nick: &str) -> bool {
+        self.accounts.contains_key(nick)
+    }
+
+    // pake가 true이면 비밀번호 해시 대신 SPAKE2 검증값을 저장 (PAKE 로그인 전용 계정)
+    pub fn add(&mut self, nick: &str, password: &str, pake: bool) -> Result<(), String> {
+        if !valid_nick(nick) {
+            return Err(format!("사용할 수 없는 닉네임입니다: {}", nick));
+        }
+        if self.is_registered(nick) {
+            return Err(format!("이미 등록된 닉네임입니다: {}", nick));
+        }
+        self.accounts.insert(nick.to_string(), make_entry(password, pake)?);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, nick: &str) -> Result<(), String> {
+        self.accounts
+            .remove(nick)
+            .map(|_| ())
+            .ok_or_else(|| format!("등록되지 않은 닉네임입니다: {}", nick))
+    }
+
+    pub fn set_password(&mut self, nick: &str, password: &str, pake: bool) -> Result<(), String> {
+        let hash = make_entry(password, pake)?;
+        match self.accounts.get_mut(nick) {
+            Some(entry) => {
+                *entry = hash;
+                Ok(())
+            }
+            None => Err(format!("등록되지 않은 닉네임입니다: {}", nick)),
+        }
+    }
+
+    // 비밀번호 확인. Argon2 연산은 수십 ms 걸리므로 비동기 코드에서는 spawn_blocking 안에서 호출할 것
+    pub fn verify(&self, nick: &str, password: &str) -> bool {
+        let Some(hash) = self.accounts.get(nick) else {
+            return false;
+        };
+        if hash.starts_with(PAKE_PREFIX) {
+            return false;
+        }
+        match PasswordHash::new(hash) {
+            Ok(parsed) => Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    // PAKE 계정의 (솔트, w) 반환. 일반 비밀번호 계정이거나 없는 닉네임이면 None
+    pub fn pake_verifier(&self, nick: &str) -> Option<(Vec<u8>, Zeroizing<[u8; 32]>)> {
+        let entry = self.accounts.get(nick)?.strip_prefix(PAKE_PREFIX)?;
+        let (salt, w) = entry.split_once('$')?;
+        let salt = general_purpose::STANDARD.decode(salt).ok()?;
+        let w = Zeroizing::new(general_purpose::STANDARD.decode(w).ok()?);
+        Some((salt, Zeroizing::new(w.as_slice().try_into().ok()?)))
+    }
+}
+
+fn make_entry(password: &str, pake: bool) -> Result<String, String> {
+    if password.is_empty() {
+        return Err("비밀번호가 비어 있습니다.".to_string());
+    }
+    if pake {
+        let mut salt = [0u8; spake2::SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let w = spake2::password_to_w(password, &salt)?;
+        Ok(format!(
+            "{}{}${}",
+            PAKE_PREFIX,
+            general_purpose::STANDARD.encode(salt),
+            general_purpose::STANDARD.encode(w.as_slice())
+        ))
+    } else {
+        hash_password(password)
+    }
+}
+
+// Argon2id(기본 파라미터)와 랜덤 솔트로 PHC 형식의 해시 문자열 생성
+fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| format!("비밀번호 해시 실패: {}", e))
+}
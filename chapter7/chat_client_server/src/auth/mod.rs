@@ -0,0 +1 @@
+pub mod accounts;
\ No newline at end of file
@@ -1,120 +1,1186 @@
 // src/bin/client.rs
 
-use tokio::net::TcpStream;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use aes_gcm::{aead::{Aead, KeyInit}, Aes256Gcm, Nonce};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::net::{TcpStream, UnixStream};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader, ReadHalf, WriteHalf};
 use base64::{engine::general_purpose, Engine as _};
-use rand::{rngs::OsRng, RngCore};
+use clap::Parser;
+use rand::{rngs::OsRng, Rng};
+use zeroize::Zeroizing;
 
+// 출력 행 수를 세는 say! 매크로를 다른 모듈보다 먼저 불러옵니다.
+#[path = "../ui/screen.rs"]
+#[macro_use]
+mod screen;
+#[path = "../ui/markdown.rs"]
+mod markdown;
+#[path = "../ui/mentions.rs"]
+mod mentions;
+#[path = "../ui/timeline.rs"]
+mod timeline;
 // ecdh.rs 파일을 모듈로 불러옵니다.
 #[path = "../ecdh/ecdhkey.rs"]
+#[allow(dead_code)] // 고정된 비밀키로 만들기(from_secret_bytes)는 chatconform에서만 사용
 mod ecdhkey;
 //mod ecdh;
 //use super::ecdh::ecdhkey;
+#[path = "../ecdh/secret.rs"]
+#[allow(dead_code)] // Room Key 생성/전달은 chatserver에서만 사용
+mod secret;
+#[path = "../proto/conn.rs"]
+mod conn;
+#[path = "../proto/frame.rs"]
+mod frame;
+#[path = "../proto/envelope.rs"]
+mod envelope;
+#[path = "../proto/packet.rs"]
+mod packet;
+#[path = "../proto/transcript.rs"]
+#[allow(dead_code)] // 기록 파일 읽기(load)는 chatconform에서만 사용
+mod transcript;
+#[path = "../proto/cipherstate.rs"]
+#[allow(dead_code)] // 서버 자신의 발신자 번호는 chatserver에서만 사용
+mod cipherstate;
+#[path = "../proto/padding.rs"]
+mod padding;
+#[path = "../ratchet/double_ratchet.rs"]
+mod double_ratchet;
+#[path = "../ratchet/sessions.rs"]
+mod sessions;
+#[path = "../group/treekem.rs"]
+mod treekem;
+#[path = "../group/groups.rs"]
+mod groups;
+#[path = "../history/store.rs"]
+mod store;
+#[path = "../pq/mlkem.rs"]
+#[allow(dead_code)] // 캡슐화는 chatserver에서만 사용
+mod mlkem;
+#[path = "../transport/tls.rs"]
+#[allow(dead_code)] // 서버 쪽 수락기(acceptor)와 인증서 생성은 chatserver에서만 사용
+mod tls;
+#[path = "../transport/quic.rs"]
+#[allow(dead_code)] // 스트림 받기(accept_stream)는 chatserver에서만 사용
+mod quic;
+#[path = "../proto/noise.rs"]
+#[allow(dead_code)] // 접속 받기(accept) 쪽은 chatserver에서만 사용
+mod noise;
+#[path = "../pake/spake2.rs"]
+#[allow(dead_code)] // 서버 역할(B)은 chatserver에서만 사용
+mod spake2;
+#[path = "../identity/identity.rs"]
+mod identity;
+#[path = "../identity/fingerprint.rs"]
+mod fingerprint;
+#[path = "../identity/known_peers.rs"]
+mod known_peers;
+#[path = "../room/metadata.rs"]
+#[allow(dead_code)] // 방 설정 파일(Rooms)은 chatserver에서만 사용
+mod metadata;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut socket = TcpStream::connect("127.0.0.1:8080").await?;
-    println!("connecting...");
+use cipherstate::CipherState;
+use conn::LineConn;
+use envelope::{Envelope, Kind};
+use frame::Frame;
+use groups::Groups;
+use identity::{IdentityKey, PreKey};
+use known_peers::{KnownPeers, Observation};
+use mentions::{Highlight, Mentions};
+use metadata::RoomInfo;
+use noise::StaticKey;
+use padding::Padding;
+use quic::QuicClient;
+use secret::AesKey;
+use sessions::DmSessions;
+use spake2::{Role, Spake2};
+use store::{History, Record};
+use timeline::Timeline;
+use transcript::{Dir, Layer, Recorder};
+use treekem::Proposal;
 
-    let (reader, mut writer) = socket.split();
-    let mut reader = BufReader::new(reader);
+// 서버에서 받을 한 줄의 최대 길이
+const MAX_LINE_BYTES: usize = 1024 * 1024;
+// SPAKE2에서 서버 쪽 신원(B)으로 쓰는 값 (chatserver와 같아야 함)
+const SPAKE2_SERVER_ID: &[u8] = b"chatserver";
+// 하이브리드 키 교환에서 ML-KEM 값 앞에 붙는 표시 (chatserver와 같아야 함)
+const MLKEM_TAG: &str = "mlkem768=";
+// 채팅 메시지와 구분되는 신원 공지 메시지의 머리말 (Room Key로 암호화되어 전송됨)
+const IDENT_PREFIX: &str = "\u{0}IDENT ";
+const ROOM_USAGE: &str = "/edit <ID|last> <메시지> | /delete <ID|last> | /react <ID|last> <이모지> | /receipts <ID|last>";
+const GROUP_USAGE: &str = "/group new|list|add|remove|update <그룹> [닉네임]";
+const HISTORY_USAGE: &str = "/history [방] [개수] | /search <텍스트>";
+const TOPIC_USAGE: &str = "/topic [새 토픽|-]";
+const COMPOSE_USAGE: &str = "여러 줄: \"\"\" 또는 ```[언어] 로 시작해서 같은 표시만 있는 줄로 끝내기";
+// 여러 줄 입력의 시작과 끝 표시 (코드 블록 표시는 본문에 남겨서 받는 쪽에서 코드로 그림)
+const TEXT_FENCE: &str = "\"\"\"";
+const CODE_FENCE: &str = "```";
+// 대화 기록에서 채팅방의 이름 (귓속말은 "@<상대>", 그룹은 "#<그룹>")
+const MAIN_ROOM: &str = "room";
+// /history 에서 개수를 생략했을 때와 /search 결과의 최대 개수
+const HISTORY_DEFAULT: usize = 20;
+// 받았다는 확인을 모아서 보내는 간격과, 한 번에 확인을 보낼 최대 인원
+// (확인도 귓속말이라 서버의 전송 한도를 쓰므로, 내 메시지를 보낼 몫이 남도록 한도보다 적게 보냄)
+const RECEIPT_INTERVAL: Duration = Duration::from_secs(2);
+const RECEIPT_MAX_SENDERS: usize = 4;
+// QUIC 연결이 끊겼을 때 처음 다시 접속하기 전의 대기 시간 (시도할 때마다 두 배)과 최대 시도 횟수
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_ATTEMPTS: u32 = 5;
+const SEARCH_LIMIT: usize = 50;
 
-    // ==========================================
-    // [ECDH 핸드셰이크 단계]
-    // ==========================================
-    
-    // 1. 서버 공개키 수신
-    let mut server_pub_line = String::new();
-    reader.read_line(&mut server_pub_line).await?;
-    let server_pub_bytes = general_purpose::STANDARD.decode(server_pub_line.trim())?;
+#[derive(Parser, Debug)]
+#[command(name = "chatclient", about = "ECDH 키 교환 + AES-GCM 채팅 클라이언트")]
+struct Args {
+    /// 접속할 서버 주소
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    server: String,
 
-    // 2. 내 임시 키 쌍 생성 및 공개키 전송
-    let client_ecdh = ecdhkey::EcdhKey::create();
-    let client_pub_b64 = general_purpose::STANDARD.encode(client_ecdh.public_key_bytes());
-    writer.write_all(format!("{}\n", client_pub_b64).as_bytes()).await?;
+    /// TCP 대신 서버의 유닉스 도메인 소켓으로 접속
+    #[arg(long)]
+    unix: Option<PathBuf>,
 
-    // 3. 세션 키 유도 (핸드셰이크 암호화용)
-    let session_key = client_ecdh.derive_aes_key(&server_pub_bytes)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    let session_cipher = Aes256Gcm::new(&session_key.into());
-
-    // 4. 암호화된 Room Key 수신 및 복호화
-    let mut room_key_line = String::new();
-    reader.read_line(&mut room_key_line).await?;
-    let room_key_packet = general_purpose::STANDARD.decode(room_key_line.trim())?;
-    
-    let (nonce_bytes, ciphertext) = room_key_packet.split_at(12);
-    let room_key_bytes = session_cipher.decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
-        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "Room Key 복호화 실패"))?;
-    
-    // 5. 채팅용 암호화 객체 생성
-    // (이제부터 이 키로 모든 채팅 메시지를 암호화/복호화합니다)
-    let room_cipher = Aes256Gcm::new_from_slice(&room_key_bytes)
-        .map_err(|_| std::io::Error::new(std::io::ErrorKind::Other, "Invalid Key Size"))?;
+    /// TCP 대신 QUIC으로 접속 (--server 는 서버의 --quic 주소, --tls-ca 또는 --tls-pin 필요)
+    #[arg(long, conflicts_with = "unix")]
+    quic: bool,
 
-    println!("✅ 보안 핸드셰이크 성공! 안전한 채팅을 시작합니다.");
+    /// 사용할 닉네임 (생략하면 guest-XXXX)
+    #[arg(long)]
+    nick: Option<String>,
+
+    /// 등록된 계정으로 로그인 (비밀번호를 입력받음)
+    #[arg(long)]
+    login: bool,
+
+    /// SPAKE2로 로그인 (비밀번호가 서버로 전달되지 않고, 서버도 함께 인증함)
+    #[arg(long, conflicts_with = "login")]
+    pake: bool,
+
+    /// 장기 신원 키 파일 (없으면 새로 생성). 확인한 사용자 목록은 같은 이름의 .peers 파일에 저장
+    /// 생략하면 실행할 때마다 새 신원 키를 사용
+    #[arg(long)]
+    identity: Option<PathBuf>,
+
+    /// TLS로 접속하고, 이 CA 인증서(PEM)로 서버 인증서를 검증
+    #[arg(long, conflicts_with = "tls_pin")]
+    tls_ca: Option<PathBuf>,
+
+    /// TLS로 접속하고, 서버 인증서가 이 인증서(PEM)와 같을 때만 허용 (자체 서명 인증서용)
+    #[arg(long)]
+    tls_pin: Option<PathBuf>,
+
+    /// 인증서 검증에 쓸 서버 이름 (생략하면 --server 의 호스트 부분)
+    #[arg(long)]
+    tls_name: Option<String>,
+
+    /// 전송 보안 핸드셰이크 방식 (서버의 --handshake 와 같아야 함)
+    #[arg(long, value_enum, default_value_t = HandshakeMode::Ecdh)]
+    handshake: HandshakeMode,
+
+    /// 서버의 Noise 정적 공개키 (Base64). 주면 Noise_IK, 생략하면 Noise_XX로 접속
+    #[arg(long, requires = "handshake")]
+    noise_server_key: Option<String>,
+
+    /// 받은 메시지를 받았다는 확인을 보낸 사람에게 모아서 보냄
+    #[arg(long)]
+    delivery_receipts: bool,
+
+    /// 받은 메시지를 읽었다는 확인을 보낸 사람에게 보냄
+    #[arg(long)]
+    read_receipts: bool,
+
+    /// ECDH에 ML-KEM-768을 더한 하이브리드(양자 내성) 키 교환 사용 (--handshake ecdh 전용)
+    #[arg(long)]
+    pq: bool,
+
+    /// 주고받은 메시지를 이 파일(SQLite)에 기록 암호로 암호화해서 보관 (/history, /search 로 조회)
+    #[arg(long)]
+    history: Option<PathBuf>,
+
+    /// @닉네임 멘션처럼 강조하고 알릴 관심 키워드 (여러 번 지정 가능, 대소문자 구분 없음)
+    #[arg(long = "keyword")]
+    keywords: Vec<String>,
+
+    /// 주고받은 프레임을 이 파일에 기록 (chatconform replay 로 서버 회귀 테스트에 사용)
+    /// 방 메시지와 로그인 요청/응답은 평문으로 남으므로 테스트 세션에서만 사용. ECDH 핸드셰이크와 닉네임 접속만 지원
+    #[arg(long, conflicts_with_all = ["login", "pake", "pq"])]
+    record: Option<PathBuf>,
+
+    /// 멘션, 키워드, 귓속말이 오면 실행할 명령 (sh -c 로 실행, 내용은 환경 변수 CHAT_KIND, CHAT_ROOM, CHAT_SENDER, CHAT_BODY)
+    /// 예: --notify 'notify-send "$CHAT_SENDER" "$CHAT_BODY"'
+    #[arg(long)]
+    notify: Option<String>,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum HandshakeMode {
+    Ecdh,
+    Noise,
+}
+
+// TCP 소켓과 TLS 스트림을 같은 타입으로 다루기 위한 트레이트
+trait ChatStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ChatStream for T {}
+
+// 내 신원 키와, 이번 세션에서 만난 사용자들의 신원 키
+struct Identities {
+    nick: String,
+    key: IdentityKey,
+    known: KnownPeers,
+    session_peers: BTreeMap<String, Vec<u8>>,
+    session_number: String,
+    // 상대별 귓속말(Double Ratchet) 세션
+    dm: DmSessions,
+    // 참여 중인 그룹(TreeKEM)과, 이번 세션에서 받은 다른 사용자들의 서명된 그룹 초대 키
+    groups: Groups,
+    key_packages: BTreeMap<String, Vec<u8>>,
+}
+
+impl Identities {
+    // "hello"는 처음 접속했을 때, "reply"는 다른 사람의 hello에 답할 때 사용
+    // 뒤에 그룹 초대 키, DM prekey와 각각의 서명을 덧붙임 (예전 클라이언트는 앞의 세 필드만 읽음)
+    fn announcement(&self, kind: &str) -> String {
+        let init_key = self.groups.init_public_key();
+        let prekey = self.dm.prekey_public();
+        format!(
+            "{}{} {} {} {} {} {} {}",
+            IDENT_PREFIX,
+            kind,
+            general_purpose::STANDARD.encode(self.key.public_key_bytes()),
+            general_purpose::STANDARD.encode(self.key.sign_announcement(&self.nick)),
+            general_purpose::STANDARD.encode(&init_key),
+            general_purpose::STANDARD.encode(self.key.sign_key_package(&self.nick, &init_key)),
+            general_purpose::STANDARD.encode(&prekey),
+            general_purpose::STANDARD.encode(self.key.sign_prekey(&self.nick, &prekey)),
+        )
+    }
+
+    // 받은 신원 공지를 검증하고 기록. 답장을 보내야 하면 보낼 공지를 돌려줌
+    fn handle_announcement(&mut self, sender: &str, payload: &str) -> Option<String> {
+        let mut fields = payload.split(' ');
+        let (Some(kind), Some(public_key), Some(signature)) = (fields.next(), fields.next(), fields.next()) else {
+            return None;
+        };
+        let (Ok(public_key), Ok(signature)) = (
+            general_purpose::STANDARD.decode(public_key),
+            general_purpose::STANDARD.decode(signature),
+        ) else {
+            return None;
+        };
+        if let Err(e) = identity::verify_announcement(sender, &public_key, &signature) {
+            say!("⚠️ {}의 신원 공지를 무시합니다: {}", sender, e);
+            return None;
+        }
+
+        if let (Some(init_key), Some(init_signature)) = (fields.next(), fields.next())
+            && let (Ok(init_key), Ok(init_signature)) = (
+                general_purpose::STANDARD.decode(init_key),
+                general_purpose::STANDARD.decode(init_signature),
+            )
+        {
+            match identity::verify_key_package(sender, &public_key, &init_key, &init_signature) {
+                Ok(()) => {
+                    self.key_packages.insert(sender.to_string(), init_key);
+                }
+                Err(e) => say!("⚠️ {}의 그룹 초대 키를 무시합니다: {}", sender, e),
+            }
+        }
+        let prekey = match (fields.next(), fields.next()) {
+            (Some(prekey), Some(prekey_signature)) => match (
+                general_purpose::STANDARD.decode(prekey),
+                general_purpose::STANDARD.decode(prekey_signature),
+            ) {
+                (Ok(prekey), Ok(prekey_signature)) => match identity::verify_prekey(sender, &public_key, &prekey, &prekey_signature) {
+                    Ok(()) => Some(prekey),
+                    Err(e) => {
+                        say!("⚠️ {}의 DM prekey를 무시합니다: {}", sender, e);
+                        None
+                    }
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let first_in_session = self.session_peers.insert(sender.to_string(), public_key.clone()).is_none();
+        match self.known.observe(sender, &public_key) {
+            Ok(Observation::New) => {
+                say!("🔑 {}의 신원 키를 처음 받았습니다. /fingerprint 로 안전 번호를 비교한 뒤 /verify {} 하세요.", sender, sender);
+            }
+            Ok(Observation::Changed { was_verified: true }) => {
+                say!("🚨🚨🚨 ========================================================");
+                say!("🚨 경고: 확인된 사용자 {}의 신원 키가 바뀌었습니다!", sender);
+                say!("🚨 상대방이 기기를 바꿨을 수도 있지만, 누군가 {}를 사칭하고 있을 수도 있습니다.", sender);
+                say!("🚨 다른 경로로 안전 번호를 다시 비교하기 전까지 민감한 대화를 하지 마세요.");
+                say!("🚨🚨🚨 ========================================================");
+            }
+            Ok(Observation::Changed { was_verified: false }) => {
+                say!("⚠️ {}의 신원 키가 바뀌었습니다. /fingerprint 로 안전 번호를 확인하세요.", sender);
+            }
+            Ok(Observation::Unchanged { .. }) => {}
+            Err(e) => say!("⚠️ {}", e),
+        }
+        // prekey는 방금 기록한 신원 키에 딸린 값으로 저장 (신원 키가 바뀌면 observe가 예전 prekey를 지움)
+        if let Some(prekey) = prekey
+            && let Err(e) = self.known.set_prekey(sender, &prekey)
+        {
+            say!("⚠️ {}", e);
+        }
+
+        (kind == "hello" && first_in_session).then(|| self.announcement("reply"))
+    }
+
+    // 상대의 신원 키: 이번 세션에서 받은 것, 없으면 예전에 저장해 둔 것 (접속하지 않은 사람과 귓속말할 때)
+    fn peer_key(&self, peer: &str) -> Option<Vec<u8>> {
+        self.session_peers.get(peer).or_else(|| self.known.get(peer).map(|p| &p.public_key)).cloned()
+    }
+
+    // 상대의 DM prekey (신원 공지에서 서명을 확인한 것. 예전 클라이언트는 보내지 않으므로 없을 수 있음)
+    fn peer_prekey(&self, peer: &str) -> Option<Vec<u8>> {
+        self.known.get(peer).and_then(|p| p.prekey.clone())
+    }
+
+    // 귓속말 암호화. 신원 키를 받은 적이 있는 사람에게만 보낼 수 있음
+    // (접속 중이 아니면 서버가 오프라인 큐에 보관했다가 다음 로그인 때 전달)
+    fn seal_dm(&mut self, peer: &str, envelope: &Envelope) -> Result<String, String> {
+        let peer_key = self.peer_key(peer).ok_or_else(|| format!("{}의 신원 키를 받은 적이 없습니다.", peer))?;
+        let message = self.dm.encrypt(peer, &peer_key, self.peer_prekey(peer).as_deref(), &envelope.to_bytes())?;
+        Ok(format!("@{} {}", peer, general_purpose::STANDARD.encode(message)))
+    }
+
+    // 귓속말 평문 (봉투이거나, 예전 클라이언트가 보낸 텍스트)
+    fn open_dm(&mut self, sender: &str, payload: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+        let peer_key = self.peer_key(sender).ok_or_else(|| format!("{}의 신원 키를 아직 받지 못했습니다.", sender))?;
+        let message = general_purpose::STANDARD
+            .decode(payload)
+            .map_err(|_| "귓속말 형식이 잘못되었습니다.".to_string())?;
+        self.dm.decrypt(sender, &peer_key, self.peer_prekey(sender).as_deref(), &message)
+    }
+
+    // 받은 메시지에 대한 수신/읽음 확인을 원래 보낸 사람에게 귓속말로 보낼 줄 (신원 키를 모르면 None)
+    fn receipt_line(&mut self, to: &str, targets: Vec<String>, read: bool) -> Option<String> {
+        self.peer_key(to)?;
+        let envelope = Envelope::new(&self.nick, Kind::Receipt { targets, read });
+        self.seal_dm(to, &envelope).map_err(|e| say!("⚠️ {}에게 수신 확인을 보내지 못했습니다: {}", to, e)).ok()
+    }
+
+    // /group 명령 처리. 서버에 보낼 그룹 메시지를 돌려줌
+    fn group_command(&mut self, words: &[&str]) -> Result<Option<String>, String> {
+        let (name, proposals) = match words {
+            ["new", name] => {
+                validate_group_name(name)?;
+                self.groups.create(name)?;
+                say!("👥 {} 그룹을 만들었습니다. /group add {} <닉네임> 으로 멤버를 추가하세요.", name, name);
+                return Ok(None);
+            }
+            ["list"] => {
+                self.groups.print();
+                return Ok(None);
+            }
+            ["add", name, peer] => {
+                let init_key = self
+                    .key_packages
+                    .get(*peer)
+                    .ok_or_else(|| format!("이번 세션에서 {}의 그룹 초대 키를 받지 못했습니다.", peer))?;
+                (name, vec![Proposal::Add { nick: peer.to_string(), init_key: init_key.clone() }])
+            }
+            ["remove", name, peer] => (name, vec![Proposal::Remove { nick: peer.to_string() }]),
+            ["update", name] => (name, vec![]),
+            _ => return Err(GROUP_USAGE.to_string()),
+        };
+        let commit = self.groups.propose(name, &proposals)?;
+        Ok(Some(group_line(name, &commit)))
+    }
+
+    fn print_fingerprints(&self) {
+        let my_key = self.key.public_key_bytes();
+        say!("🔐 서버와의 세션 번호: {}", self.session_number);
+        say!("🔐 내 지문 ({}): {}", self.nick, fingerprint::fingerprint(&self.nick, &my_key));
+        if self.session_peers.is_empty() {
+            say!("   (아직 신원 키를 받은 사용자가 없습니다)");
+        }
+        for (peer, peer_key) in &self.session_peers {
+            let verified = self.known.get(peer).is_some_and(|p| p.verified && &p.public_key == peer_key);
+            say!(
+                "   {} {}: {}",
+                if verified { "✅" } else { "❔" },
+                peer,
+                fingerprint::safety_number(&self.nick, &my_key, peer, peer_key)
+            );
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let nick = args.nick.clone().unwrap_or_else(|| format!("guest-{:04}", OsRng.gen_range(0..10000)));
+    let password = if args.login || args.pake {
+        Some(Zeroizing::new(rpassword::prompt_password(format!("{} 비밀번호: ", nick))?))
+    } else {
+        None
+    };
+    let history = match &args.history {
+        Some(path) => Some(open_history(path)?),
+        None => None,
+    };
+
+    // QUIC이면 연결이 끊겼을 때 같은 엔드포인트로 다시 접속 (지난 접속의 세션 티켓으로 TLS 세션을 재개)
+    let quic_client = if args.quic {
+        let tls_config = match (&args.tls_ca, &args.tls_pin) {
+            (Some(ca), _) => tls::client_config_with_ca(ca)?,
+            (None, Some(pin)) => tls::client_config_with_pin(pin)?,
+            (None, None) => return Err("QUIC 접속에는 --tls-ca 또는 --tls-pin 이 필요합니다.".into()),
+        };
+        let server = tokio::net::lookup_host(&args.server)
+            .await?
+            .next()
+            .ok_or_else(|| format!("서버 주소를 찾을 수 없습니다: {}", args.server))?;
+        let name = args.tls_name.as_deref().unwrap_or_else(|| tls::host(&args.server));
+        Some(QuicClient::new(server, name, tls_config)?)
+    } else {
+        None
+    };
+    let recorder = match &args.record {
+        Some(path) => {
+            if args.handshake != HandshakeMode::Ecdh {
+                return Err("--record 는 ECDH 핸드셰이크에서만 사용할 수 있습니다.".into());
+            }
+            Some((path, Recorder::create(path)?))
+        }
+        None => None,
+    };
+    let Session { mut conn, mut room, mut padding, binding, mut quic_connection } =
+        open_session(&args, &nick, password.as_ref(), quic_client.as_ref(), recorder).await?;
+
+    // 7. 신원 키와 DM prekey 준비 및 방에 신원 공지
+    let (key, prekey) = match &args.identity {
+        Some(path) => (IdentityKey::load_or_create(path)?, PreKey::load_or_create(&path.with_extension("prekey"))?),
+        None => (IdentityKey::generate(), PreKey::generate()),
+    };
+    let dm = DmSessions::load(args.identity.as_ref().map(|p| p.with_extension("ratchet")), &nick, key.public_key_bytes(), prekey)?;
+    let known = KnownPeers::load(args.identity.as_ref().map(|p| p.with_extension("peers")))?;
+    let mut identities = Identities {
+        nick: nick.clone(),
+        key,
+        known,
+        session_peers: BTreeMap::new(),
+        session_number: fingerprint::session_number(&binding),
+        dm,
+        groups: Groups::new(&nick),
+        key_packages: BTreeMap::new(),
+    };
+    send_room(&mut conn, &mut room, padding, identities.announcement("hello").as_bytes()).await?;
 
-    
     // ==========================================
     // [메인 채팅 루프]
     // ==========================================
     let mut stdin = BufReader::new(tokio::io::stdin());
-    let mut socket_line = String::new();
+    let highlight = Highlight::new(&nick, &args.keywords);
+    let mut timeline = Timeline::new(&highlight);
+    let mut mentions = Mentions::new(highlight.clone(), args.notify.clone());
+    // 이미 받은 귓속말 ID (오프라인 큐로 같은 메시지가 다시 와도 한 번만 보여줌)
+    let mut seen_dms = HashSet::new();
     let mut input_line = String::new();
+    let mut compose: Option<Compose> = None;
+    // 서버가 마지막으로 알려준 방 정보 (토픽 등)
+    let mut room_info: Option<RoomInfo> = None;
+    // 아직 받았다는 확인을 보내지 않은 메시지 (보낸 사람별 ID, --delivery-receipts 일 때만 모음)
+    let mut undelivered: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut receipt_timer = tokio::time::interval(RECEIPT_INTERVAL);
 
     loop {
         tokio::select! {
             // 메시지 수신 (Room Key로 복호화)
-            result = reader.read_line(&mut socket_line) => {
-                if result? == 0 { break; }
+            result = conn.recv_frame() => {
+                let frame = match (result, &quic_client) {
+                    (Ok(frame), _) => frame,
+                    (Err(e), None) => return Err(e.into()),
+                    (Err(e), Some(_)) => {
+                        say!("⚠️ {}", e);
+                        Frame::Eof
+                    }
+                };
+                let socket_line = match frame {
+                    Frame::Line(line) => line,
+                    Frame::TooLong(_) => continue,
+                    Frame::Eof => {
+                        let Some(client) = &quic_client else { break };
+                        // 다시 접속하면 발신자 번호와 Room Key가 새로 정해지므로 접속에 딸린 것을 모두 바꾸고, 신원을 다시 공지함
+                        let session = reconnect(&args, &nick, password.as_ref(), client).await?;
+                        if args.record.is_some() {
+                            say!("📼 다시 접속한 뒤의 프레임은 기록하지 않습니다.");
+                        }
+                        Session { conn, room, padding, quic_connection, .. } = session;
+                        identities.session_number = fingerprint::session_number(&session.binding);
+                        send_room(&mut conn, &mut room, padding, identities.announcement("hello").as_bytes()).await?;
+                        continue;
+                    }
+                };
 
-                if let Some((sender, content)) = parse_message(&socket_line) {
-                    if let Ok(data) = general_purpose::STANDARD.decode(content.trim()) {
-                        if data.len() > 12 {
-                            let (nonce, cipher) = data.split_at(12);
-                            match room_cipher.decrypt(Nonce::from_slice(nonce), cipher) {
-                                Ok(pt) => println!("{}: {}", sender, String::from_utf8_lossy(&pt)),
-                                Err(_) => println!("{} (복호화 실패)", sender),
+                if let Some((sender, payload)) = socket_line.strip_prefix('@').and_then(|dm| dm.split_once(' ')) {
+                    // 귓속말 (Double Ratchet으로 종단간 암호화)
+                    match identities.open_dm(sender, payload) {
+                        Ok(pt) => match Envelope::from_bytes(&pt) {
+                            Some(envelope) if !seen_dms.insert(envelope.id.clone()) => {}
+                            Some(Envelope { kind: Kind::Receipt { targets, read }, .. }) => timeline.receipt(sender, &targets, read),
+                            Some(Envelope { kind: Kind::Text { body }, id, ts, .. }) => {
+                                say!("💌 {} → 나: {}", sender, markdown::render(&body, &highlight));
+                                mentions.direct(sender, &body);
+                                archive_text(&history, &id, &format!("@{}", sender), ts, sender, &body);
+                            }
+                            Some(_) => {}
+                            None => say!("💌 {} → 나: {}", sender, markdown::sanitize(&String::from_utf8_lossy(&pt))),
+                        },
+                        Err(e) => say!("⚠️ {}의 귓속말을 열 수 없습니다: {}", sender, e),
+                    }
+                } else if let Some(ack) = socket_line.strip_prefix(envelope::ACK_PREFIX) {
+                    // 서버가 내 방 메시지를 받아들였다는 확인 "ACK <ID> <서버 시각>"
+                    let mut fields = ack.split(' ');
+                    if let Some(id) = fields.next() {
+                        timeline.ack(id, fields.next().and_then(|ts| ts.parse().ok()));
+                    }
+                } else if let Some((name, rest)) = socket_line.strip_prefix('#').and_then(|g| g.split_once(' ')) {
+                    // 그룹 메시지 "#<그룹> <보낸 사람> <TreeKEM 메시지>" (내가 보낸 것도 되돌아옴)
+                    let Some((sender, payload)) = rest.split_once(' ') else { continue };
+                    let result = general_purpose::STANDARD
+                        .decode(payload)
+                        .map_err(|_| "그룹 메시지 형식이 잘못되었습니다.".to_string())
+                        .and_then(|message| identities.groups.receive(name, sender, &message));
+                    match result {
+                        Ok(received) => {
+                            // 그룹 메시지의 평문도 봉투이므로, 봉투의 ID로 기록해서 다시 받아도 한 번만 남김
+                            match received.plaintext.as_deref().map(Envelope::from_bytes) {
+                                Some(Some(Envelope { kind: Kind::Text { body }, id, ts, .. })) => {
+                                    say!("#{} {}: {}", name, sender, markdown::render(&body, &highlight));
+                                    mentions.received(&format!("#{}", name), sender, &body);
+                                    archive_text(&history, &id, &format!("#{}", name), ts, sender, &body);
+                                }
+                                Some(Some(_)) | None => {}
+                                Some(None) => say!("⚠️ {} 그룹의 {} 메시지가 봉투 형식이 아닙니다.", name, sender),
+                            }
+                            for message in received.outgoing {
+                                conn.send_line(&group_line(name, &message)).await?;
+                            }
+                        }
+                        Err(e) => say!("⚠️ {} 그룹의 {} 메시지를 처리할 수 없습니다: {}", name, sender, e),
+                    }
+                } else if let Some((sender, content)) = envelope::parse_room_line(&socket_line) {
+                    let (content, ts) = envelope::split_timestamp(content);
+                    match room.open(content).and_then(|(_, pt)| padding.unpad(pt)) {
+                        Ok(pt) => {
+                            let text = String::from_utf8_lossy(&pt);
+                            conn.record(Dir::Recv, Layer::Room, Some(sender), &text);
+                            if sender == metadata::SERVER_NICK {
+                                // 서버의 공지(MOTD)와 방 정보
+                                if let Some(motd) = text.strip_prefix(metadata::MOTD_PREFIX) {
+                                    say!("📢 {}", markdown::render(motd, &highlight));
+                                } else if let Some(info) = RoomInfo::from_control(&text) {
+                                    print_room_info(room_info.as_ref(), &info);
+                                    room_info = Some(info);
+                                }
+                            } else if let Some(payload) = text.strip_prefix(IDENT_PREFIX) {
+                                if let Some(reply) = identities.handle_announcement(sender, payload) {
+                                    send_room(&mut conn, &mut room, padding, reply.as_bytes()).await?;
+                                }
+                            } else if let Some(mut envelope) = Envelope::from_bytes(&pt) {
+                                envelope.ts = ts;
+                                archive(&history, MAIN_ROOM, sender, &envelope);
+                                // 새 메시지는 받았다는 확인을 모아 두었다가 보낸 사람에게 종단간 암호화로 보냄
+                                let text = match &envelope.kind {
+                                    Kind::Text { body } => Some((envelope.id.clone(), body.clone())),
+                                    _ => None,
+                                };
+                                if timeline.receive(sender, envelope)
+                                    && let Some((id, body)) = text
+                                {
+                                    mentions.received(MAIN_ROOM, sender, &body);
+                                    if args.delivery_receipts {
+                                        undelivered.entry(sender.to_string()).or_default().push(id);
+                                    }
+                                }
+                            } else {
+                                timeline.receive_plain(sender, ts, &text);
+                                mentions.received(MAIN_ROOM, sender, &text);
                             }
                         }
+                        Err(_) => say!("{} (복호화 실패)", sender),
                     }
                 } else {
-                    print!("{}", socket_line);
+                    say!("{}", socket_line);
+                }
+            }
+
+            // 모아 둔 수신 확인을 보낸 사람마다 한 번에 보냄 (남은 사람은 다음 차례에)
+            _ = receipt_timer.tick(), if !undelivered.is_empty() => {
+                for _ in 0..RECEIPT_MAX_SENDERS {
+                    let Some((sender, ids)) = undelivered.pop_first() else { break };
+                    if let Some(line) = identities.receipt_line(&sender, ids, false) {
+                        conn.send_line(&line).await?;
+                    }
                 }
-                socket_line.clear();
             }
 
             // 메시지 전송 (Room Key로 암호화)
             result = stdin.read_line(&mut input_line) => {
                 if result? == 0 { break; }
-                
-                let plaintext = input_line.trim_end();
-                if !plaintext.is_empty() {
-                    let mut nonce_bytes = [0u8; 12];
-                    OsRng.fill_bytes(&mut nonce_bytes);
-                    let nonce = Nonce::from_slice(&nonce_bytes);
-
-                    let ciphertext = room_cipher.encrypt(nonce, plaintext.as_bytes()).expect("Enc Fail");
-
-                    let mut payload = nonce_bytes.to_vec();
-                    payload.extend_from_slice(&ciphertext);
-                    let b64 = general_purpose::STANDARD.encode(payload);
-                    
-                    writer.write_all(format!("{}\n", b64).as_bytes()).await?;
+                let input_position = screen::count_input(&input_line);
+
+                // 무언가 입력했다면 그 전에 받은 메시지는 읽은 것으로 봄
+                for (sender, ids) in timeline.take_unread() {
+                    if args.read_receipts
+                        && let Some(line) = identities.receipt_line(&sender, ids, true)
+                    {
+                        conn.send_line(&line).await?;
+                        // 읽음 확인은 받았다는 확인도 겸함
+                        undelivered.remove(&sender);
+                    }
+                }
+
+                // 줄 끝의 줄바꿈만 떼어냄 (앞뒤 공백과 들여쓰기는 그대로 보냄)
+                let line = input_line.trim_end_matches(['\n', '\r']).to_string();
+                input_line.clear();
+                // 여러 줄 입력 중이면 닫는 줄이 올 때까지 모아서 한 메시지로 보냄
+                let (plaintext, input_position, input_rows, composed) = match compose.take() {
+                    Some(mut block) => match block.push(line) {
+                        Some(body) => (body, block.position, block.rows, true),
+                        None => {
+                            compose = Some(block);
+                            continue;
+                        }
+                    },
+                    None => match Compose::start(&line, input_position) {
+                        Some(block) => {
+                            compose = Some(block);
+                            continue;
+                        }
+                        None => {
+                            let rows = screen::rows(&line);
+                            (line, input_position, rows, false)
+                        }
+                    },
+                };
+                let plaintext = plaintext.as_str();
+                if !composed && let Some(command) = plaintext.strip_prefix('/') {
+                    let mut words = command.split_whitespace();
+                    match (words.next(), words.next()) {
+                        (Some("dm"), Some(peer)) => {
+                            mentions.read(&format!("@{}", peer));
+                            let text = command.splitn(3, ' ').nth(2).unwrap_or("").trim();
+                            if text.is_empty() {
+                                say!("사용법: /dm <닉네임> <메시지>");
+                            } else {
+                                let envelope = Envelope::new(&nick, Kind::Text { body: text.to_string() });
+                                match identities.seal_dm(peer, &envelope) {
+                                    Ok(line) => {
+                                        conn.send_line(&line).await?;
+                                        archive(&history, &format!("@{}", peer), &nick, &envelope);
+                                    }
+                                    Err(e) => say!("⚠️ {}", e),
+                                }
+                            }
+                        }
+                        (Some("group"), _) => {
+                            let words: Vec<&str> = command.split_whitespace().skip(1).collect();
+                            match identities.group_command(&words) {
+                                Ok(Some(line)) => conn.send_line(&line).await?,
+                                Ok(None) => {}
+                                Err(e) => say!("⚠️ {}", e),
+                            }
+                        }
+                        (Some("g"), Some(name)) => {
+                            mentions.read(&format!("#{}", name));
+                            let text = command.splitn(3, ' ').nth(2).unwrap_or("").trim();
+                            if text.is_empty() {
+                                say!("사용법: /g <그룹> <메시지>");
+                            } else {
+                                let envelope = Envelope::new(&nick, Kind::Text { body: text.to_string() });
+                                match identities.groups.seal(name, &envelope.to_bytes()) {
+                                    Ok(message) => {
+                                        conn.send_line(&group_line(name, &message)).await?;
+                                        archive(&history, &format!("#{}", name), &nick, &envelope);
+                                    }
+                                    Err(e) => say!("⚠️ {}", e),
+                                }
+                            }
+                        }
+                        (Some(verb @ ("edit" | "delete" | "react")), Some(id)) => {
+                            mentions.read(MAIN_ROOM);
+                            let rest = command.splitn(3, ' ').nth(2).unwrap_or("").trim();
+                            match room_command(&timeline, &nick, verb, id, rest) {
+                                Ok(envelope) => {
+                                    send_room(&mut conn, &mut room, padding, &envelope.to_bytes()).await?;
+                                    archive(&history, MAIN_ROOM, &nick, &envelope);
+                                    timeline.sent(&envelope, input_position, input_rows);
+                                }
+                                Err(e) => say!("⚠️ {}", e),
+                            }
+                        }
+                        (Some("receipts"), Some(id)) => match timeline.resolve(id) {
+                            Ok(id) => timeline.print_receipts(&id),
+                            Err(e) => say!("⚠️ {}", e),
+                        },
+                        (Some("history"), _) => {
+                            // "/history", "/history 50", "/history @bob", "/history #team 50"
+                            let mut rest = command.split_whitespace().skip(1).peekable();
+                            let room = match rest.peek() {
+                                Some(word) if word.parse::<usize>().is_err() => rest.next().unwrap_or(MAIN_ROOM),
+                                _ => MAIN_ROOM,
+                            };
+                            let n = rest.next().and_then(|n| n.parse().ok()).unwrap_or(HISTORY_DEFAULT);
+                            match &history {
+                                Some(store) => print_records(store.recent(room, n), &format!("{}의 기록이 없습니다.", room)),
+                                None => say!("⚠️ --history 로 기록 파일을 지정해야 합니다."),
+                            }
+                        }
+                        (Some("search"), Some(_)) => {
+                            let text = command.split_once(' ').map(|(_, text)| text.trim()).unwrap_or("");
+                            match &history {
+                                Some(store) => print_records(store.search(text, SEARCH_LIMIT), &format!("'{}'이(가) 들어 있는 메시지가 없습니다.", text)),
+                                None => say!("⚠️ --history 로 기록 파일을 지정해야 합니다."),
+                            }
+                        }
+                        (Some("topic"), None) => match &room_info {
+                            Some(info) => print_room_info(None, info),
+                            None => say!("⚠️ 서버에게서 방 정보를 받지 못했습니다."),
+                        },
+                        (Some("topic"), Some(_)) => {
+                            // "/topic -" 이면 토픽을 지움. 운영자가 아니면 서버가 거절함
+                            let text = command.split_once(' ').map_or("", |(_, text)| text.trim());
+                            let text = if text == "-" { "" } else { text };
+                            let request = format!("{}{}", metadata::TOPIC_PREFIX, text);
+                            send_room(&mut conn, &mut room, padding, request.as_bytes()).await?;
+                        }
+                        (Some("mentions"), clear) => mentions.print(clear == Some("clear")),
+                        (Some("fingerprint"), _) => identities.print_fingerprints(),
+                        (Some("verify"), Some(peer)) => match identities.session_peers.get(peer) {
+                            Some(_) => match identities.known.mark_verified(peer) {
+                                Ok(()) => say!("✅ {}를 확인된 사용자로 표시했습니다.", peer),
+                                Err(e) => say!("⚠️ {}", e),
+                            },
+                            None => say!("⚠️ 이번 세션에서 {}의 신원 키를 받지 못했습니다.", peer),
+                        },
+                        _ => say!("사용법: /fingerprint | /verify <닉네임> | /dm <닉네임> <메시지> | /g <그룹> <메시지> | /mentions [clear] | {} | {} | {} | {} | {}", GROUP_USAGE, ROOM_USAGE, HISTORY_USAGE, TOPIC_USAGE, COMPOSE_USAGE),
+                    }
+                } else if !plaintext.trim().is_empty() {
+                    mentions.read(MAIN_ROOM);
+                    let envelope = Envelope::new(&nick, Kind::Text { body: plaintext.to_string() });
+                    send_room(&mut conn, &mut room, padding, &envelope.to_bytes()).await?;
+                    archive(&history, MAIN_ROOM, &nick, &envelope);
+                    timeline.sent(&envelope, input_position, input_rows);
                 }
                 input_line.clear();
             }
         }
     }
+
+    // QUIC 스트림을 닫고(drop) 연결 종료를 서버에 알림
+    drop(conn);
+    if let (Some(client), Some(connection)) = (&quic_client, &quic_connection) {
+        client.close(connection).await;
+    }
     Ok(())
 }
 
-fn parse_message(line: &str) -> Option<(&str, &str)> {
-    let parts: Vec<&str> = line.splitn(2, "]: ").collect();
-    if parts.len() == 2 {
-        Some((parts[0].trim_start_matches('['), parts[1]))
+// 서버와의 접속 하나에서 정해지는 것들 (QUIC 연결이 끊겨서 다시 접속하면 새로 만듦)
+struct Session {
+    conn: ChatConn,
+    room: CipherState,
+    padding: Padding,
+    binding: Vec<u8>,
+    quic_connection: Option<quinn::Connection>,
+}
+
+type ChatConn = LineConn<BufReader<ReadHalf<Box<dyn ChatStream>>>, WriteHalf<Box<dyn ChatStream>>>;
+
+// 서버에 접속해서 전송 보안 핸드셰이크, 로그인, Room Key 수신까지 마침
+async fn open_session(
+    args: &Args,
+    nick: &str,
+    password: Option<&Zeroizing<String>>,
+    quic: Option<&QuicClient>,
+    recorder: Option<(&PathBuf, Recorder)>,
+) -> Result<Session, Box<dyn std::error::Error>> {
+    // QUIC이면 연결 안에 채팅방 스트림을 하나 열고, 그 스트림으로 TCP와 똑같은 줄 단위 프로토콜을 주고받음
+    let mut quic_connection = None;
+    let stream: Box<dyn ChatStream> = if let Some(client) = quic {
+        say!("connecting...");
+        let (connection, stream, offered_ticket) = client.connect(quic::ROOM_STREAM).await?;
+        say!("🚀 QUIC 연결 완료{}", if offered_ticket { " (세션 티켓으로 TLS 세션 재개 요청)" } else { "" });
+        quic_connection = Some(connection);
+        Box::new(stream)
     } else {
-        None
+        let socket: Box<dyn ChatStream> = match &args.unix {
+            Some(path) => Box::new(UnixStream::connect(path).await?),
+            None => Box::new(TcpStream::connect(&args.server).await?),
+        };
+        say!("connecting...");
+
+        // TLS는 바깥 포장일 뿐, 그 안의 핸드셰이크와 Room Key 암호화는 그대로 진행
+        let connector = match (&args.tls_ca, &args.tls_pin) {
+            (Some(ca), _) => Some(tls::connector_with_ca(ca)?),
+            (None, Some(pin)) => Some(tls::connector_with_pin(pin)?),
+            (None, None) => None,
+        };
+        match connector {
+            Some(connector) => {
+                let name = tls::server_name(args.tls_name.as_deref().unwrap_or(&args.server))?;
+                let stream = connector.connect(name, socket).await?;
+                say!("🔒 TLS 연결 완료");
+                Box::new(stream)
+            }
+            None => socket,
+        }
+    };
+
+    let (reader, writer) = tokio::io::split(stream);
+    let mut conn = LineConn::new(BufReader::new(reader), writer, MAX_LINE_BYTES);
+    if let Some((path, recorder)) = recorder {
+        conn.set_recorder(recorder);
+        say!("📼 주고받는 프레임을 {}에 기록합니다. (메시지 평문이 들어 있으니 테스트 세션에서만 사용하세요)", path.display());
+    }
+
+    // ==========================================
+    // [핸드셰이크 단계 (ECDH 또는 Noise)]
+    // ==========================================
+    let (session_key, binding) = match args.handshake {
+        HandshakeMode::Ecdh => ecdh_handshake(&mut conn, args.pq).await?,
+        HandshakeMode::Noise => {
+            if args.pq {
+                say!("⚠️ --pq 는 ECDH 핸드셰이크에서만 사용되므로 무시합니다.");
+            }
+            let server_static = match &args.noise_server_key {
+                Some(key) => Some(general_purpose::STANDARD.decode(key.trim())?),
+                None => None,
+            };
+            let local = StaticKey::generate()?;
+            let session = noise::initiate(&mut conn, &local, server_static.as_deref()).await?;
+            if server_static.is_none() {
+                // XX: 서버 키를 미리 몰랐으므로, 다음부터 IK로 접속하려면 이 값을 고정하면 됨
+                say!(
+                    "🔐 Noise_XX 서버 정적 공개키: {} (--noise-server-key 로 고정 가능)",
+                    general_purpose::STANDARD.encode(&session.remote_static)
+                );
+            }
+            conn.set_transport(session.transport);
+            (session.session_key, session.handshake_hash)
+        }
+    };
+    let session_cipher = session_key.cipher();
+
+    // 4. 로그인 (세션 키로 암호화된 채널 안에서 진행)
+    //    PAKE 로그인이면 Room Key는 PAKE 공유 비밀을 섞은 키로 암호화되어 옴
+    let room_key_cipher = match (password, args.pake) {
+        (Some(password), true) => {
+            let mixed_key = pake_login(&mut conn, &session_key, nick, password, &binding).await?;
+            mixed_key.cipher()
+        }
+        (Some(password), false) => {
+            let request = Zeroizing::new(format!("LOGIN {} {}", nick, password.as_str()));
+            conn.send_sealed(&session_cipher, request.as_bytes()).await?;
+            session_cipher.clone()
+        }
+        (None, _) => {
+            conn.send_sealed(&session_cipher, format!("NICK {}", nick).as_bytes()).await?;
+            session_cipher.clone()
+        }
+    };
+
+    let reply = conn.recv_sealed_text(&session_cipher).await?;
+    if let Some(reason) = reply.strip_prefix("ERR ") {
+        return Err(format!("로그인 실패: {}", reason).into());
+    }
+    // "OK <닉네임> sender=<발신자 번호> rekey=<키 갱신 한도> [pad=<패딩 정책>]"
+    let field = |tag: &str| reply.split(' ').find_map(|field| field.strip_prefix(tag)).map(str::to_string);
+    let padding: Padding = match field(padding::PADDING_TAG) {
+        Some(policy) => policy.parse()?,
+        None => Padding::None,
+    };
+    let (Some(sender), Some(rekey_after)) = (
+        field(cipherstate::SENDER_TAG).and_then(|n| n.parse().ok()),
+        field(cipherstate::REKEY_TAG).and_then(|n| n.parse().ok()),
+    ) else {
+        return Err("서버가 발신자 번호를 알려주지 않았습니다. (서버 버전이 다릅니다)".into());
+    };
+
+    // 5. 암호화된 Room Key 수신 및 복호화
+    let room_key_line = conn.recv_line().await?;
+    let room_key = AesKey::unwrap(&room_key_cipher, &room_key_line)
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "Room Key 복호화 실패"))?;
+    
+    // 6. 채팅용 암호화 상태 생성
+    // (이제부터 이 키에서 유도한 발신자별 키와 카운터 nonce로 모든 채팅 메시지를 암호화/복호화합니다)
+    let room = CipherState::new(&room_key, sender, rekey_after)?;
+    drop(room_key);
+
+    say!("✅ 보안 핸드셰이크 성공! {} 닉네임으로 안전한 채팅을 시작합니다.", nick);
+    if padding != Padding::None {
+        say!("📦 이 방은 메시지 길이를 숨깁니다 (패딩: {})", padding);
+    }
+
+    Ok(Session { conn, room, padding, binding, quic_connection })
+}
+
+// QUIC 연결이 끊기면 간격을 늘려 가며 다시 접속 (TLS 세션은 재개하지만, 핸드셰이크와 로그인은 처음부터 다시 함)
+// 서버가 끊긴 연결을 아직 알아채지 못했으면 같은 닉네임으로 로그인할 수 없으므로, 전체 대기 시간은 QUIC 유휴 시간 한도보다 길게 잡음
+async fn reconnect(args: &Args, nick: &str, password: Option<&Zeroizing<String>>, client: &QuicClient) -> Result<Session, Box<dyn std::error::Error>> {
+    let mut delay = RECONNECT_DELAY;
+    for attempt in 1..=RECONNECT_ATTEMPTS {
+        say!("🔌 서버와의 연결이 끊겼습니다. {}초 뒤에 다시 접속합니다. ({}/{})", delay.as_secs(), attempt, RECONNECT_ATTEMPTS);
+        tokio::time::sleep(delay).await;
+        match open_session(args, nick, password, Some(client), None).await {
+            Ok(session) => return Ok(session),
+            Err(e) => say!("⚠️ 다시 접속하지 못했습니다: {}", e),
+        }
+        delay *= 2;
+    }
+    Err("서버에 다시 접속하지 못했습니다.".into())
+}
+
+// 직접 만든 ECDH 핸드셰이크. 세션 키와 바인딩 값(서버 공개키 || 클라이언트 공개키)을 돌려줌
+async fn ecdh_handshake<R, W>(conn: &mut LineConn<R, W>, hybrid: bool) -> Result<(AesKey, Vec<u8>), Box<dyn std::error::Error>>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    // 1. 서버 공개키 수신
+    let server_pub_line = conn.recv_line().await?;
+    let server_pub_bytes = general_purpose::STANDARD.decode(server_pub_line.trim())?;
+
+    // 2. 내 임시 키 쌍 생성 및 공개키 전송
+    //    하이브리드면 ML-KEM-768 캡슐화 키를 같은 줄에 덧붙임 (예전 서버는 이 줄을 받아들이지 못함)
+    let client_ecdh = ecdhkey::EcdhKey::create();
+    let client_pub = client_ecdh.public_key_bytes();
+    let client_pub_b64 = general_purpose::STANDARD.encode(&client_pub);
+    let mlkem_key = hybrid.then(mlkem::generate);
+    match &mlkem_key {
+        None => conn.send_line(&client_pub_b64).await?,
+        Some((_, ek)) => {
+            conn.send_line(&format!("{} {}{}", client_pub_b64, MLKEM_TAG, general_purpose::STANDARD.encode(ek)))
+                .await?
+        }
     }
-}
\ No newline at end of file
+
+    let mut binding = server_pub_bytes.clone();
+    binding.extend_from_slice(&client_pub);
+
+    // 3. 세션 키 유도 (핸드셰이크 암호화용)
+    let session_key = match mlkem_key {
+        None => client_ecdh.derive_aes_key(&server_pub_bytes),
+        Some((dk, ek)) => {
+            // 3-1. 서버가 보낸 ML-KEM 암호문에서 공유 비밀을 꺼내 ECDH 비밀과 함께 사용
+            let ct_line = conn.recv_line().await?;
+            let ct = ct_line
+                .trim()
+                .strip_prefix(MLKEM_TAG)
+                .and_then(|ct| general_purpose::STANDARD.decode(ct).ok())
+                .ok_or("서버가 하이브리드 키 교환에 응답하지 않았습니다.")?;
+            let pq_secret = dk.decapsulate(&ct)?;
+            binding.extend_from_slice(&ek);
+            binding.extend_from_slice(&ct);
+            say!("🛡️ ML-KEM-768 하이브리드 키 교환 완료");
+            client_ecdh.derive_hybrid_aes_key(&server_pub_bytes, pq_secret.as_slice())
+        }
+    }
+    .map_err(std::io::Error::other)?;
+
+    Ok((session_key, binding))
+}
+
+// SPAKE2 로그인 (클라이언트 = A 역할). 서버의 확인값을 먼저 검증하므로
+// 비밀번호를 모르는 가짜 서버나 ECDH 중간자에게는 내 확인값을 보내지 않음
+// 성공하면 PAKE 공유 비밀을 섞은 새 세션 키를 돌려줌
+async fn pake_login<R, W>(
+    conn: &mut LineConn<R, W>,
+    session_key: &AesKey,
+    nick: &str,
+    password: &str,
+    handshake_aad: &[u8],
+) -> Result<AesKey, Box<dyn std::error::Error>>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let session_cipher = &session_key.cipher();
+    let b64 = |bytes: &[u8]| general_purpose::STANDARD.encode(bytes);
+    conn.send_sealed(session_cipher, format!("PAKE {}", nick).as_bytes()).await?;
+
+    let reply = conn.recv_sealed_text(session_cipher).await?;
+    let salt = match reply.strip_prefix("SALT ") {
+        Some(salt) => general_purpose::STANDARD.decode(salt.trim())?,
+        None => return Err(format!("로그인 실패: {}", reply.trim_start_matches("ERR ")).into()),
+    };
+
+    let w = spake2::password_to_w(password, &salt)?;
+    let spake = Spake2::start(Role::Client, &w, nick.as_bytes(), SPAKE2_SERVER_ID, handshake_aad)?;
+    conn.send_sealed(session_cipher, format!("SHARE {}", b64(spake.share())).as_bytes()).await?;
+
+    let reply = conn.recv_sealed_text(session_cipher).await?;
+    let mut fields = reply.strip_prefix("SHARE ").unwrap_or("").split(' ');
+    let (Some(server_share), Some(server_confirmation)) = (fields.next(), fields.next()) else {
+        return Err(format!("로그인 실패: {}", reply.trim_start_matches("ERR ")).into());
+    };
+    let keys = spake.finish(&general_purpose::STANDARD.decode(server_share)?)?;
+    keys.verify_peer(&general_purpose::STANDARD.decode(server_confirmation)?)
+        .map_err(|_| "서버 인증 실패: 비밀번호가 틀렸거나 중간자 공격이 의심됩니다.")?;
+
+    conn.send_sealed(session_cipher, format!("CONFIRM {}", b64(keys.confirmation())).as_bytes()).await?;
+    Ok(keys.mix_into(session_key)?)
+}
+
+// /edit, /delete, /react 명령으로 보낼 봉투 생성 (수정과 삭제는 내 메시지만 가능)
+fn room_command(timeline: &Timeline, nick: &str, verb: &str, id: &str, rest: &str) -> Result<Envelope, String> {
+    let target = timeline.resolve(id)?;
+    let kind = match verb {
+        "edit" | "delete" if !timeline.is_mine(&target) => return Err("내가 보낸 메시지만 고치거나 지울 수 있습니다.".to_string()),
+        "edit" if !rest.is_empty() => Kind::Edit { target, body: rest.to_string() },
+        "delete" => Kind::Delete { target },
+        "react" if !rest.is_empty() => Kind::Reaction { target, emoji: rest.to_string() },
+        _ => return Err(format!("사용법: {}", ROOM_USAGE)),
+    };
+    Ok(Envelope::new(nick, kind))
+}
+
+// 대화 기록 파일 열기. 처음 만드는 파일이면 암호를 두 번 입력받음
+fn open_history(path: &std::path::Path) -> Result<History, Box<dyn std::error::Error>> {
+    let is_new = !path.exists();
+    let passphrase = Zeroizing::new(rpassword::prompt_password("대화 기록 암호: ")?);
+    if is_new && *passphrase != rpassword::prompt_password("대화 기록 암호 확인: ")? {
+        return Err("대화 기록 암호가 일치하지 않습니다.".into());
+    }
+    Ok(History::open(path, &passphrase)?)
+}
+
+// 방 메시지를 대화 기록에 반영 (새 메시지는 저장, 수정/삭제는 저장된 메시지에 적용)
+fn archive(history: &Option<History>, room: &str, sender: &str, envelope: &Envelope) {
+    let Some(store) = history else { return };
+    let result = match &envelope.kind {
+        Kind::Text { body } => return archive_text(history, &envelope.id, room, envelope.ts, sender, body),
+        Kind::Edit { target, body } => store.edit(target, sender, body),
+        Kind::Delete { target } => store.delete(target, sender),
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        say!("⚠️ 대화 기록에 반영하지 못했습니다: {}", e);
+    }
+}
+
+fn archive_text(history: &Option<History>, id: &str, room: &str, ts: Option<i64>, sender: &str, body: &str) {
+    let Some(store) = history else { return };
+    let record = Record {
+        room: room.to_string(),
+        ts: ts.unwrap_or_else(envelope::now_millis),
+        sender: sender.to_string(),
+        body: body.to_string(),
+    };
+    if let Err(e) = store.record(id, &record) {
+        say!("⚠️ 대화 기록에 저장하지 못했습니다: {}", e);
+    }
+}
+
+// /history, /search 결과 출력: [방] 날짜 시각 보낸 사람: 내용
+fn print_records(records: Result<Vec<Record>, String>, empty: &str) {
+    match records {
+        Ok(records) if records.is_empty() => say!("   ({})", empty),
+        Ok(records) => {
+            for record in records {
+                let when = chrono::DateTime::from_timestamp_millis(record.ts)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                say!("   [{}] {} {}: {}", record.room, when, record.sender, markdown::sanitize(&record.body));
+            }
+        }
+        Err(e) => say!("⚠️ {}", e),
+    }
+}
+
+// 방 정보 출력. 처음 받았거나 /topic 이면 전부, 그 뒤로는 토픽이 바뀌었을 때만 알림
+fn print_room_info(previous: Option<&RoomInfo>, info: &RoomInfo) {
+    let when = |ms: i64| {
+        chrono::DateTime::from_timestamp_millis(ms)
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default()
+    };
+    match previous {
+        None => {
+            let lock = if info.encrypted { "🔒 암호화됨" } else { "⚠️ 암호화 안 됨" };
+            say!("🏷️ #{} | {} | 접속 {}명 | {} 만듦", info.name, lock, info.members, when(info.created));
+            match &info.topic {
+                Some(topic) => say!("   토픽: {} ({}, {})", markdown::sanitize(&topic.text), topic.set_by, when(topic.set_at)),
+                None => say!("   (토픽 없음)"),
+            }
+        }
+        Some(previous) if previous.topic != info.topic => match &info.topic {
+            Some(topic) => say!("🏷️ {}님이 토픽을 바꿨습니다: {}", topic.set_by, markdown::sanitize(&topic.text)),
+            None => say!("🏷️ 토픽이 지워졌습니다."),
+        },
+        Some(_) => {}
+    }
+}
+
+// 여러 줄 입력 중인 메시지
+struct Compose {
+    fence: &'static str,
+    lines: Vec<String>,
+    // 화면에서 입력이 시작된 행과 지금까지 입력한 줄들이 차지한 행 수 (보낸 뒤 메시지 모양으로 다시 그릴 때 사용)
+    position: usize,
+    rows: usize,
+}
+
+impl Compose {
+    // """ 또는 ```[언어] 만 있는 줄이면 여러 줄 입력을 시작
+    fn start(line: &str, position: usize) -> Option<Self> {
+        let trimmed = line.trim_end();
+        let (fence, lines) = if trimmed == TEXT_FENCE {
+            (TEXT_FENCE, vec![])
+        } else if let Some(lang) = trimmed.strip_prefix(CODE_FENCE)
+            && !lang.contains('`')
+        {
+            (CODE_FENCE, vec![trimmed.to_string()])
+        } else {
+            return None;
+        };
+        Some(Self { fence, lines, position, rows: screen::rows(line) })
+    }
+
+    // 한 줄 추가. 닫는 줄이면 모은 본문을 돌려줌
+    fn push(&mut self, line: String) -> Option<String> {
+        self.rows += screen::rows(&line);
+        let closing = line.trim_end() == self.fence;
+        if !closing || self.fence == CODE_FENCE {
+            self.lines.push(line);
+        }
+        closing.then(|| self.lines.join("\n"))
+    }
+}
+
+// 방 메시지 전송 (패딩 후 암호화). 키 갱신 한도에 닿아 다음 세대 키로 바뀌었으면 알려줌
+async fn send_room<R, W>(
+    conn: &mut LineConn<R, W>,
+    room: &mut CipherState,
+    padding: Padding,
+    plaintext: &[u8],
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let epoch = room.epoch();
+    let line = room.seal(&padding.pad(plaintext))?;
+    if room.epoch() != epoch {
+        say!("🔄 보내는 키를 {}세대로 바꿨습니다.", room.epoch());
+    }
+    conn.send_recorded(&line, Layer::Room, plaintext).await?;
+    Ok(())
+}
+
+fn group_line(name: &str, message: &[u8]) -> String {
+    format!("#{} {}", name, general_purpose::STANDARD.encode(message))
+}
+
+fn validate_group_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > 32 || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err("그룹 이름은 32자 이하의 영문, 숫자, '-', '_'만 쓸 수 있습니다.".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,717 @@
+// src/bin/chat_conform.rs
+// chatserver/chatclient 프로토콜의 적합성(conformance) 검사 도구입니다.
+//   - vectors: 고정된 키와 nonce로 만든 기대값(테스트 벡터)을 라이브러리가 그대로 재현하는지 확인
+//              프로토콜을 일부러 바꿨다면 버전을 올린 새 벡터 파일을 만들고 --bless 로 기대값을 다시 채움
+//   - replay:  chatclient --record 로 남긴 세션 기록을 새 서버에 다시 보내고, 서버의 응답이 기록과 같은지 비교
+//              핸드셰이크는 새로 하고, 방 메시지와 로그인 요청은 기록된 평문을 새 키로 다시 암호화해서 보냄
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine as _};
+use clap::{Parser, Subcommand};
+use rand::rngs::OsRng; // ecdhkey 모듈이 crate::OsRng 로 사용
+use serde::{Deserialize, Serialize};
+use tokio::io::BufReader;
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use zeroize::Zeroizing;
+
+#[path = "../ecdh/ecdhkey.rs"]
+mod ecdhkey;
+#[path = "../ecdh/secret.rs"]
+#[allow(dead_code)] // 랜덤 키 생성과 키 감싸기(wrap)는 chatserver에서만 사용
+mod secret;
+#[path = "../proto/packet.rs"]
+mod packet;
+#[path = "../proto/conn.rs"]
+#[allow(dead_code)] // 기록기와 Noise 전송 상태 설정은 chatclient/chatserver에서만 사용
+mod conn;
+#[path = "../proto/frame.rs"]
+mod frame;
+#[path = "../proto/noise.rs"]
+#[allow(dead_code)] // LineConn이 쓰는 Noise 전송 상태 타입만 필요
+mod noise;
+#[path = "../proto/transcript.rs"]
+#[allow(dead_code)] // 기록하기(Recorder)는 chatclient에서만 사용
+mod transcript;
+#[path = "../proto/cipherstate.rs"]
+#[allow(dead_code)] // 세대 확인(epoch)은 chatclient에서만 사용
+mod cipherstate;
+#[path = "../proto/padding.rs"]
+mod padding;
+#[path = "../proto/envelope.rs"]
+#[allow(dead_code)] // 봉투 생성과 시각 만들기는 chatclient/chatserver에서만 사용
+mod envelope;
+#[path = "../auth/accounts.rs"]
+#[allow(dead_code)] // 연합 메시지의 이름 확인(valid_nick)만 필요
+mod accounts;
+#[path = "../pake/spake2.rs"]
+#[allow(dead_code)] // 계정 모듈이 사용하지만 chatconform은 로그인하지 않음
+mod spake2;
+#[path = "../federation/link.rs"]
+#[allow(dead_code)] // 링크 핸드셰이크와 접속 상태 관리는 chatserver에서만 사용
+mod link;
+#[path = "../room/metadata.rs"]
+#[allow(dead_code)] // 서버가 보내는 방 정보의 머리말만 필요
+mod metadata;
+#[path = "../pq/mlkem.rs"]
+#[allow(dead_code)] // 시드와 메시지를 정해서 쓰는 결정적 함수만 사용
+mod mlkem;
+#[path = "../identity/identity.rs"]
+#[allow(dead_code)] // 서명과 키 파일은 쓰지 않음
+mod identity;
+#[path = "../ratchet/double_ratchet.rs"]
+#[allow(dead_code)] // 세션 저장과 시작 메시지 기록은 chatclient에서만 사용
+mod double_ratchet;
+#[path = "../group/treekem.rs"]
+#[allow(dead_code)] // Welcome으로 참가한 쪽의 함수만 사용
+mod treekem;
+
+use cipherstate::CipherState;
+use conn::LineConn;
+use double_ratchet::{Party, Ratchet};
+use ecdhkey::EcdhKey;
+use envelope::Envelope;
+use frame::Frame;
+use identity::{IdentityKey, PreKey};
+use link::FedMessage;
+use padding::Padding;
+use secret::AesKey;
+use sha3::{Digest, Sha3_256};
+use transcript::{Dir, Event, Layer};
+use treekem::Group;
+
+// 서버에서 받을 한 줄의 최대 길이 (chatclient와 같음)
+const MAX_LINE_BYTES: usize = 1024 * 1024;
+// 세션 키처럼 밖으로 꺼낼 수 없는 키는, 이 평문을 0 nonce로 암호화한 결과로 비교
+const PROBE: &[u8] = b"chat-protocol-probe";
+const PROBE_NONCE: [u8; packet::NONCE_LEN] = [0; packet::NONCE_LEN];
+// 재생에서 다르게 나와도 되는 값(시각)을 바꿔 넣을 표시
+const ANY_TIME: &str = "<시각>";
+// 비교 결과에서 보여줄 최대 차이 수
+const MAX_DIFFS: usize = 20;
+
+#[derive(Parser, Debug)]
+#[command(name = "chatconform", about = "채팅 프로토콜 테스트 벡터 검사와 세션 기록 재생")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 테스트 벡터 파일의 기대값을 라이브러리가 그대로 재현하는지 확인
+    Vectors {
+        /// 테스트 벡터 파일 (JSON)
+        #[arg(default_value = "vectors/protocol-v1.json")]
+        file: PathBuf,
+
+        /// 확인하는 대신 지금 라이브러리의 결과로 기대값을 다시 채움 (프로토콜을 일부러 바꿨을 때만)
+        #[arg(long)]
+        bless: bool,
+    },
+    /// chatclient --record 로 남긴 세션 기록을 서버에 다시 보내고 응답을 비교 (여러 개면 기록된 시각에 맞춰 함께 재생)
+    Replay {
+        /// 세션 기록 파일 (JSON 줄)
+        #[arg(required = true)]
+        transcripts: Vec<PathBuf>,
+
+        /// 재생할 서버 주소 (새로 띄운 서버여야 기록과 같은 응답이 나옴)
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        server: String,
+
+        /// 마지막으로 보낸 뒤 서버 응답을 더 기다리는 시간 (밀리초)
+        #[arg(long, default_value_t = 1000)]
+        settle_ms: u64,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    match Cli::parse().command {
+        Command::Vectors { file, bless } => check_vectors(&file, bless)?,
+        Command::Replay { transcripts, server, settle_ms } => replay_all(&transcripts, &server, settle_ms).await?,
+    }
+    Ok(())
+}
+
+// ==========================================
+// [테스트 벡터]
+// ==========================================
+
+#[derive(Serialize, Deserialize, Debug)]
+struct VectorFile {
+    version: u32,
+    description: Vec<String>,
+    vectors: Vec<Vector>,
+}
+
+// 바이트 값은 16진수, 줄(packet)은 chatserver/chatclient가 실제로 주고받는 Base64 그대로
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Vector {
+    // AES-256-GCM 패킷: Base64(nonce || 암호문)
+    Packet { name: String, key: String, nonce: String, plaintext: String, line: String },
+    // ECDH(P-256) 핸드셰이크: 양쪽 공개키와, 유도한 세션 키로 PROBE를 암호화한 줄 (pq_secret이 있으면 하이브리드)
+    Handshake {
+        name: String,
+        client_secret: String,
+        server_secret: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pq_secret: Option<String>,
+        client_public: String,
+        server_public: String,
+        probe: String,
+    },
+    // 세션 키에 PAKE 공유 비밀을 섞은 키로 PROBE를 암호화한 줄
+    SessionMix { name: String, session_key: String, extra: String, probe: String },
+    // Room Key의 발신자별 카운터 nonce와 키 갱신: 차례로 암호화한 줄들
+    Room { name: String, room_key: String, sender: u32, rekey_after: u32, plaintexts: Vec<String>, lines: Vec<String> },
+    // 패딩 정책을 적용한 평문
+    Padding { name: String, policy: String, plaintext: String, padded: String },
+    // 메시지 봉투의 JSON 직렬화 (읽어서 다시 쓰면 같은 바이트)
+    Envelope { name: String, json: String },
+    // 서버가 붙이는 시각 표시 " t=<밀리초>"
+    Timestamp {
+        name: String,
+        line: String,
+        packet: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ts: Option<i64>,
+    },
+    // 서버 사이 연합 링크의 한 줄 (읽어서 다시 쓰면 같은 줄)
+    Federation { name: String, line: String },
+    // ML-KEM-768: 시드(d, z)로 만든 캡슐화 키와, 메시지 m으로 캡슐화한 암호문의 SHA3-256, 그리고 공유 비밀
+    Mlkem { name: String, d: String, z: String, m: String, ek_sha3_256: String, ct_sha3_256: String, shared: String },
+    // Double Ratchet: 두 신원 키, 두 prekey와 개시자의 첫 래칫 키로 시작한 세션의 첫 귓속말 (응답자가 복호화할 수 있어야 함)
+    // ad는 chatclient가 넣는 인증 데이터 "chat-dm-v1" || 길이(2) || 보낸 사람 || 길이(2) || 받는 사람
+    DoubleRatchet {
+        name: String,
+        initiator: String,
+        responder: String,
+        initiator_secret: String,
+        responder_secret: String,
+        initiator_prekey: String,
+        responder_prekey: String,
+        ratchet_secret: String,
+        ad: String,
+        nonce: String,
+        plaintext: String,
+        message: String,
+    },
+    // TreeKEM: 정해진 초대 키로 Welcome을 받아 참가하고 Commit들을 적용한 뒤, 그 epoch에서 정해진 nonce로 암호화한 그룹 메시지
+    Treekem {
+        name: String,
+        nick: String,
+        init_secret: String,
+        welcome: String,
+        commits: Vec<String>,
+        epoch: u64,
+        members: Vec<String>,
+        sender: String,
+        nonce: String,
+        plaintext: String,
+        message: String,
+    },
+}
+
+impl Vector {
+    fn name(&self) -> &str {
+        match self {
+            Vector::Packet { name, .. }
+            | Vector::Handshake { name, .. }
+            | Vector::SessionMix { name, .. }
+            | Vector::Room { name, .. }
+            | Vector::Padding { name, .. }
+            | Vector::Envelope { name, .. }
+            | Vector::Timestamp { name, .. }
+            | Vector::Federation { name, .. }
+            | Vector::Mlkem { name, .. }
+            | Vector::DoubleRatchet { name, .. }
+            | Vector::Treekem { name, .. } => name,
+        }
+    }
+}
+
+fn check_vectors(path: &Path, bless: bool) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut file: VectorFile = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    println!("📐 프로토콜 v{} 테스트 벡터 {}개 ({})", file.version, file.vectors.len(), path.display());
+
+    let mut failed = 0;
+    for vector in &mut file.vectors {
+        let computed = match compute(vector) {
+            Ok(computed) => computed,
+            Err(e) => {
+                println!("❌ {}: {}", vector.name(), e);
+                failed += 1;
+                continue;
+            }
+        };
+        if computed == *vector {
+            println!("✅ {}", vector.name());
+        } else if bless {
+            println!("✏️ {}: 기대값을 다시 채웠습니다.", vector.name());
+            *vector = computed;
+        } else {
+            println!("❌ {}", vector.name());
+            println!("   기대값: {}", serde_json::to_string(vector).unwrap_or_default());
+            println!("   실제값: {}", serde_json::to_string(&computed).unwrap_or_default());
+            failed += 1;
+        }
+    }
+
+    if bless {
+        let text = serde_json::to_string_pretty(&file).expect("벡터 직렬화는 실패하지 않음");
+        std::fs::write(path, text + "\n").map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    if failed > 0 {
+        return Err(format!("테스트 벡터 {}개가 맞지 않습니다.", failed));
+    }
+    println!("🎉 모든 테스트 벡터가 맞습니다.");
+    Ok(())
+}
+
+// 벡터의 입력으로 라이브러리를 실행해서, 출력 칸을 채운 벡터를 돌려줌
+fn compute(vector: &Vector) -> Result<Vector, String> {
+    let mut out = vector.clone();
+    match &mut out {
+        Vector::Packet { key, nonce, plaintext, line, .. } => {
+            let cipher = aes_key(key)?.cipher();
+            let nonce: [u8; packet::NONCE_LEN] = from_hex(nonce)?.try_into().map_err(|_| "nonce는 12바이트여야 합니다.")?;
+            *line = packet::seal_with_nonce(&cipher, nonce, plaintext.as_bytes());
+            if packet::open(&cipher, line)? != plaintext.as_bytes() {
+                return Err("복호화한 평문이 다릅니다.".to_string());
+            }
+        }
+        Vector::Handshake { client_secret, server_secret, pq_secret, client_public, server_public, probe, .. } => {
+            let client = EcdhKey::from_secret_bytes(&from_hex(client_secret)?)?;
+            let server = EcdhKey::from_secret_bytes(&from_hex(server_secret)?)?;
+            let client_bytes = client.public_key_bytes();
+            let server_bytes = server.public_key_bytes();
+            *client_public = general_purpose::STANDARD.encode(&client_bytes);
+            *server_public = general_purpose::STANDARD.encode(&server_bytes);
+            let (client_key, server_key) = match pq_secret {
+                None => (client.derive_aes_key(&server_bytes)?, server.derive_aes_key(&client_bytes)?),
+                Some(pq) => {
+                    let pq = from_hex(pq)?;
+                    (client.derive_hybrid_aes_key(&server_bytes, &pq)?, server.derive_hybrid_aes_key(&client_bytes, &pq)?)
+                }
+            };
+            *probe = probe_line(&client_key);
+            if probe_line(&server_key) != *probe {
+                return Err("클라이언트와 서버가 유도한 세션 키가 다릅니다.".to_string());
+            }
+        }
+        Vector::SessionMix { session_key, extra, probe, .. } => {
+            *probe = probe_line(&ecdhkey::mix_session_key(&aes_key(session_key)?, &from_hex(extra)?)?);
+        }
+        Vector::Room { room_key, sender, rekey_after, plaintexts, lines, .. } => {
+            let room_key = aes_key(room_key)?;
+            let mut sending = CipherState::new(&room_key, *sender, *rekey_after)?;
+            let mut receiving = CipherState::new(&room_key, cipherstate::SERVER_SENDER, *rekey_after)?;
+            lines.clear();
+            for plaintext in plaintexts.iter() {
+                let line = sending.seal(plaintext.as_bytes())?;
+                if receiving.open(&line)? != (*sender, plaintext.as_bytes().to_vec()) {
+                    return Err("복호화한 발신자나 평문이 다릅니다.".to_string());
+                }
+                lines.push(line);
+            }
+        }
+        Vector::Padding { policy, plaintext, padded, .. } => {
+            let policy: Padding = policy.parse()?;
+            let bytes = policy.pad(plaintext.as_bytes());
+            if policy.unpad(bytes.clone())? != plaintext.as_bytes() {
+                return Err("패딩을 뗀 평문이 다릅니다.".to_string());
+            }
+            *padded = to_hex(&bytes);
+        }
+        Vector::Envelope { json, .. } => {
+            let envelope = Envelope::from_bytes(json.as_bytes()).ok_or("봉투로 읽을 수 없습니다.")?;
+            *json = String::from_utf8(envelope.to_bytes()).expect("봉투 JSON은 UTF-8");
+        }
+        Vector::Timestamp { line, packet, ts, .. } => {
+            let (split, time) = envelope::split_timestamp(line);
+            *packet = split.to_string();
+            *ts = time;
+        }
+        Vector::Federation { line, .. } => {
+            *line = FedMessage::parse(line).ok_or("연합 메시지로 읽을 수 없습니다.")?.to_line();
+        }
+        Vector::Mlkem { d, z, m, ek_sha3_256, ct_sha3_256, shared, .. } => {
+            let (dk, ek) = mlkem::keygen_internal(&seed(d)?, &seed(z)?);
+            let (key, ct) = mlkem::encapsulate_internal(&ek, &seed(m)?)?;
+            if dk.decapsulate(&ct)? != key {
+                return Err("캡슐 해제한 공유 비밀이 다릅니다.".to_string());
+            }
+            *ek_sha3_256 = to_hex(&Sha3_256::digest(&ek));
+            *ct_sha3_256 = to_hex(&Sha3_256::digest(&ct));
+            *shared = to_hex(key.as_slice());
+        }
+        Vector::DoubleRatchet {
+            initiator,
+            responder,
+            initiator_secret,
+            responder_secret,
+            initiator_prekey,
+            responder_prekey,
+            ratchet_secret,
+            ad,
+            nonce,
+            plaintext,
+            message,
+            ..
+        } => {
+            let alice = IdentityKey::from_secret_bytes(&from_hex(initiator_secret)?)?.public_key_bytes();
+            let bob = IdentityKey::from_secret_bytes(&from_hex(responder_secret)?)?.public_key_bytes();
+            let alice_prekey = PreKey::from_secret_bytes(&from_hex(initiator_prekey)?)?;
+            let bob_prekey = PreKey::from_secret_bytes(&from_hex(responder_prekey)?)?;
+            let (alice_pre, bob_pre) = (alice_prekey.public_key_bytes(), bob_prekey.public_key_bytes());
+            let a = Party { nick: initiator, identity: &alice, prekey: &alice_pre };
+            let b = Party { nick: responder, identity: &bob, prekey: &bob_pre };
+            let ratchet_key = p256::SecretKey::from_slice(&from_hex(ratchet_secret)?).map_err(|_| "래칫 비밀키 형식이 잘못되었습니다.")?;
+            let ad = from_hex(ad)?;
+            let sk = double_ratchet::initial_secret(&alice_prekey, &a, &b)?;
+            let mut sending = Ratchet::initiate_with_key(sk, &bob, &bob_pre, ratchet_key)?;
+            let bytes = sending.encrypt_with_nonce(&ad, plaintext.as_bytes(), nonce_bytes(nonce)?)?;
+            let sk = double_ratchet::initial_secret(&bob_prekey, &b, &a)?;
+            let mut receiving = Ratchet::respond(sk, &alice, &bob_prekey);
+            if receiving.decrypt(&ad, &bytes)?.as_slice() != plaintext.as_bytes() {
+                return Err("응답자가 복호화한 평문이 다릅니다.".to_string());
+            }
+            *message = to_hex(&bytes);
+        }
+        Vector::Treekem { nick, init_secret, welcome, commits, epoch, members, sender, nonce, plaintext, message, .. } => {
+            let init_key = p256::SecretKey::from_slice(&from_hex(init_secret)?).map_err(|_| "초대 비밀키 형식이 잘못되었습니다.")?;
+            let mut group = Group::join(&from_hex(welcome)?, nick, &init_key)?;
+            for commit in commits.iter() {
+                group.process_commit(&from_hex(commit)?)?;
+            }
+            *epoch = group.epoch();
+            *members = group.members();
+            let bytes = group.seal_with_nonce(sender, plaintext.as_bytes(), nonce_bytes(nonce)?)?;
+            if group.open(sender, &bytes)? != plaintext.as_bytes() {
+                return Err("복호화한 그룹 메시지가 다릅니다.".to_string());
+            }
+            *message = to_hex(&bytes);
+        }
+    }
+    Ok(out)
+}
+
+fn seed(text: &str) -> Result<[u8; 32], String> {
+    from_hex(text)?.try_into().map_err(|_| "시드는 32바이트여야 합니다.".to_string())
+}
+
+fn nonce_bytes(text: &str) -> Result<[u8; 12], String> {
+    from_hex(text)?.try_into().map_err(|_| "nonce는 12바이트여야 합니다.".to_string())
+}
+
+fn probe_line(key: &AesKey) -> String {
+    packet::seal_with_nonce(&key.cipher(), PROBE_NONCE, PROBE)
+}
+
+fn aes_key(text: &str) -> Result<AesKey, String> {
+    let bytes: [u8; 32] = from_hex(text)?.try_into().map_err(|_| "키는 32바이트여야 합니다.")?;
+    Ok(AesKey::from_bytes(Zeroizing::new(bytes)))
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) {
+        return Err(format!("16진수 길이가 홀수입니다: {}", text));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| format!("16진수가 아닙니다: {}", text)))
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// ==========================================
+// [세션 기록 재생]
+// ==========================================
+
+// 서버에게서 받은(또는 받아야 할) 것 한 가지
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Received {
+    // 로그인 응답 (세션 키로 암호화된 평문)
+    Sealed(String),
+    // 방 메시지 (보낸 사람, Room Key로 암호화된 평문)
+    Room(String, String),
+    // 그 밖의 줄 (시각은 지움)
+    Line(String),
+}
+
+impl std::fmt::Display for Received {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Received::Sealed(text) => write!(f, "(로그인 응답) {}", text),
+            Received::Room(from, text) => write!(f, "(방) {}: {}", from, text),
+            Received::Line(line) => write!(f, "{}", line),
+        }
+    }
+}
+
+// 기록 하나를 재생하기 위해 나눈 것
+struct Script {
+    // 기록이 시작된 시각 (여러 기록의 시작을 맞추는 데 사용)
+    start: u64,
+    login: String,
+    // (기록된 시각, 보낼 것)
+    sends: VecDeque<(u64, Layer, String)>,
+    expected: Vec<Received>,
+}
+
+impl Script {
+    fn from_events(events: Vec<Event>) -> Result<Self, String> {
+        let start = events.first().ok_or("기록이 비어 있습니다.")?.ms;
+        // 핸드셰이크 줄은 새로 만들어야 하므로 로그인 요청 전까지는 건너뜀
+        let login_at = events
+            .iter()
+            .position(|e| e.dir == Dir::Send && e.layer == Layer::Sealed)
+            .ok_or("로그인 요청이 없습니다. (chatclient --record 로 남긴 ECDH 세션만 재생할 수 있습니다)")?;
+        let login = events[login_at].text.clone();
+
+        let mut sends = VecDeque::new();
+        let mut expected = vec![];
+        // 로그인 응답 다음 줄은 감싼 Room Key (새 세션에서는 값이 다르므로 비교하지 않음)
+        let mut skip_room_key = false;
+        for event in events.into_iter().skip(login_at + 1) {
+            match (event.dir, event.layer) {
+                (Dir::Send, layer) => sends.push_back((event.ms, layer, event.text)),
+                (Dir::Recv, Layer::Sealed) => {
+                    expected.push(Received::Sealed(event.text));
+                    skip_room_key = true;
+                }
+                (Dir::Recv, Layer::Raw) if skip_room_key => skip_room_key = false,
+                (Dir::Recv, Layer::Raw) => expected.push(Received::Line(normalize(&event.text))),
+                // 복호화한 방 메시지 기록은 바로 앞에 기록된 원래 줄을 대신함
+                (Dir::Recv, Layer::Room) => {
+                    expected.pop();
+                    let from = event.from.unwrap_or_default();
+                    let text = normalize_room(&from, &event.text);
+                    expected.push(Received::Room(from, text));
+                }
+            }
+        }
+        Ok(Self { start, login, sends, expected })
+    }
+}
+
+async fn replay_all(paths: &[PathBuf], server: &str, settle_ms: u64) -> Result<(), String> {
+    let mut scripts = vec![];
+    for path in paths {
+        scripts.push((path.clone(), Script::from_events(transcript::load(path)?).map_err(|e| format!("{}: {}", path.display(), e))?));
+    }
+    let base = scripts.iter().map(|(_, script)| script.start).min().unwrap_or(0);
+    let began = Instant::now();
+
+    let mut tasks = JoinSet::new();
+    for (index, (path, script)) in scripts.into_iter().enumerate() {
+        let server = server.to_string();
+        tasks.spawn(async move {
+            let offset = |ms: u64| began + Duration::from_millis(ms.saturating_sub(base));
+            tokio::time::sleep_until(offset(script.start)).await;
+            let expected = script.expected.clone();
+            let result = replay(&server, script, offset, Duration::from_millis(settle_ms)).await;
+            (index, path, expected, result)
+        });
+    }
+    let mut results: Vec<_> = tasks.join_all().await;
+    results.sort_by_key(|(index, ..)| *index);
+
+    let mut failed = 0;
+    for (_, path, expected, result) in results {
+        match result {
+            Ok(actual) => {
+                let diffs = compare(&expected, &actual);
+                if diffs.is_empty() {
+                    println!("✅ {}: 서버 응답 {}개가 기록과 같습니다.", path.display(), actual.len());
+                } else {
+                    failed += 1;
+                    println!("❌ {}: 기록과 다른 응답이 있습니다.", path.display());
+                    for diff in diffs.iter().take(MAX_DIFFS) {
+                        println!("   {}", diff);
+                    }
+                    if diffs.len() > MAX_DIFFS {
+                        println!("   ... 외 {}개", diffs.len() - MAX_DIFFS);
+                    }
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                println!("❌ {}: {}", path.display(), e);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("기록 {}개의 재생 결과가 다릅니다.", failed));
+    }
+    Ok(())
+}
+
+// 기록 하나를 재생하고 서버에게서 받은 것을 돌려줌
+async fn replay(
+    server: &str,
+    mut script: Script,
+    offset: impl Fn(u64) -> Instant,
+    settle: Duration,
+) -> Result<Vec<Received>, String> {
+    let socket = TcpStream::connect(server).await.map_err(|e| format!("{}에 접속할 수 없습니다: {}", server, e))?;
+    let (reader, writer) = tokio::io::split(socket);
+    let mut conn = LineConn::new(BufReader::new(reader), writer, MAX_LINE_BYTES);
+    let io = |e: std::io::Error| e.to_string();
+
+    // chatclient와 같은 ECDH 핸드셰이크와 닉네임 로그인
+    let server_pub = general_purpose::STANDARD
+        .decode(conn.recv_line().await.map_err(io)?.trim())
+        .map_err(|_| "서버 공개키 형식이 잘못되었습니다.")?;
+    let ecdh = EcdhKey::create();
+    conn.send_line(&general_purpose::STANDARD.encode(ecdh.public_key_bytes())).await.map_err(io)?;
+    let session_cipher = ecdh.derive_aes_key(&server_pub)?.cipher();
+    conn.send_sealed(&session_cipher, script.login.as_bytes()).await.map_err(io)?;
+
+    let reply = conn.recv_sealed_text(&session_cipher).await.map_err(io)?;
+    let mut actual = vec![Received::Sealed(reply.to_string())];
+    let field = |tag: &str| reply.split(' ').find_map(|field| field.strip_prefix(tag)).map(str::to_string);
+    let padding: Padding = field(padding::PADDING_TAG).map_or(Ok(Padding::None), |policy| policy.parse())?;
+    let (Some(sender), Some(rekey_after)) = (
+        field(cipherstate::SENDER_TAG).and_then(|n| n.parse().ok()),
+        field(cipherstate::REKEY_TAG).and_then(|n| n.parse().ok()),
+    ) else {
+        return Ok(actual);
+    };
+    let room_key = AesKey::unwrap(&session_cipher, &conn.recv_line().await.map_err(io)?)?;
+    let mut room = CipherState::new(&room_key, sender, rekey_after)?;
+    drop(room_key);
+
+    // 기록된 시각에 맞춰 보내면서, 그 사이에 온 줄을 모음. 다 보낸 뒤에는 settle 동안 조용하면 끝
+    let mut quiet_until = Instant::now() + settle;
+    loop {
+        let next_send = script.sends.front().map(|(ms, ..)| offset(*ms));
+        let deadline = next_send.unwrap_or(quiet_until);
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => {
+                let Some((_, layer, text)) = script.sends.pop_front() else { break };
+                match layer {
+                    Layer::Room => {
+                        let line = room.seal(&padding.pad(text.as_bytes()))?;
+                        conn.send_recorded(&line, Layer::Room, text.as_bytes()).await.map_err(io)?;
+                    }
+                    Layer::Sealed => conn.send_sealed(&session_cipher, text.as_bytes()).await.map_err(io)?,
+                    Layer::Raw => conn.send_line(&text).await.map_err(io)?,
+                }
+                quiet_until = Instant::now() + settle;
+            }
+            frame = conn.recv_frame() => match frame.map_err(io)? {
+                Frame::Line(line) => {
+                    actual.push(classify(&line, &mut room, padding));
+                    quiet_until = quiet_until.max(Instant::now() + settle);
+                }
+                Frame::TooLong(len) => actual.push(Received::Line(format!("(너무 긴 줄 {} 바이트)", len))),
+                Frame::Eof => break,
+            },
+        }
+    }
+    Ok(actual)
+}
+
+// 받은 줄이 방 메시지("[보낸 사람]: <패킷> t=<시각>")이고 복호화되면 평문으로, 아니면 시각을 지운 줄로
+fn classify(line: &str, room: &mut CipherState, padding: Padding) -> Received {
+    if let Some((from, content)) = envelope::parse_room_line(line) {
+        let (packet, _) = envelope::split_timestamp(content);
+        if let Ok(plaintext) = room.open(packet).and_then(|(_, pt)| padding.unpad(pt)) {
+            return Received::Room(from.to_string(), normalize_room(from, &String::from_utf8_lossy(&plaintext)));
+        }
+    }
+    Received::Line(normalize(line))
+}
+
+// 재생할 때마다 달라지는 서버 시각을 지움: " t=<밀리초>", "ACK <ID> <밀리초>"
+fn normalize(line: &str) -> String {
+    let is_time = |word: &str| word.len() >= 10 && word.bytes().all(|b| b.is_ascii_digit());
+    line.split(' ')
+        .map(|word| match word.strip_prefix("t=") {
+            Some(ts) if is_time(ts) => format!("t={}", ANY_TIME),
+            _ if is_time(word) => ANY_TIME.to_string(),
+            _ => word.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// 서버가 보낸 방 정보 "\0ROOM <JSON>"에서 방을 만든 시각과 토픽을 바꾼 시각을 지움
+fn normalize_room(from: &str, text: &str) -> String {
+    let json = text.strip_prefix(metadata::ROOM_PREFIX).filter(|_| from == metadata::SERVER_NICK);
+    let Some(mut info) = json.and_then(|json| serde_json::from_str::<serde_json::Value>(json).ok()) else {
+        return text.to_string();
+    };
+    for pointer in ["/created", "/topic/set_at"] {
+        if let Some(field) = info.pointer_mut(pointer) {
+            *field = ANY_TIME.into();
+        }
+    }
+    format!("{}{}", metadata::ROOM_PREFIX, info)
+}
+
+// 순서대로 비교해서 다른 곳을 "- 기록 / + 재생" 형식으로 돌려줌
+fn compare(expected: &[Received], actual: &[Received]) -> Vec<String> {
+    let mut diffs = vec![];
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => {}
+            (e, a) => {
+                if let Some(e) = e {
+                    diffs.push(format!("#{} - {}", i + 1, e));
+                }
+                if let Some(a) = a {
+                    diffs.push(format!("#{} + {}", i + 1, a));
+                }
+            }
+        }
+    }
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 저장소의 테스트 벡터가 모두 그대로 재현되어야 함 (chatconform vectors 와 같은 검사)
+    #[test]
+    fn protocol_v1_vectors_reproduce() {
+        let file: VectorFile = serde_json::from_str(include_str!("../../vectors/protocol-v1.json")).expect("벡터 파일 형식");
+        assert_eq!(file.version, 1);
+        for vector in &file.vectors {
+            let computed = compute(vector).unwrap_or_else(|e| panic!("{}: {}", vector.name(), e));
+            assert_eq!(computed, *vector, "{}", vector.name());
+        }
+        // 종류마다 벡터가 하나 이상 있어야 함
+        for prefix in ["packet/", "handshake/", "room/", "padding/", "envelope/", "federation/", "mlkem/", "double-ratchet/", "treekem/"] {
+            assert!(file.vectors.iter().any(|v| v.name().starts_with(prefix)), "{} 벡터가 없습니다.", prefix);
+        }
+    }
+
+    // 기대값을 하나라도 바꾸면 검사에 걸려야 함
+    #[test]
+    fn tampered_vector_is_detected() {
+        let file: VectorFile = serde_json::from_str(include_str!("../../vectors/protocol-v1.json")).expect("벡터 파일 형식");
+        for mut vector in file.vectors {
+            match &mut vector {
+                Vector::Mlkem { shared: out, .. }
+                | Vector::DoubleRatchet { message: out, .. }
+                | Vector::Treekem { message: out, .. }
+                | Vector::Packet { line: out, .. } => out.replace_range(0..2, if out.starts_with("00") { "01" } else { "00" }),
+                _ => continue,
+            }
+            assert_ne!(compute(&vector).unwrap(), vector, "{}", vector.name());
+        }
+    }
+}
@@ -1,135 +1,1702 @@
 // src/bin/server.rs
 
-use tokio::net::TcpListener;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::broadcast;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use aes_gcm::{aead::{Aead, KeyInit}, Aes256Gcm, Nonce};
-use aes_gcm::AeadCore;
+use tokio::task::JoinSet;
+use tokio_rustls::TlsAcceptor;
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, BufReader};
+use aes_gcm::Aes256Gcm;
 use base64::{engine::general_purpose, Engine as _};
+use clap::{Parser, Subcommand};
+use hkdf::Hkdf;
 use rand::{rngs::OsRng, RngCore};
+use sha2::Sha256;
 
 // ecdh.rs 파일을 모듈로 불러옵니다. (파일 경로가 ../ecdh.rs 라고 가정)
 #[path = "../ecdh/ecdhkey.rs"]
+#[allow(dead_code)] // 고정된 비밀키로 만들기(from_secret_bytes)는 chatconform에서만 사용
 mod ecdhkey;
 //mod ecdh;
 //use ecdh::ecdhkey;
+#[path = "../ecdh/secret.rs"]
+#[allow(dead_code)] // Room Key 복원(unwrap)은 chatclient에서만 사용
+mod secret;
+#[path = "../proto/conn.rs"]
+#[allow(dead_code)] // 세션 기록(transcript)은 chatclient에서만 사용
+mod conn;
+#[path = "../proto/transcript.rs"]
+#[allow(dead_code)] // 기록 파일 읽기(load)는 chatconform에서만 사용
+mod transcript;
+#[path = "../proto/frame.rs"]
+mod frame;
+#[path = "../pq/mlkem.rs"]
+#[allow(dead_code)] // 키 생성과 캡슐 해제는 chatclient에서만 사용
+mod mlkem;
+#[path = "../transport/tls.rs"]
+#[allow(dead_code)] // 클라이언트 쪽 연결(connector)은 chatclient에서만 사용
+mod tls;
+#[path = "../transport/quic.rs"]
+#[allow(dead_code)] // 접속하는 쪽(QuicClient)은 chatclient에서만 사용
+mod quic;
+#[path = "../transport/listener.rs"]
+mod listener;
+#[path = "../proto/noise.rs"]
+#[allow(dead_code)] // 접속 시작(initiate) 쪽은 chatclient에서만 사용
+mod noise;
+#[path = "../proto/envelope.rs"]
+#[allow(dead_code)] // 봉투 생성은 chatclient에서만 사용
+mod envelope;
+#[path = "../proto/packet.rs"]
+mod packet;
+#[path = "../proto/cipherstate.rs"]
+#[allow(dead_code)] // 세대 확인(epoch)은 chatclient에서만 사용
+mod cipherstate;
+#[path = "../proto/padding.rs"]
+mod padding;
+#[path = "../limits/ratelimit.rs"]
+mod ratelimit;
+#[path = "../limits/metrics.rs"]
+mod metrics;
+#[path = "../auth/accounts.rs"]
+mod accounts;
+#[path = "../offline/queue.rs"]
+mod queue;
+#[path = "../federation/link.rs"]
+mod link;
+#[path = "../identity/fingerprint.rs"]
+#[allow(dead_code)] // 사용자 지문은 chatclient에서만 사용
+mod fingerprint;
+#[path = "../pake/spake2.rs"]
+#[allow(dead_code)] // 클라이언트 역할(A)은 chatclient에서만 사용
+mod spake2;
+#[path = "../room/metadata.rs"]
+#[allow(dead_code)] // 방 정보 해석(from_control)은 chatclient에서만 사용
+mod metadata;
+#[path = "../room/keystore.rs"]
+mod keystore;
+
+use accounts::AccountStore;
+use cipherstate::CipherState;
+use conn::LineConn;
+use envelope::{Envelope, Kind};
+use frame::Frame;
+use keystore::Keystore;
+use link::{Body, FedMessage, Presence, Seen};
+use listener::{Listener, PeerAddr};
+use metadata::{RoomInfo, Rooms};
+use metrics::Metrics;
+use noise::StaticKey;
+use padding::Padding;
+use queue::{Delivery, OfflineQueue};
+use ratelimit::{AbusePolicy, TokenBucket, Verdict, Violation};
+use secret::AesKey;
+use spake2::{Role, Spake2};
+use zeroize::Zeroizing;
+
+// SPAKE2에서 서버 쪽 신원(B)으로 쓰는 값
+const SPAKE2_SERVER_ID: &[u8] = b"chatserver";
+// 하이브리드 키 교환에서 ML-KEM 값 앞에 붙는 표시 (chatclient와 같아야 함)
+const MLKEM_TAG: &str = "mlkem768=";
+// 연합 링크가 끊기거나 연결에 실패했을 때 다시 연결하기까지 기다리는 시간
+const PEER_RETRY: Duration = Duration::from_secs(5);
+
+#[derive(Parser, Debug)]
+#[command(name = "chatserver", about = "ECDH 키 교환 + AES-GCM 채팅 서버")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    config: Config,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 계정 파일 관리 (비밀번호는 Argon2id 해시로 저장)
+    User {
+        /// 계정 파일 경로
+        #[arg(long, default_value = "accounts.txt")]
+        accounts: PathBuf,
+
+        #[command(subcommand)]
+        action: UserAction,
+    },
+    /// 테스트용 TLS 인증서 생성 (자체 서명 CA + 그 CA가 서명한 서버 인증서)
+    GenCerts {
+        /// ca.pem, server.pem, server.key를 저장할 디렉터리
+        #[arg(long, default_value = "certs")]
+        out_dir: PathBuf,
+
+        /// 서버 인증서에 넣을 이름 (DNS 이름 또는 IP 주소, 여러 번 지정 가능)
+        #[arg(long = "name", default_values_t = ["localhost".to_string(), "127.0.0.1".to_string()])]
+        names: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum UserAction {
+    /// 새 계정 추가
+    Add {
+        nick: String,
+        /// 비밀번호 해시 대신 SPAKE2 검증값을 저장 (PAKE 로그인 전용 계정)
+        #[arg(long)]
+        pake: bool,
+    },
+    /// 계정 삭제
+    Remove { nick: String },
+    /// 비밀번호 변경
+    Passwd {
+        nick: String,
+        /// 비밀번호 해시 대신 SPAKE2 검증값을 저장 (PAKE 로그인 전용 계정)
+        #[arg(long)]
+        pake: bool,
+    },
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum HandshakeMode {
+    /// 직접 만든 P-256 ECDH 핸드셰이크
+    Ecdh,
+    /// Noise_XX / Noise_IK (25519, ChaChaPoly, BLAKE2s). 이후 모든 줄이 Noise 전송 상태로 암호화됨
+    Noise,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+struct Config {
+    /// 바인딩할 TCP 주소. --unix 나 소켓 활성화 없이 실행하면 기본값 127.0.0.1:8080 사용
+    #[arg(long)]
+    bind: Option<String>,
+
+    /// 유닉스 도메인 소켓 경로. 같은 컴퓨터의 사용자만 접속할 수 있고, 파일 권한으로 접근을 제한
+    #[arg(long)]
+    unix: Option<PathBuf>,
+
+    /// 유닉스 소켓 파일 권한 (8진수)
+    #[arg(long, default_value = "660", value_parser = parse_mode)]
+    unix_mode: u32,
+
+    /// TLS 서버 인증서 체인 (PEM). 지정하면 모든 접속을 TLS로 받음
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// TLS 서버 비밀키 (PEM)
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// QUIC으로도 접속을 받을 UDP 주소 (예: 127.0.0.1:8443). --quic-cert/--quic-key 필요
+    #[arg(long, requires = "quic_cert")]
+    quic: Option<String>,
+
+    /// QUIC 서버 인증서 체인 (PEM). 테스트용은 `chatserver gen-certs`로 생성
+    #[arg(long, requires = "quic_key")]
+    quic_cert: Option<PathBuf>,
+
+    /// QUIC 서버 비밀키 (PEM)
+    #[arg(long, requires = "quic_cert")]
+    quic_key: Option<PathBuf>,
+
+    /// 전송 보안 핸드셰이크 방식
+    #[arg(long, value_enum, default_value_t = HandshakeMode::Ecdh)]
+    handshake: HandshakeMode,
+
+    /// Noise 정적 비밀키 파일 (없으면 생성). 생략하면 실행할 때마다 새 키 사용
+    #[arg(long, conflicts_with = "keystore")]
+    noise_key: Option<PathBuf>,
+
+    /// Room Key와 서버 신원 키(Noise 정적 키)를 암호화해서 보관할 키 저장소 파일 (없으면 생성). 지정하면 서버를 다시 시작해도 같은 키를 씀
+    #[arg(long)]
+    keystore: Option<PathBuf>,
+
+    /// 키 저장소를 여는 키 암호화 키(KEK) 파일. 생략하면 시작할 때 키 저장소 암호를 물어봄
+    #[arg(long, requires = "keystore")]
+    keystore_kek: Option<PathBuf>,
+
+    /// 계정 파일 경로. 지정하면 등록된 닉네임은 비밀번호 로그인으로만 사용할 수 있음
+    #[arg(long)]
+    accounts: Option<PathBuf>,
+
+    /// 오프라인 큐 파일 경로. 지정하면 접속하지 않은 등록 사용자에게 온 귓속말과 멘션을 보관했다가 다음 로그인 때 전달
+    #[arg(long, requires = "accounts")]
+    offline_queue: Option<PathBuf>,
+
+    /// 사용자별 오프라인 큐의 최대 크기(바이트). 넘으면 오래된 메시지부터 버림
+    #[arg(long, default_value_t = 256 * 1024)]
+    offline_max_bytes: usize,
+
+    /// 오프라인 큐에 메시지를 보관하는 최대 기간(시간)
+    #[arg(long, default_value_t = 168)]
+    offline_max_age_hours: u64,
+
+    /// 접속한 사용자에게 핸드셰이크 직후 보여줄 공지(MOTD) 파일
+    #[arg(long)]
+    motd: Option<PathBuf>,
+
+    /// 방 설정과 메타데이터(만든 시각, 토픽) 파일. 지정하면 서버를 다시 시작해도 토픽이 유지됨
+    #[arg(long)]
+    rooms: Option<PathBuf>,
+
+    /// /topic 으로 방 토픽을 바꿀 수 있는 운영자 닉네임 (여러 번 지정 가능). 계정 파일에 등록된 닉네임으로 로그인했을 때만 인정
+    #[arg(long = "operator", requires = "accounts")]
+    operators: Vec<String>,
+
+    /// 연합(federation)에서 이 서버를 부르는 이름. 생략하면 임의로 만듦
+    #[arg(long)]
+    server_name: Option<String>,
+
+    /// 다른 chatserver의 연합 링크를 받을 TCP 주소 (예: 127.0.0.1:9080)
+    #[arg(long, requires = "federation_secret")]
+    federation_bind: Option<String>,
+
+    /// 연합 링크로 연결할 다른 chatserver 주소 (여러 번 지정 가능). 링크가 끊기면 다시 연결함
+    /// (접속 상태는 링크들이 고리 없이 나무 모양일 때 정확함)
+    #[arg(long = "peer", requires = "federation_secret")]
+    peers: Vec<String>,
+
+    /// 연합 비밀 파일. 같은 비밀을 가진 서버끼리만 링크됨
+    #[arg(long)]
+    federation_secret: Option<PathBuf>,
+
+    /// 방 메시지 길이 숨김 정책: none, pow2(2의 거듭제곱), block:<N>(N바이트 배수). 접속한 클라이언트도 같은 정책을 따름
+    #[arg(long, default_value_t = Padding::None)]
+    padding: Padding,
+
+    /// 발신자 한 명이 Room Key에서 유도한 한 키로 보낼 수 있는 최대 메시지 수. 닿으면 다음 세대 키로 바꿈
+    #[arg(long, default_value_t = 1 << 24, value_parser = clap::value_parser!(u32).range(1..))]
+    rekey_after: u32,
+
+    /// 한 줄(프레임)의 최대 길이(바이트). 넘으면 버리고 위반으로 기록
+    #[arg(long, default_value_t = 16 * 1024)]
+    max_line_bytes: usize,
+
+    /// 연결당 초당 허용 메시지 수 (토큰 버킷 충전 속도)
+    #[arg(long, default_value_t = 5.0)]
+    rate: f64,
+
+    /// 순간적으로 몰아서 보낼 수 있는 최대 메시지 수 (토큰 버킷 크기)
+    #[arg(long, default_value_t = 10)]
+    burst: u32,
+
+    /// 이 횟수까지의 위반은 경고만 함
+    #[arg(long, default_value_t = 3)]
+    warn_strikes: u32,
+
+    /// 이 횟수까지의 위반은 메시지를 버리고, 넘으면 연결을 끊음
+    #[arg(long, default_value_t = 10)]
+    drop_strikes: u32,
+
+    /// 통계 로그 출력 주기(초). 0이면 출력하지 않음
+    #[arg(long, default_value_t = 60)]
+    metrics_interval: u64,
+}
+
+// 모든 연결 태스크가 공유하는 서버 상태
+struct ServerState {
+    config: Config,
+    // Room Key는 여기 한 곳에만 보관 (모든 태스크가 Arc로 공유)
+    room_key: AesKey,
+    // Room Key 암호화 상태 (서버 로그용 복호화와, 서버가 다시 암호화해서 보내는 메시지)
+    room: Mutex<CipherState>,
+    // 로그인한 연결에 나눠 줄 다음 발신자 번호 (nonce가 겹치지 않게 연결마다 다름)
+    next_sender: AtomicU32,
+    tx: broadcast::Sender<Relay>,
+    metrics: Metrics,
+    // 현재 접속 중인 닉네임 (중복 사용 방지)
+    online: Mutex<HashSet<String>>,
+    // 없는 닉네임의 PAKE 로그인 시도에 가짜 솔트를 만들 때 쓰는 비밀값
+    fake_salt_secret: Zeroizing<[u8; 32]>,
+    // Noise 핸드셰이크를 쓸 때의 서버 정적 키
+    noise_key: Option<StaticKey>,
+    // Room Key와 서버 정적 키, 발신자 번호와 세대의 사용 기록 (--keystore)
+    keystore: Option<Mutex<Keystore>>,
+    // 계정 파일을 읽은 결과와 그때의 수정 시각 (--accounts, 파일이 바뀌면 다시 읽음)
+    accounts: Mutex<Option<(Option<SystemTime>, Arc<AccountStore>)>>,
+    // 접속하지 않은 등록 사용자에게 온 메시지 보관함 (--offline-queue)
+    offline: Option<Mutex<OfflineQueue>>,
+    // 다른 chatserver들과 방/귓속말/그룹 메시지와 접속 상태를 주고받는 연합 (--federation-secret)
+    federation: Option<Federation>,
+    // 접속 직후 보내는 공지 (--motd)
+    motd: Option<String>,
+    // 방 설정과 메타데이터 (--rooms)
+    rooms: Mutex<Rooms>,
+}
+
+struct Federation {
+    name: String,
+    secret: Zeroizing<Vec<u8>>,
+    // 링크들로 내보낼 메시지와, 그 메시지가 들어온 링크 번호 (그 링크로는 되돌려 보내지 않음)
+    tx: broadcast::Sender<(FedMessage, Option<u64>)>,
+    // 다른 서버에 접속한 사용자
+    presence: Mutex<Presence>,
+    seen: Mutex<Seen>,
+    // 지금 링크된 서버 이름 → 링크 번호 (같은 서버와 링크가 두 개 생기지 않게)
+    links: Mutex<HashMap<String, u64>>,
+    next_link: AtomicU64,
+}
+
+// 연결 태스크끼리 주고받는 전달 메시지. to가 있으면 그 닉네임에게만 전달 (귓속말)
+// echo면 보낸 사람에게도 되돌려 보냄 (그룹 메시지의 순서를 모두가 똑같이 보도록)
+// from이 None이면 연합 링크로 들어온 메시지나 서버 안내
+#[derive(Clone, Debug)]
+struct Relay {
+    line: String,
+    from: Option<PeerAddr>,
+    to: Option<String>,
+    echo: bool,
+}
+
+// 연결이 끊기면 닉네임 점유를 자동으로 해제
+struct NickGuard {
+    state: Arc<ServerState>,
+    nick: String,
+}
+
+impl Drop for NickGuard {
+    fn drop(&mut self) {
+        self.state.online.lock().unwrap().remove(&self.nick);
+        federate(&self.state, Body::Part { nick: self.nick.clone() });
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let listener = TcpListener::bind("127.0.0.1:8080").await?;
-    println!("🚀 채팅 서버(ECDH Key Exchange)가 시작되었습니다.");
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::User { accounts, action }) => return manage_user(&accounts, action),
+        Some(Command::GenCerts { out_dir, names }) => {
+            tls::generate_certs(&out_dir, &names)?;
+            println!("📜 {}에 ca.pem, server.pem, server.key를 만들었습니다.", out_dir.display());
+            return Ok(());
+        }
+        None => {}
+    }
+    run(cli.config).await
+}
+
+// 설정대로 리스너와 연합 링크를 열고, 접속 수락 루프가 끝날 때까지 서버를 실행
+async fn run(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    // systemd가 넘겨준 소켓, 유닉스 소켓, TCP 주소 순으로 리스너 준비 (모두 같은 방식으로 접속을 처리)
+    let mut listeners = Listener::from_systemd()?;
+    if let Some(path) = &config.unix {
+        listeners.push(Listener::bind_unix(path, config.unix_mode)?);
+    }
+    let mut quic_listener = None;
+    if let (Some(addr), Some(cert), Some(key)) = (&config.quic, &config.quic_cert, &config.quic_key) {
+        quic_listener = Some(Listener::quic(quic::server_endpoint(addr, cert, key)?));
+    }
+    match &config.bind {
+        Some(addr) => listeners.push(Listener::bind_tcp(addr).await?),
+        None if listeners.is_empty() && quic_listener.is_none() => listeners.push(Listener::bind_tcp("127.0.0.1:8080").await?),
+        None => {}
+    }
+    let names: Vec<String> = listeners.iter().chain(&quic_listener).map(Listener::describe).collect();
+    println!("🚀 채팅 서버(ECDH Key Exchange)가 시작되었습니다. ({})", names.join(", "));
+    if let Some(path) = &config.accounts {
+        // 시작할 때 한 번 읽어서 파일 형식 오류를 바로 알림
+        AccountStore::load(path)?;
+        println!("👤 계정 파일 사용: {}", path.display());
+    }
+    let offline = match &config.offline_queue {
+        Some(path) => {
+            let max_age_ms = (config.offline_max_age_hours * 3600 * 1000) as i64;
+            let queue = OfflineQueue::load(path, config.offline_max_bytes, max_age_ms)?;
+            println!("📮 오프라인 큐 사용: {}", path.display());
+            Some(Mutex::new(queue))
+        }
+        None => None,
+    };
+    let federation = match &config.federation_secret {
+        Some(path) => {
+            let name = match &config.server_name {
+                Some(name) if accounts::valid_nick(name) => name.clone(),
+                Some(name) => return Err(format!("사용할 수 없는 서버 이름입니다: {}", name).into()),
+                None => format!("chatserver-{:04x}", OsRng.next_u32() as u16),
+            };
+            let secret = Zeroizing::new(std::fs::read(path).map_err(|e| format!("연합 비밀 파일을 읽을 수 없습니다: {}", e))?);
+            if secret.is_empty() {
+                return Err("연합 비밀 파일이 비어 있습니다.".into());
+            }
+            println!("🌐 연합 사용: 서버 이름 {}", name);
+            Some(Federation {
+                name,
+                secret,
+                tx: broadcast::channel(256).0,
+                presence: Mutex::new(Presence::default()),
+                seen: Mutex::new(Seen::default()),
+                links: Mutex::new(HashMap::new()),
+                next_link: AtomicU64::new(1),
+            })
+        }
+        None => None,
+    };
+    if config.padding != Padding::None {
+        println!("📦 방 메시지 패딩: {}", config.padding);
+    }
+    let motd = match &config.motd {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).map_err(|e| format!("MOTD 파일을 읽을 수 없습니다: {}", e))?;
+            Some(text.trim_end().to_string())
+        }
+        None => None,
+    };
+    let mut rooms = Rooms::load(config.rooms.as_deref())?;
+    // 이 서버의 방은 아래에서 만드는 Room Key로 암호화되므로 암호화된 방으로 기록
+    rooms.open(metadata::MAIN_ROOM, &config.padding.to_string(), config.rekey_after, true, envelope::now_millis())?;
+    if let Some(path) = &config.rooms {
+        println!("🏷️ 방 설정 파일 사용: {}", path.display());
+    }
+    let tls_acceptor = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => {
+            let acceptor = tls::acceptor(cert, key)?;
+            println!("🔒 TLS 사용: {}", cert.display());
+            Some(acceptor)
+        }
+        _ => None,
+    };
+
+    let mut keystore = match &config.keystore {
+        Some(path) => {
+            let secret = match &config.keystore_kek {
+                Some(kek) => Zeroizing::new(std::fs::read(kek).map_err(|e| format!("KEK 파일을 읽을 수 없습니다: {}", e))?),
+                None => prompt_keystore_passphrase(!path.exists())?,
+            };
+            if secret.is_empty() {
+                return Err("키 저장소 암호(KEK)가 비어 있습니다.".into());
+            }
+            let keystore = Keystore::open(path, &secret)?;
+            println!("🗝️ 키 저장소 사용: {}", path.display());
+            Some(keystore)
+        }
+        None => None,
+    };
 
     // 1. 서버 실행 시, 채팅방 전용 랜덤 키(Room Key) 생성 (이 키로 대화함)
-    let mut room_key_bytes = [0u8; 32];
-    OsRng.fill_bytes(&mut room_key_bytes);
-    let room_key_vec = room_key_bytes.to_vec(); // 클론하여 태스크로 넘기기 위해 Vec 사용
-    
-    // 서버 로그용 복호화 객체
-    let server_room_cipher = Aes256Gcm::new(&room_key_bytes.into());
+    //    키 저장소를 쓰면 지난 실행의 Room Key를 그대로 쓰고, 발신자 번호와 서버의 세대는 지난 실행에서 쓴 것 다음부터 시작
+    let (room_key, first_sender, server_epoch) = match &mut keystore {
+        Some(keystore) => {
+            let (key, first_sender, epoch) = keystore.open_room(metadata::MAIN_ROOM)?;
+            println!("🗝️ 키 저장소의 Room Key 사용 (발신자 번호 {}, 서버 세대 {}부터)", first_sender, epoch);
+            (key, first_sender, epoch)
+        }
+        None => (AesKey::random(), cipherstate::SERVER_SENDER + 1, 0),
+    };
+
+    let noise_key = match config.handshake {
+        HandshakeMode::Ecdh => None,
+        HandshakeMode::Noise => {
+            let key = match (&mut keystore, &config.noise_key) {
+                (Some(keystore), _) => keystore.noise_key()?,
+                (None, Some(path)) => StaticKey::load_or_create(path)?,
+                (None, None) => StaticKey::generate()?,
+            };
+            // 클라이언트가 --noise-server-key 로 고정(pin)하면 Noise_IK로 접속함
+            println!("🔐 Noise 핸드셰이크 사용. 서버 정적 공개키: {}", general_purpose::STANDARD.encode(key.public_key()));
+            Some(key)
+        }
+    };
 
+    let room = Mutex::new(CipherState::resume(&room_key, cipherstate::SERVER_SENDER, server_epoch, config.rekey_after)?);
     let (tx, _rx) = broadcast::channel(100);
+    let state = Arc::new(ServerState {
+        config,
+        room,
+        next_sender: AtomicU32::new(first_sender),
+        room_key,
+        tx,
+        metrics: Metrics::default(),
+        online: Mutex::new(HashSet::new()),
+        fake_salt_secret: {
+            let mut secret = Zeroizing::new([0u8; 32]);
+            OsRng.fill_bytes(secret.as_mut());
+            secret
+        },
+        noise_key,
+        keystore: keystore.map(Mutex::new),
+        accounts: Mutex::new(None),
+        offline,
+        federation,
+        motd,
+        rooms: Mutex::new(rooms),
+    });
+
+    // 남용 탐지 통계를 주기적으로 로그에 남김
+    if state.config.metrics_interval > 0 {
+        let state = state.clone();
+        let period = Duration::from_secs(state.config.metrics_interval);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                println!("📊 통계: {}", state.metrics.summary());
+            }
+        });
+    }
+
+    // 리스너마다 접속 수락 루프를 돌리고, 하나라도 실패하면 서버 종료
+    let mut accept_loops = JoinSet::new();
+    for listener in listeners {
+        accept_loops.spawn(accept_loop(state.clone(), listener, tls_acceptor.clone()));
+    }
+    // QUIC은 이미 TLS 1.3으로 암호화되어 있으므로 TLS로 한 번 더 감싸지 않음
+    if let Some(listener) = quic_listener {
+        accept_loops.spawn(accept_loop(state.clone(), listener, None));
+    }
+    // 연합 링크: 다른 서버의 링크를 받고, 지정한 서버들에는 직접 연결
+    if let Some(addr) = &state.config.federation_bind {
+        let listener = TcpListener::bind(addr).await.map_err(|e| format!("{}에 바인딩할 수 없습니다: {}", addr, e))?;
+        println!("🌐 연합 링크 대기: {}", addr);
+        accept_loops.spawn(federation_accept_loop(state.clone(), listener));
+    }
+    if state.federation.is_some() {
+        for peer in &state.config.peers {
+            tokio::spawn(federation_peer_loop(state.clone(), peer.clone()));
+        }
+    }
+    while let Some(result) = accept_loops.join_next().await {
+        result??;
+    }
+    Ok(())
+}
 
+async fn accept_loop(state: Arc<ServerState>, listener: Listener, tls_acceptor: Option<TlsAcceptor>) -> std::io::Result<()> {
     loop {
-        let (mut socket, addr) = listener.accept().await?;
+        let (socket, addr) = listener.accept().await?;
         println!("✨ 클라이언트 접속 시도: {}", addr);
+        Metrics::incr(&state.metrics.connections);
 
-        let tx = tx.clone();
-        let mut rx = tx.subscribe();
-        let room_key = room_key_vec.clone();
-        let server_room_cipher = server_room_cipher.clone();
+        match &tls_acceptor {
+            None => {
+                tokio::spawn(handle_client(state.clone(), socket, addr));
+            }
+            Some(acceptor) => {
+                // TLS 핸드셰이크도 접속마다 별도 태스크에서 진행 (느린 클라이언트가 accept 루프를 막지 않도록)
+                let acceptor = acceptor.clone();
+                let state = state.clone();
+                tokio::spawn(async move {
+                    match acceptor.accept(socket).await {
+                        Ok(stream) => handle_client(state, stream, addr).await,
+                        Err(e) => eprintln!("TLS 핸드셰이크 실패 [{}]: {}", addr, e),
+                    }
+                });
+            }
+        }
+    }
+}
 
-        tokio::spawn(async move {
-            let (reader, mut writer) = socket.split();
-            let mut reader = BufReader::new(reader);
-
-            // ==========================================
-            // [ECDH 핸드셰이크 단계]
-            // ==========================================
-            
-            // 1. 서버의 임시 키 쌍 생성
-            let server_ecdh = ecdhkey::EcdhKey::create();
-            let server_pub_b64 = general_purpose::STANDARD.encode(server_ecdh.public_key_bytes());
-            
-            // 2. 클라이언트에게 서버 공개키 전송
-            if let Err(_) = writer.write_all(format!("{}\n", server_pub_b64).as_bytes()).await {
-                return;
+// TCP 소켓이든 유닉스 소켓이든 TLS 스트림이든 같은 방식으로 처리
+async fn handle_client<S>(state: Arc<ServerState>, socket: S, addr: PeerAddr)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let config = &state.config;
+    let metrics = &state.metrics;
+    let tx = &state.tx;
+    let mut rx = tx.subscribe();
+
+    let (reader, writer) = tokio::io::split(socket);
+    let mut conn = LineConn::new(BufReader::new(reader), writer, config.max_line_bytes);
+
+    // ==========================================
+    // [핸드셰이크 단계 (ECDH 또는 Noise)]
+    // ==========================================
+    //   결과: 로그인/Room Key 전달에 쓸 세션 키, 그리고 이 핸드셰이크를 식별하는 바인딩 값
+    let handshake = match &state.noise_key {
+        None => ecdh_handshake(&mut conn, &addr).await,
+        Some(noise_key) => noise::accept(&mut conn, noise_key).await.map(|session| {
+            conn.set_transport(session.transport);
+            (session.session_key, session.handshake_hash)
+        }),
+    };
+    let (session_key, binding) = match handshake {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("키 교환 실패 [{}]: {}", addr, e);
+            return;
+        }
+    };
+
+    // ==========================================
+    // [로그인 단계 (Session Key로 암호화된 채널 안에서 진행)]
+    // ==========================================
+
+    // 5. "LOGIN <닉네임> <비밀번호>", "PAKE <닉네임>" 또는 익명 접속용 "NICK <닉네임>" 처리
+    //    PAKE 로그인이면 Room Key를 보낼 키에 PAKE 공유 비밀이 섞임
+    let (nick_guard, room_key_cipher, sender, operator) = match login(&state, &mut conn, &session_key, &binding).await {
+        Ok(result) => result,
+        Err(reason) => {
+            eprintln!("🚫 [{}] 로그인 거부: {}", addr, reason);
+            Metrics::incr(&metrics.auth_failures);
+            let session_cipher = session_key.cipher();
+            let _ = conn.send_sealed(&session_cipher, format!("ERR {}", reason).as_bytes()).await;
+            return;
+        }
+    };
+    let nick = nick_guard.nick.clone();
+
+    // 6. 'Room Key'를 암호화하여 클라이언트에게 전송
+    //    (이 과정이 끝나면 이제 둘 다 Room Key를 알게 됨)
+    if conn.send_line(&state.room_key.wrap(&room_key_cipher)).await.is_err() {
+        return;
+    }
+
+    println!("🔒 [{}] {} 로그인, 핸드셰이크 완료 및 Room Key 전달됨", addr, nick);
+    println!("🔑 [{}] 세션 번호: {}", nick, fingerprint::session_number(&binding));
+
+    // 7. 서버 공지(MOTD)와 방 정보(토픽, 접속자 수 등)
+    let mut notices: Vec<String> = state.motd.iter().map(|motd| format!("{}{}", metadata::MOTD_PREFIX, motd)).collect();
+    notices.push(room_info(&state).to_control());
+    for notice in notices {
+        let line = match server_line(&state, &notice) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("⚠️ [{}] 서버 공지를 암호화하지 못해 버림: {}", nick, e);
+                continue;
+            }
+        };
+        if conn.send_line(&line).await.is_err() {
+            return;
+        }
+    }
+
+    // 8. 접속하지 않은 동안 보관된 메시지 전달 (같은 메시지를 두 번 받으면 클라이언트가 ID로 걸러냄)
+    if deliver_offline(&state, &mut conn, &nick).await.is_err() {
+        return;
+    }
+
+    // ==========================================
+    // [메인 채팅 루프 (Room Key 사용)]
+    // ==========================================
+    let mut bucket = TokenBucket::new(config.rate, config.burst);
+    let mut policy = AbusePolicy::new(config.warn_strikes, config.drop_strikes);
+    loop {
+        tokio::select! {
+            // 메시지 수신 (암호화된 상태)
+            result = conn.recv_frame() => {
+                let (line, violation) = match result {
+                    Ok(Frame::Line(line)) => {
+                        let violation = (!bucket.try_take()).then_some(Violation::RateLimited);
+                        (Some(line), violation)
+                    }
+                    Ok(Frame::TooLong(len)) => {
+                        eprintln!("⚠️ [{}] 최대 길이 초과 프레임 수신: {} 바이트", addr, len);
+                        (None, Some(Violation::Oversized))
+                    }
+                    Ok(Frame::Eof) | Err(_) => break,
+                };
+
+                // 위반이 있으면 정책에 따라 경고/버림/연결 종료
+                if let Some(kind) = violation {
+                    Metrics::incr(match kind {
+                        Violation::RateLimited => &metrics.rate_limited,
+                        Violation::Oversized => &metrics.oversized,
+                    });
+                    let verdict = policy.record(kind);
+                    eprintln!("⚠️ [{}] {:?} 위반 {}회 → {:?}", addr, kind, policy.strikes(), verdict);
+
+                    let notice = match verdict {
+                        Verdict::Warn => "*** 경고: 메시지를 너무 빠르게 보내고 있습니다.",
+                        Verdict::Drop => "*** 경고: 제한을 넘은 메시지는 전달되지 않았습니다.",
+                        Verdict::Disconnect => "*** 제한을 반복해서 위반하여 연결을 종료합니다.",
+                    };
+                    let _ = conn.send_line(notice).await;
+
+                    match verdict {
+                        Verdict::Warn => Metrics::incr(&metrics.warned),
+                        Verdict::Drop => {
+                            Metrics::incr(&metrics.dropped);
+                            continue;
+                        }
+                        Verdict::Disconnect => {
+                            Metrics::incr(&metrics.disconnected);
+                            break;
+                        }
+                    }
+                }
+                let Some(line) = line else { continue };
+
+                let trimmed = line.trim();
+
+                // 귓속말 "@<받는 사람> <Double Ratchet 메시지>": 서버는 내용을 모르고 받는 사람에게만 전달
+                if let Some(dm) = trimmed.strip_prefix('@') {
+                    let Some((to, payload)) = dm.split_once(' ') else { continue };
+                    // 내용은 Base64 한 덩어리여야 함 (연합 링크와 오프라인 큐 파일은 공백으로 필드를 나눔)
+                    if general_purpose::STANDARD.decode(payload).is_err() {
+                        let _ = conn.send_line("*** 귓속말 형식이 잘못되어 전달되지 않았습니다.").await;
+                        continue;
+                    }
+                    // 다른 서버에 접속한 사람이면 연합 링크로 보냄
+                    if is_remote(&state, to) {
+                        println!("수신 [{} → {}]: (귓속말, 연합)", nick, to);
+                        federate(&state, Body::Dm { from: nick.clone(), to: to.to_string(), payload: payload.to_string() });
+                        Metrics::incr(&metrics.messages_relayed);
+                        continue;
+                    }
+                    if let Some(notice) = queue_offline(&state, to, &nick, |queue| queue.push_dm(to, &nick, payload)) {
+                        let _ = conn.send_line(&notice).await;
+                        continue;
+                    }
+                    println!("수신 [{} → {}]: (귓속말)", nick, to);
+                    let _ = tx.send(Relay { line: format!("@{} {}", nick, payload), from: Some(addr.clone()), to: Some(to.to_string()), echo: false });
+                    Metrics::incr(&metrics.messages_relayed);
+                    continue;
+                }
+
+                // 그룹 메시지 "#<그룹> <TreeKEM 메시지>": 서버는 그룹 키를 모르고 순서만 정해서 모두에게 전달
+                // (Welcome은 그룹 트리 전체를 담으므로 멤버가 백 명을 넘으면 --max-line-bytes 를 늘려야 함)
+                if let Some(group) = trimmed.strip_prefix('#') {
+                    let Some((name, payload)) = group.split_once(' ') else { continue };
+                    println!("수신 [{} → #{}]: (그룹 메시지)", nick, name);
+                    let _ = tx.send(Relay { line: format!("#{} {} {}", name, nick, payload), from: Some(addr.clone()), to: None, echo: true });
+                    federate(&state, Body::Group { name: name.to_string(), from: nick.clone(), payload: payload.to_string() });
+                    Metrics::incr(&metrics.messages_relayed);
+                    continue;
+                }
+
+                // 로깅: 서버도 Room Key가 있으므로 복호화해서 내용을 볼 수 있음
+                let mut message_id = None;
+                let mut mentions = vec![];
+                let ts = envelope::now_millis();
+                // 다른 사람의 발신자 번호를 쓴 메시지는 nonce가 겹칠 수 있으므로 전달하지 않음
+                let result = state.room.lock().unwrap().open(trimmed);
+                let opened = match result {
+                    Ok((claimed, _)) if claimed != sender => {
+                        eprintln!("⚠️ [{}] 발신자 번호 {}가 아닌 {}로 암호화한 메시지를 버림", addr, sender, claimed);
+                        let _ = conn.send_line("*** 발신자 번호가 맞지 않는 메시지는 전달되지 않았습니다.").await;
+                        continue;
+                    }
+                    Ok((_, pt)) => config.padding.unpad(pt).ok(),
+                    Err(_) => None,
+                };
+                // 토픽 변경 요청은 다른 사람에게 전달하지 않고, 바뀐 방 정보를 서버가 모두에게 알림
+                if let Some(topic) = opened.as_deref().and_then(|pt| pt.strip_prefix(metadata::TOPIC_PREFIX.as_bytes())) {
+                    if let Err(reason) = change_topic(&state, &nick, operator, &String::from_utf8_lossy(topic)) {
+                        let _ = conn.send_line(&format!("*** {}", reason)).await;
+                    }
+                    continue;
+                }
+                if let Some(pt) = &opened {
+                    if pt.first() == Some(&0) {
+                        // 클라이언트끼리 주고받는 제어 메시지 (예: 신원 공지)
+                        println!("수신 [{}]: (제어 메시지)", nick);
+                    } else if let Some(envelope) = Envelope::from_bytes(pt) {
+                        println!("수신 [{}]: {:?} ({})", nick, envelope.kind, envelope.id);
+                        if let Kind::Text { body } = &envelope.kind {
+                            mentions = mentioned_nicks(body, &nick);
+                        }
+                        message_id = Some(envelope.id);
+                    } else {
+                        println!("수신 [{}]: {}", nick, String::from_utf8_lossy(pt));
+                    }
+                }
+
+                // 멘션(@닉네임)된 사람 중 접속하지 않은 등록 사용자에게는 보관해 둠
+                if state.offline.is_some() && let Some(pt) = &opened {
+                    for to in mentions.iter().filter(|to| !is_remote(&state, to)) {
+                        queue_offline(&state, to, &nick, |queue| queue.push_mention(to, &nick, ts, pt));
+                    }
+                }
+
+                // 브로드캐스트 (암호문 그대로 전달하고, 서버가 받은 시각을 줄 끝에 붙임)
+                let msg = format!("[{}]: {}{}{}", nick, trimmed, envelope::TIMESTAMP_TAG, ts);
+                let _ = tx.send(Relay { line: msg, from: Some(addr.clone()), to: None, echo: false });
+                Metrics::incr(&metrics.messages_relayed);
+                // 다른 서버는 Room Key가 다르므로 평문을 연합 링크로 보내고, 받은 서버가 다시 암호화함
+                if let Some(plaintext) = opened {
+                    federate(&state, Body::Room { nick: nick.clone(), ts, plaintext });
+                }
+
+                // 보낸 사람에게 받아들였다는 확인 (봉투 형식의 메시지만 ID가 있음)
+                if let Some(id) = message_id {
+                    let _ = conn.send_line(&format!("{}{} {}", envelope::ACK_PREFIX, id, ts)).await;
+                }
             }
 
-            // 3. 클라이언트로부터 공개키 수신 대기
-            let mut client_pub_line = String::new();
-            if reader.read_line(&mut client_pub_line).await.unwrap_or(0) == 0 {
-                return; // 연결 끊김
+            // 다른 사람의 메시지 전송
+            result = rx.recv() => {
+                match result {
+                    Ok(relay) => {
+                        if (relay.echo || relay.from.as_ref() != Some(&addr)) && relay.to.as_ref().is_none_or(|to| *to == nick) {
+                            let _ = conn.send_line(&relay.line).await;
+                        }
+                    }
+                    // 이 연결이 너무 느려서 브로드캐스트 채널에서 밀려난 메시지가 있음
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        eprintln!("⚠️ [{}] 브로드캐스트 지연으로 메시지 {}개 누락", addr, skipped);
+                        let _ = conn.send_line(&format!("*** 연결이 느려서 메시지 {}개를 전달하지 못했습니다.", skipped)).await;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
             }
-            let client_pub_bytes = match general_purpose::STANDARD.decode(client_pub_line.trim()) {
-                Ok(b) => b,
-                Err(_) => return,
-            };
+        }
+    }
+    println!("👋 클라이언트 접속 종료: {} ({})", nick, addr);
+}
+
+// 받는 사람이 접속 중이 아니면 오프라인 큐에 넣고(등록 사용자만), 보낸 사람에게 줄 안내를 돌려줌
+// 접속 중이면 None (평소처럼 전달)
+fn queue_offline<F>(state: &ServerState, to: &str, from: &str, push: F) -> Option<String>
+where
+    F: FnOnce(&mut OfflineQueue) -> Result<usize, String>,
+{
+    // 계정 파일은 큐를 잠그기 전에 확인 (파일을 읽는 동안 다른 연결의 보관과 전달을 막지 않도록)
+    if state.online.lock().unwrap().contains(to) {
+        return None;
+    }
+    let registered = account_store(state).ok().flatten().is_some_and(|s| s.is_registered(to));
+    // 큐를 잠근 채로 접속 여부를 다시 확인해서, 받는 사람이 로그인하며 큐를 비우는 순간과 엇갈리지 않게 함
+    let mut queue = state.offline.as_ref().map(|q| q.lock().unwrap());
+    if state.online.lock().unwrap().contains(to) {
+        return None;
+    }
+    let Some(queue) = queue.as_mut().filter(|_| registered) else {
+        return Some(format!("*** {}님은 접속 중이 아닙니다.", to));
+    };
+    match push(queue) {
+        Ok(dropped) => {
+            println!("📮 [{} → {}] 오프라인 큐에 보관{}", from, to, if dropped > 0 { format!(" (오래된 메시지 {}개 버림)", dropped) } else { String::new() });
+            Some(format!("*** {}님이 접속 중이 아니어서 메시지를 보관했습니다.", to))
+        }
+        Err(e) => {
+            eprintln!("⚠️ [{} → {}] 오프라인 큐 보관 실패: {}", from, to, e);
+            Some(format!("*** {}님이 접속 중이 아니고 메시지를 보관하지 못했습니다: {}", to, e))
+        }
+    }
+}
+
+// 계정 파일. 수정 시각이 바뀌었을 때만 다시 읽어서, 서버 실행 중의 `user add/remove`도 바로 반영
+fn account_store(state: &ServerState) -> Result<Option<Arc<AccountStore>>, String> {
+    let Some(path) = &state.config.accounts else {
+        return Ok(None);
+    };
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut cache = state.accounts.lock().unwrap();
+    if let Some((at, store)) = cache.as_ref()
+        && *at == modified
+    {
+        return Ok(Some(store.clone()));
+    }
+    let store = Arc::new(AccountStore::load(path)?);
+    *cache = Some((modified, store.clone()));
+    Ok(Some(store))
+}
 
-            // 4. 핸드셰이크 키(Session Key) 유도
-            let session_key = match server_ecdh.derive_aes_key(&client_pub_bytes) {
-                Ok(k) => k,
+// 방 메시지 본문에서 "@닉네임" 형태의 멘션을 찾음 (자기 자신과 중복은 제외)
+fn mentioned_nicks(body: &str, sender: &str) -> Vec<String> {
+    let mut nicks: Vec<String> = vec![];
+    for word in body.split_whitespace() {
+        let Some(nick) = word.strip_prefix('@') else { continue };
+        let nick = nick.trim_end_matches(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'));
+        if accounts::valid_nick(nick) && nick != sender && !nicks.iter().any(|n| n == nick) {
+            nicks.push(nick.to_string());
+        }
+    }
+    nicks
+}
+
+// 로그인한 사용자에게 오프라인 큐에 보관된 메시지를 전달
+async fn deliver_offline<R, W>(state: &ServerState, conn: &mut LineConn<R, W>, nick: &str) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let Some(queue) = &state.offline else {
+        return Ok(());
+    };
+    let pending = queue.lock().unwrap().peek(nick);
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    println!("📮 [{}] 보관된 메시지 {}개 전달", nick, pending.len());
+    // 연결에 쓴 메시지(와 전달할 수 없어서 버린 메시지)만 큐에서 지움. 중간에 끊기면 나머지는 다음 로그인 때 다시 전달
+    let mut done = Vec::with_capacity(pending.len());
+    let mut result = conn.send_line(&format!("*** 접속하지 않은 동안 온 메시지 {}개를 전달합니다.", pending.len())).await;
+    for (seq, delivery) in pending {
+        if result.is_err() {
+            break;
+        }
+        let line = match delivery {
+            Ok(Delivery::Dm { from, payload }) => format!("@{} {}", from, payload),
+            // 멘션은 지금의 Room Key로 다시 암호화해서 원래 방 메시지와 같은 모양으로 보냄
+            Ok(Delivery::Mention { from, ts, plaintext }) => match seal_room(state, &plaintext) {
+                Ok(packet) => format!("[{}]: {}{}{}", from, packet, envelope::TIMESTAMP_TAG, ts),
                 Err(e) => {
-                    eprintln!("키 교환 실패: {}", e);
-                    return;
+                    eprintln!("⚠️ [{}] 보관된 멘션을 암호화하지 못해 버림: {}", nick, e);
+                    done.push(seq);
+                    continue;
                 }
-            };
+            },
+            Err(e) => {
+                eprintln!("⚠️ [{}] 보관된 멘션을 풀 수 없어 버림: {}", nick, e);
+                done.push(seq);
+                continue;
+            }
+        };
+        result = conn.send_line(&line).await;
+        if result.is_ok() {
+            done.push(seq);
+        }
+    }
+    if let Err(e) = queue.lock().unwrap().ack(nick, &done) {
+        eprintln!("⚠️ [{}] 전달한 보관 메시지를 큐에서 지우지 못했습니다: {}", nick, e);
+    }
+    result
+}
+
+// 서버가 방 메시지를 다시 암호화 (오프라인 멘션, 연합으로 받은 메시지)
+// 실패하면 그 메시지만 버림 (잠금을 쥔 채 패닉하면 이후의 모든 연결이 멈춤)
+fn seal_room(state: &ServerState, plaintext: &[u8]) -> Result<String, String> {
+    let padded = state.config.padding.pad(plaintext);
+    // 발신자 번호 0의 세대가 2^32번 넘게 바뀌어야 실패하므로, 그때는 서버를 다시 시작해서 Room Key를 바꿔야 함 (키 저장소를 쓰면 저장소 파일도 새로 만듦)
+    let mut room = state.room.lock().unwrap();
+    // 키 저장소를 쓰면 새 세대로 암호화하기 전에 저장해 둠 (다시 시작한 뒤 같은 nonce를 쓰지 않도록)
+    if let Some(keystore) = &state.keystore {
+        keystore.lock().unwrap().reserve_epoch(metadata::MAIN_ROOM, room.next_epoch())?;
+    }
+    room.seal(&padded)
+}
+
+// 서버가 보내는 방 메시지 "[*]: <패킷> t=<시각>"
+fn server_line(state: &ServerState, text: &str) -> Result<String, String> {
+    let packet = seal_room(state, text.as_bytes())?;
+    Ok(format!("[{}]: {}{}{}", metadata::SERVER_NICK, packet, envelope::TIMESTAMP_TAG, envelope::now_millis()))
+}
+
+// 방 정보. 접속자 수에는 연합된 다른 서버의 접속자도 포함
+fn room_info(state: &ServerState) -> RoomInfo {
+    let remote = state.federation.as_ref().map_or(0, |fed| fed.presence.lock().unwrap().snapshot().len());
+    let members = state.online.lock().unwrap().len() + remote;
+    state.rooms.lock().unwrap().info(metadata::MAIN_ROOM, members).expect("서버를 시작할 때 방을 만듦")
+}
+
+fn change_topic(state: &ServerState, nick: &str, operator: bool, topic: &str) -> Result<(), String> {
+    if !operator {
+        return Err("토픽은 운영자만 바꿀 수 있습니다.".to_string());
+    }
+    state.rooms.lock().unwrap().set_topic(metadata::MAIN_ROOM, topic, nick, envelope::now_millis())?;
+    println!("🏷️ [{}] 토픽 변경: {}", nick, topic.trim());
+    match server_line(state, &room_info(state).to_control()) {
+        Ok(line) => {
+            let _ = state.tx.send(Relay { line, from: None, to: None, echo: false });
+        }
+        Err(e) => eprintln!("⚠️ [{}] 바뀐 방 정보를 암호화하지 못해 알리지 못함: {}", nick, e),
+    }
+    Ok(())
+}
+
+// 다른 서버에 접속한 사용자인지
+fn is_remote(state: &ServerState, nick: &str) -> bool {
+    state.federation.as_ref().is_some_and(|fed| fed.presence.lock().unwrap().server_of(nick).is_some())
+}
+
+// 이 서버에서 생긴 일을 모든 연합 링크로 알림
+fn federate(state: &ServerState, body: Body) {
+    let Some(fed) = &state.federation else { return };
+    let message = FedMessage { origin: fed.name.clone(), id: envelope::new_id(), body };
+    fed.seen.lock().unwrap().insert(&message.origin, &message.id);
+    let _ = fed.tx.send((message, None));
+}
+
+// 이 서버에 접속한 모든 사용자에게 서버 안내 전달
+fn notify_local(state: &ServerState, line: String) {
+    let _ = state.tx.send(Relay { line, from: None, to: None, echo: false });
+}
+
+// 다른 서버의 연합 링크를 받음
+async fn federation_accept_loop(state: Arc<ServerState>, listener: TcpListener) -> std::io::Result<()> {
+    loop {
+        let (socket, addr) = listener.accept().await?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run_link(&state, socket, false).await {
+                eprintln!("🌐 연합 링크 [{}] 종료: {}", addr, e);
+            }
+        });
+    }
+}
+
+// 다른 서버로 연합 링크를 열고, 끊기면(netsplit) 잠시 뒤 다시 연결
+async fn federation_peer_loop(state: Arc<ServerState>, addr: String) {
+    let mut last_error = None;
+    loop {
+        let result = match TcpStream::connect(&addr).await {
+            Ok(socket) => run_link(&state, socket, true).await,
+            Err(e) => Err(e.to_string()),
+        };
+        // 같은 실패가 계속되면 처음 한 번만 기록
+        if let Err(e) = &result
+            && last_error.as_ref() != Some(e)
+        {
+            eprintln!("🌐 연합 링크 [{}] 실패: {} ({}초 뒤 다시 연결)", addr, e, PEER_RETRY.as_secs());
+        }
+        last_error = result.err();
+        tokio::time::sleep(PEER_RETRY).await;
+    }
+}
 
-            // 5. 유도된 세션 키로 'Room Key'를 암호화하여 클라이언트에게 전송
-            //    (이 과정이 끝나면 이제 둘 다 Room Key를 알게 됨)
-            let session_cipher = Aes256Gcm::new(&session_key.into());
-            let nonce = Aes256Gcm::generate_nonce(&mut OsRng); // 96-bits unique
-            let encrypted_room_key = session_cipher.encrypt(&nonce, room_key.as_slice()).unwrap();
-            
-            // 전송 포맷: Base64( Nonce + EncryptedRoomKey )
-            let mut payload = nonce.to_vec();
-            payload.extend_from_slice(&encrypted_room_key);
-            let payload_b64 = general_purpose::STANDARD.encode(payload);
-            
-            if let Err(_) = writer.write_all(format!("{}\n", payload_b64).as_bytes()).await {
+// 연합 링크 하나를 처리. 링크가 끊기면 그 링크로 알게 된 사용자를 정리하고 다른 링크에도 알림
+async fn run_link(state: &Arc<ServerState>, socket: TcpStream, outgoing: bool) -> Result<(), String> {
+    let fed = state.federation.as_ref().ok_or("연합을 사용하지 않는 서버입니다.")?;
+    let (reader, writer) = socket.into_split();
+    // 방 메시지는 평문을 base64로 한 번 더 감싸서 보내므로 클라이언트 줄보다 길어질 수 있음
+    let mut conn = LineConn::new(BufReader::new(reader), writer, state.config.max_line_bytes * 2);
+    let (cipher, peer) = if outgoing {
+        link::connect(&mut conn, &fed.name, &fed.secret).await?
+    } else {
+        link::accept(&mut conn, &fed.name, &fed.secret).await?
+    };
+
+    let link_id = fed.next_link.fetch_add(1, Ordering::Relaxed);
+    {
+        let mut links = fed.links.lock().unwrap();
+        if peer == fed.name {
+            return Err("서버 이름이 같은 서버와는 링크할 수 없습니다.".to_string());
+        }
+        if links.contains_key(&peer) {
+            return Err(format!("{} 서버와는 이미 링크되어 있습니다.", peer));
+        }
+        links.insert(peer.clone(), link_id);
+    }
+    println!("🌐 {} 서버와 연합 링크 연결됨", peer);
+    // 접속 상태를 보내기 전에 구독해서, 그 사이에 생긴 일도 빠짐없이 전달
+    let mut rx = fed.tx.subscribe();
+    let result = serve_link(state, fed, &mut conn, &cipher, link_id, &mut rx).await;
+
+    fed.links.lock().unwrap().remove(&peer);
+    let lost = fed.presence.lock().unwrap().split(link_id);
+    println!("🌐 {} 서버와 연합 링크 끊김 (netsplit): 사용자 {}명 정리", peer, lost.len());
+    for (nick, server) in lost {
+        notify_local(state, format!("*** netsplit: {}님({} 서버)과 연결이 끊겼습니다.", nick, server));
+        let message = FedMessage { origin: server, id: envelope::new_id(), body: Body::Part { nick } };
+        fed.seen.lock().unwrap().insert(&message.origin, &message.id);
+        let _ = fed.tx.send((message, Some(link_id)));
+    }
+    result
+}
+
+// 링크가 열리면 서로 아는 사용자를 모두 알려주고(netsplit 뒤 다시 연결되면 접속 상태를 다시 맞춤), 이후 메시지를 양방향으로 중계
+async fn serve_link<R, W>(
+    state: &ServerState,
+    fed: &Federation,
+    conn: &mut LineConn<R, W>,
+    cipher: &Aes256Gcm,
+    link_id: u64,
+    rx: &mut broadcast::Receiver<(FedMessage, Option<u64>)>,
+) -> Result<(), String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let join = |origin: String, nick: String| FedMessage { origin, id: envelope::new_id(), body: Body::Join { nick } };
+    let mut burst: Vec<FedMessage> = state.online.lock().unwrap().iter().map(|nick| join(fed.name.clone(), nick.clone())).collect();
+    burst.extend(fed.presence.lock().unwrap().snapshot().into_iter().map(|(nick, server)| join(server, nick)));
+    for message in burst {
+        conn.send_sealed(cipher, message.to_line().as_bytes()).await.map_err(|e| e.to_string())?;
+    }
+
+    loop {
+        tokio::select! {
+            result = conn.recv_frame() => {
+                let line = match result.map_err(|e| e.to_string())? {
+                    Frame::Line(line) => line,
+                    Frame::TooLong(len) => {
+                        eprintln!("⚠️ 연합 링크에서 최대 길이 초과 프레임 수신: {} 바이트", len);
+                        continue;
+                    }
+                    Frame::Eof => return Ok(()),
+                };
+                let message = packet::open(cipher, line.trim())
+                    .ok()
+                    .and_then(|pt| String::from_utf8(pt).ok())
+                    .and_then(|text| FedMessage::parse(&text))
+                    .ok_or("상대 서버가 잘못된 메시지를 보냈습니다.")?;
+                receive_federated(state, fed, link_id, message);
+            }
+            result = rx.recv() => match result {
+                Ok((message, via)) if via != Some(link_id) => {
+                    conn.send_sealed(cipher, message.to_line().as_bytes()).await.map_err(|e| e.to_string())?;
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("⚠️ 연합 링크 지연으로 메시지 {}개 누락", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+}
+
+// 연합 링크로 받은 메시지를 이 서버의 사용자에게 전달하고, 나머지 링크로 다시 보냄
+// 자기가 보낸 메시지나 이미 본 메시지는 버려서 링크가 고리를 이뤄도 한 번만 처리
+fn receive_federated(state: &ServerState, fed: &Federation, link_id: u64, message: FedMessage) {
+    if message.origin == fed.name || !fed.seen.lock().unwrap().insert(&message.origin, &message.id) {
+        return;
+    }
+    let origin = &message.origin;
+    let relay = |line: String, to: Option<&str>| {
+        let _ = state.tx.send(Relay { line, from: None, to: to.map(str::to_string), echo: false });
+    };
+    match &message.body {
+        Body::Join { nick } => {
+            let local = state.online.lock().unwrap().contains(nick);
+            if local || !fed.presence.lock().unwrap().join(nick, origin, link_id) {
+                eprintln!("🌐 닉네임 충돌: {} 서버의 {}님은 이미 있는 닉네임이라 무시", origin, nick);
+                return;
+            }
+            println!("🌐 {}님이 {} 서버에 접속", nick, origin);
+            notify_local(state, format!("*** {}님이 {} 서버에 접속했습니다.", nick, origin));
+        }
+        Body::Part { nick } => {
+            if !fed.presence.lock().unwrap().part(nick, origin, link_id) {
                 return;
             }
-            
-            println!("🔒 [{}] 핸드셰이크 완료 및 Room Key 전달됨", addr);
+            println!("🌐 {}님이 {} 서버에서 나감", nick, origin);
+            notify_local(state, format!("*** {}님이 {} 서버에서 나갔습니다.", nick, origin));
+        }
+        Body::Room { nick, ts, plaintext } => {
+            println!("🌐 수신 [{}@{}]: (방 메시지)", nick, origin);
+            match seal_room(state, plaintext) {
+                Ok(packet) => relay(format!("[{}]: {}{}{}", nick, packet, envelope::TIMESTAMP_TAG, ts), None),
+                Err(e) => eprintln!("⚠️ [{}@{}] 방 메시지를 암호화하지 못해 버림: {}", nick, origin, e),
+            }
+        }
+        Body::Dm { from, to, payload } => {
+            // 받는 사람이 이 서버에 있으면 여기서 끝
+            if state.online.lock().unwrap().contains(to) {
+                println!("🌐 수신 [{}@{} → {}]: (귓속말)", from, origin, to);
+                relay(format!("@{} {}", from, payload), Some(to));
+                return;
+            }
+        }
+        Body::Group { name, from, payload } => {
+            println!("🌐 수신 [{}@{} → #{}]: (그룹 메시지)", from, origin, name);
+            relay(format!("#{} {} {}", name, from, payload), None);
+        }
+    }
+    let _ = fed.tx.send((message, Some(link_id)));
+}
 
+// 직접 만든 ECDH 핸드셰이크. 세션 키와 바인딩 값(서버 공개키 || 클라이언트 공개키)을 돌려줌
+async fn ecdh_handshake<R, W>(conn: &mut LineConn<R, W>, addr: &PeerAddr) -> Result<(AesKey, Vec<u8>), String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    // 1. 서버의 임시 키 쌍 생성
+    let server_ecdh = ecdhkey::EcdhKey::create();
+    let server_pub = server_ecdh.public_key_bytes();
+    let server_pub_b64 = general_purpose::STANDARD.encode(&server_pub);
 
-            // ==========================================
-            // [메인 채팅 루프 (Room Key 사용)]
-            // ==========================================
-            let mut line = String::new();
-            loop {
-                tokio::select! {
-                    // 메시지 수신 (암호화된 상태)
-                    result = reader.read_line(&mut line) => {
-                        if result.unwrap_or(0) == 0 { break; }
-
-                        // 로깅: 서버도 Room Key가 있으므로 복호화해서 내용을 볼 수 있음
-                        let trimmed = line.trim();
-                        if let Ok(data) = general_purpose::STANDARD.decode(trimmed) {
-                            if data.len() > 12 {
-                                let (nonce, cipher) = data.split_at(12);
-                                if let Ok(pt) = server_room_cipher.decrypt(Nonce::from_slice(nonce), cipher) {
-                                     println!("수신 [{}]: {}", addr, String::from_utf8_lossy(&pt));
-                                }
-                            }
-                        }
+    // 2. 클라이언트에게 서버 공개키 전송 (하이브리드 지원 여부와 상관없이 예전과 같은 형식)
+    conn.send_line(&server_pub_b64).await.map_err(|e| e.to_string())?;
+
+    // 3. 클라이언트로부터 공개키 수신 대기 (비정상적으로 긴 줄이면 바로 종료)
+    //    "<ECDH 공개키>" 또는 하이브리드를 원하면 "<ECDH 공개키> mlkem768=<캡슐화 키>"
+    let client_line = conn.recv_line().await.map_err(|e| e.to_string())?;
+    let mut fields = client_line.split_whitespace();
+    let client_pub_bytes = general_purpose::STANDARD
+        .decode(fields.next().unwrap_or_default())
+        .map_err(|_| "클라이언트 공개키 형식이 잘못되었습니다.".to_string())?;
+    let mlkem_ek = match fields.next() {
+        None => None,
+        Some(field) => {
+            let ek = field
+                .strip_prefix(MLKEM_TAG)
+                .and_then(|ek| general_purpose::STANDARD.decode(ek).ok())
+                .ok_or_else(|| "지원하지 않는 키 교환 요청입니다.".to_string())?;
+            Some(ek)
+        }
+    };
+
+    let mut binding = server_pub;
+    binding.extend_from_slice(&client_pub_bytes);
+
+    // 4. 핸드셰이크 키(Session Key) 유도
+    let session_key = match mlkem_ek {
+        None => server_ecdh.derive_aes_key(&client_pub_bytes)?,
+        Some(ek) => {
+            // 4-1. 하이브리드: 클라이언트의 ML-KEM 키로 캡슐화해서 암호문을 돌려주고, 두 공유 비밀을 함께 사용
+            let (pq_secret, ct) = mlkem::encapsulate(&ek)?;
+            conn.send_line(&format!("{}{}", MLKEM_TAG, general_purpose::STANDARD.encode(&ct)))
+                .await
+                .map_err(|e| e.to_string())?;
+            println!("🛡️ [{}] ML-KEM-768 하이브리드 키 교환", addr);
+            binding.extend_from_slice(&ek);
+            binding.extend_from_slice(&ct);
+            server_ecdh.derive_hybrid_aes_key(&client_pub_bytes, pq_secret.as_slice())?
+        }
+    };
+
+    Ok((session_key, binding))
+}
+
+// 로그인 요청을 처리하고 닉네임을 점유
+// 성공하면 닉네임 점유 가드와, Room Key 전달에 사용할 암호화 객체, 발신자 번호, 운영자 여부를 돌려줌
+async fn login<R, W>(
+    state: &Arc<ServerState>,
+    conn: &mut LineConn<R, W>,
+    session_key: &AesKey,
+    handshake_aad: &[u8],
+) -> Result<(NickGuard, Aes256Gcm, u32, bool), String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let session_cipher = session_key.cipher();
+    let request = conn.recv_sealed_text(&session_cipher).await.map_err(|e| e.to_string())?;
+
+    let mut parts = request.splitn(3, ' ');
+    let (command, nick) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    if !accounts::valid_nick(nick) {
+        return Err("사용할 수 없는 닉네임입니다.".to_string());
+    }
+
+    let store = account_store(state)?;
+    let registered = store.as_ref().is_some_and(|s| s.is_registered(nick));
+    let mut room_key_cipher = session_cipher.clone();
+
+    match command {
+        "NICK" if registered => {
+            return Err("등록된 닉네임입니다. 비밀번호로 로그인하세요.".to_string());
+        }
+        "NICK" => {}
+        "LOGIN" => {
+            let password = Zeroizing::new(parts.next().unwrap_or("").to_string());
+            let (store, nick_owned) = (store, nick.to_string());
+            let ok = tokio::task::spawn_blocking(move || {
+                store.is_some_and(|s| s.verify(&nick_owned, &password))
+            })
+            .await
+            .unwrap_or(false);
+            if !ok {
+                // 무차별 대입을 늦추기 위해 실패 응답을 지연
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                return Err("닉네임 또는 비밀번호가 올바르지 않습니다.".to_string());
+            }
+        }
+        "PAKE" => {
+            let mixed = pake_login(state, conn, session_key, store.as_deref(), nick, handshake_aad).await;
+            let Ok(mixed_key) = mixed else {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                return Err("닉네임 또는 비밀번호가 올바르지 않습니다.".to_string());
+            };
+            room_key_cipher = mixed_key.cipher();
+        }
+        _ => return Err("알 수 없는 로그인 요청입니다.".to_string()),
+    }
+
+    // 연결마다 다른 발신자 번호 (Room Key를 바꾸지 않고 번호를 한 바퀴 다 쓰면 nonce가 겹칠 수 있으므로 거부)
+    let sender = state
+        .next_sender
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_add(1))
+        .map_err(|_| "서버의 발신자 번호를 모두 썼습니다. 서버를 다시 시작해야 합니다.".to_string())?;
+    if let Some(keystore) = &state.keystore {
+        keystore.lock().unwrap().reserve_sender(metadata::MAIN_ROOM, sender)?;
+    }
+    // 가드는 Drop할 때 Part를 연합에 알리므로, 실패할 수 있는 일을 모두 끝낸 뒤에 닉네임을 점유 (Join 없는 Part 방지)
+    if is_remote(state, nick) || !state.online.lock().unwrap().insert(nick.to_string()) {
+        return Err("이미 사용 중인 닉네임입니다.".to_string());
+    }
+    let guard = NickGuard { state: state.clone(), nick: nick.to_string() };
+    federate(state, Body::Join { nick: nick.to_string() });
+    // 발신자 번호, 키 갱신 한도, 방의 패딩 정책을 함께 알림 (세션 키로 암호화되어 있어서 중간에서 바꿀 수 없음)
+    let mut reply = format!("OK {} {}{} {}{}", nick, cipherstate::SENDER_TAG, sender, cipherstate::REKEY_TAG, state.config.rekey_after);
+    if state.config.padding != Padding::None {
+        reply.push_str(&format!(" {}{}", padding::PADDING_TAG, state.config.padding));
+    }
+    conn.send_sealed(&session_cipher, reply.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    // 등록된 닉네임은 비밀번호(또는 PAKE)로만 로그인할 수 있으므로, 운영자 닉네임을 다른 사람이 쓸 수 없음
+    let operator = registered && state.config.operators.iter().any(|op| op == nick);
+    Ok((guard, room_key_cipher, sender, operator))
+}
+
+// SPAKE2 로그인 (서버 = B 역할). 성공하면 PAKE 공유 비밀을 섞은 새 세션 키를 돌려줌
+//   S→C "SALT <솔트>",  C→S "SHARE <X>",  S→C "SHARE <Y> <cB>",  C→S "CONFIRM <cA>"
+async fn pake_login<R, W>(
+    state: &ServerState,
+    conn: &mut LineConn<R, W>,
+    session_key: &AesKey,
+    store: Option<&AccountStore>,
+    nick: &str,
+    handshake_aad: &[u8],
+) -> Result<AesKey, String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let session_cipher = &session_key.cipher();
+    // 없는 닉네임이어도 같은 흐름으로 진행해서, 응답만으로 계정 존재 여부를 알 수 없게 함
+    let (salt, w) = match store.and_then(|s| s.pake_verifier(nick)) {
+        Some(verifier) => verifier,
+        None => {
+            let mut fake_w = Zeroizing::new([0u8; 32]);
+            OsRng.fill_bytes(fake_w.as_mut());
+            (fake_salt(state, nick), spake2::reduce_to_w(&fake_w))
+        }
+    };
+    let b64 = |bytes: &[u8]| general_purpose::STANDARD.encode(bytes);
+
+    conn.send_sealed(session_cipher, format!("SALT {}", b64(&salt)).as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let reply = conn.recv_sealed_text(session_cipher).await.map_err(|e| e.to_string())?;
+    let client_share = reply
+        .strip_prefix("SHARE ")
+        .and_then(|s| general_purpose::STANDARD.decode(s.trim()).ok())
+        .ok_or_else(|| "SPAKE2 공유값 형식이 잘못되었습니다.".to_string())?;
+
+    let spake = Spake2::start(Role::Server, &w, nick.as_bytes(), SPAKE2_SERVER_ID, handshake_aad)?;
+    let server_share = spake.share().to_vec();
+    let keys = spake.finish(&client_share)?;
+    let reply = format!("SHARE {} {}", b64(&server_share), b64(keys.confirmation()));
+    conn.send_sealed(session_cipher, reply.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let reply = conn.recv_sealed_text(session_cipher).await.map_err(|e| e.to_string())?;
+    let client_confirmation = reply
+        .strip_prefix("CONFIRM ")
+        .and_then(|s| general_purpose::STANDARD.decode(s.trim()).ok())
+        .ok_or_else(|| "SPAKE2 확인값 형식이 잘못되었습니다.".to_string())?;
+    keys.verify_peer(&client_confirmation)?;
+
+    keys.mix_into(session_key)
+}
+
+// 등록되지 않은 닉네임에 줄 솔트. 같은 닉네임에는 항상 같은 값을 돌려줌
+fn fake_salt(state: &ServerState, nick: &str) -> Vec<u8> {
+    let hkdf = Hkdf::<Sha256>::new(Some(state.fake_salt_secret.as_slice()), nick.as_bytes());
+    let mut salt = vec![0u8; spake2::SALT_LEN];
+    hkdf.expand(b"chat-fake-salt-v1", &mut salt).expect("HKDF 출력 길이");
+    salt
+}
+
+// `chatserver user add|remove|passwd` 하위 명령 처리
+fn manage_user(path: &std::path::Path, action: UserAction) -> Result<(), Box<dyn std::error::Error>> {
+    let mut store = AccountStore::load(path)?;
+    match action {
+        UserAction::Add { nick, pake } => {
+            let password = prompt_new_password()?;
+            store.add(&nick, &password, pake)?;
+            println!("✅ 계정 추가: {}", nick);
+        }
+        UserAction::Remove { nick } => {
+            store.remove(&nick)?;
+            println!("✅ 계정 삭제: {}", nick);
+        }
+        UserAction::Passwd { nick, pake } => {
+            if !store.is_registered(&nick) {
+                return Err(format!("등록되지 않은 닉네임입니다: {}", nick).into());
+            }
+            let password = prompt_new_password()?;
+            store.set_password(&nick, &password, pake)?;
+            println!("✅ 비밀번호 변경: {}", nick);
+        }
+    }
+    store.save()?;
+    Ok(())
+}
+
+// --unix-mode 값 (예: 660, 0600)
+fn parse_mode(text: &str) -> Result<u32, String> {
+    u32::from_str_radix(text, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| format!("8진수 파일 권한이 아닙니다: {}", text))
+}
+
+// 키 저장소 암호. 새로 만드는 저장소면 한 번 더 확인
+fn prompt_keystore_passphrase(is_new: bool) -> Result<Zeroizing<Vec<u8>>, Box<dyn std::error::Error>> {
+    let passphrase = Zeroizing::new(rpassword::prompt_password("키 저장소 암호: ")?);
+    if is_new && *passphrase != rpassword::prompt_password("키 저장소 암호 확인: ")? {
+        return Err("암호가 일치하지 않습니다.".into());
+    }
+    Ok(Zeroizing::new(passphrase.as_bytes().to_vec()))
+}
+
+fn prompt_new_password() -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    let password = Zeroizing::new(rpassword::prompt_password("새 비밀번호: ")?);
+    let confirm = Zeroizing::new(rpassword::prompt_password("비밀번호 확인: ")?);
+    if password != confirm {
+        return Err("비밀번호가 일치하지 않습니다.".into());
+    }
+    Ok(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 서버의 ecdh_handshake와 chatclient 쪽 절차를 메모리 위의 연결로 주고받고, 두 쪽의 세션 키와 바인딩 값이 같은지 확인
+    async fn handshake_round_trip(hybrid: bool) {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(async move {
+            let (reader, writer) = tokio::io::split(server_io);
+            let mut conn = LineConn::new(BufReader::new(reader), writer, 64 * 1024);
+            ecdh_handshake(&mut conn, &PeerAddr::Unix(1)).await
+        });
+
+        let (reader, writer) = tokio::io::split(client_io);
+        let mut conn = LineConn::new(BufReader::new(reader), writer, 64 * 1024);
+        let server_pub = general_purpose::STANDARD.decode(conn.recv_line().await.unwrap().trim()).unwrap();
+        let client = ecdhkey::EcdhKey::create();
+        let client_pub = client.public_key_bytes();
+        let mut binding = server_pub.clone();
+        binding.extend_from_slice(&client_pub);
+        let client_key = if hybrid {
+            let (dk, ek) = mlkem::generate();
+            let line = format!("{} {}{}", general_purpose::STANDARD.encode(&client_pub), MLKEM_TAG, general_purpose::STANDARD.encode(&ek));
+            conn.send_line(&line).await.unwrap();
+            let ct_line = conn.recv_line().await.unwrap();
+            let ct = general_purpose::STANDARD.decode(ct_line.trim().strip_prefix(MLKEM_TAG).expect("ML-KEM 암호문 줄")).unwrap();
+            assert_eq!(ct.len(), mlkem::CT_LEN);
+            binding.extend_from_slice(&ek);
+            binding.extend_from_slice(&ct);
+            client.derive_hybrid_aes_key(&server_pub, dk.decapsulate(&ct).unwrap().as_slice()).unwrap()
+        } else {
+            // 예전 클라이언트처럼 공개키만 보냄
+            conn.send_line(&general_purpose::STANDARD.encode(&client_pub)).await.unwrap();
+            client.derive_aes_key(&server_pub).unwrap()
+        };
+
+        let (server_key, server_binding) = server.await.unwrap().unwrap();
+        assert_eq!(server_binding, binding);
+        let sealed = packet::seal(&server_key.cipher(), b"hello");
+        assert_eq!(packet::open(&client_key.cipher(), &sealed).unwrap(), b"hello");
+    }
+
+    #[tokio::test]
+    async fn classical_handshake_round_trip() {
+        handshake_round_trip(false).await;
+    }
+
+    #[tokio::test]
+    async fn hybrid_handshake_round_trip() {
+        handshake_round_trip(true).await;
+    }
+
+    #[tokio::test]
+    async fn handshake_rejects_unknown_extension() {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(server_io);
+        let mut server = LineConn::new(BufReader::new(reader), writer, 64 * 1024);
+        let (reader, writer) = tokio::io::split(client_io);
+        let mut client = LineConn::new(BufReader::new(reader), writer, 64 * 1024);
+        let client_pub = general_purpose::STANDARD.encode(ecdhkey::EcdhKey::create().public_key_bytes());
+        client.send_line(&format!("{} x25519=AAAA", client_pub)).await.unwrap();
+        assert!(ecdh_handshake(&mut server, &PeerAddr::Unix(1)).await.is_err());
+    }
+
+    // 연합 테스트용 클라이언트: ECDH 핸드셰이크 후 NICK으로 로그인하고 Room Key를 받음
+    struct TestClient {
+        conn: LineConn<BufReader<tokio::net::tcp::OwnedReadHalf>, tokio::net::tcp::OwnedWriteHalf>,
+        room: CipherState,
+    }
 
-                        // 브로드캐스트 (암호문 그대로 전달)
-                        let msg = format!("[{}]: {}", addr, line);
-                        let _ = tx.send((msg, addr));
-                        line.clear();
+    impl TestClient {
+        async fn connect(addr: &str, nick: &str) -> Self {
+            // 서버가 아직 리스너를 열지 않았을 수 있으므로 잠깐씩 다시 시도
+            let mut attempts = 0;
+            let socket = loop {
+                match TcpStream::connect(addr).await {
+                    Ok(socket) => break socket,
+                    Err(_) if attempts < 100 => {
+                        attempts += 1;
+                        tokio::time::sleep(Duration::from_millis(50)).await;
                     }
+                    Err(e) => panic!("{}에 접속할 수 없습니다: {}", addr, e),
+                }
+            };
+            let (reader, writer) = socket.into_split();
+            let mut conn = LineConn::new(BufReader::new(reader), writer, 64 * 1024);
+
+            let server_pub = general_purpose::STANDARD.decode(conn.recv_line().await.unwrap().trim()).unwrap();
+            let client = ecdhkey::EcdhKey::create();
+            conn.send_line(&general_purpose::STANDARD.encode(client.public_key_bytes())).await.unwrap();
+            let session = client.derive_aes_key(&server_pub).unwrap().cipher();
+
+            conn.send_sealed(&session, format!("NICK {}", nick).as_bytes()).await.unwrap();
+            let reply = conn.recv_sealed_text(&session).await.unwrap();
+            let field = |tag: &str| reply.split(' ').find_map(|f| f.strip_prefix(tag)).unwrap().parse::<u32>().unwrap();
+            let (sender, rekey_after) = (field(cipherstate::SENDER_TAG), field(cipherstate::REKEY_TAG));
+            let room_key = AesKey::unwrap(&session, conn.recv_line().await.unwrap().trim()).unwrap();
+            Self { conn, room: CipherState::new(&room_key, sender, rekey_after).unwrap() }
+        }
+
+        async fn say(&mut self, text: &str) {
+            let packet = self.room.seal(text.as_bytes()).unwrap();
+            self.conn.send_line(&packet).await.unwrap();
+        }
 
-                    // 다른 사람의 메시지 전송
-                    result = rx.recv() => {
-                        if let Ok((msg, other_addr)) = result {
-                            if addr != other_addr {
-                                let _ = writer.write_all(msg.as_bytes()).await;
+        // 조건에 맞는 줄이 올 때까지 읽음. 방 메시지 줄은 "[닉네임]: 평문"으로 바꿔서 비교
+        async fn expect(&mut self, wanted: &str) {
+            self.expect_all(&[wanted]).await;
+        }
+
+        // 여러 줄을 순서에 상관없이 모두 받을 때까지 읽음 (netsplit 안내처럼 순서가 정해지지 않은 줄)
+        async fn expect_all(&mut self, wanted: &[&str]) {
+            let mut missing: Vec<&str> = wanted.to_vec();
+            let read = async {
+                loop {
+                    let line = self.conn.recv_line().await.unwrap();
+                    let line = line.trim_end();
+                    let shown = match line.strip_prefix('[').and_then(|rest| rest.split_once("]: ")) {
+                        Some((nick, rest)) => {
+                            let packet = rest.rsplit_once(envelope::TIMESTAMP_TAG).map_or(rest, |(packet, _)| packet);
+                            match self.room.open(packet) {
+                                Ok((_, pt)) => format!("[{}]: {}", nick, String::from_utf8_lossy(&pt)),
+                                Err(_) => line.to_string(),
                             }
                         }
+                        None => line.to_string(),
+                    };
+                    missing.retain(|wanted| *wanted != shown);
+                    if missing.is_empty() {
+                        return;
                     }
                 }
+            };
+            // 링크가 처음 연결에 실패하면 PEER_RETRY 뒤에 다시 시도하므로 그보다 넉넉히 기다림
+            if tokio::time::timeout(Duration::from_secs(30), read).await.is_err() {
+                panic!("{:?} 줄을 받지 못했습니다.", wanted);
+            }
+        }
+    }
+
+    fn free_port() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    // 연합을 켠 서버를 이 프로세스 안에서 실행하고 클라이언트 접속 주소를 돌려줌
+    fn start_server(name: &str, secret: &std::path::Path, federation_bind: Option<&str>, peers: &[&str]) -> String {
+        let bind = free_port();
+        let mut args = vec!["chatserver", "--bind", &bind, "--server-name", name, "--metrics-interval", "0"];
+        let secret = secret.to_str().unwrap();
+        args.extend(["--federation-secret", secret]);
+        if let Some(addr) = federation_bind {
+            args.extend(["--federation-bind", addr]);
+        }
+        for peer in peers {
+            args.extend(["--peer", peer]);
+        }
+        let config = Cli::try_parse_from(args).unwrap().config;
+        tokio::spawn(async move {
+            if let Err(e) = run(config).await.map_err(|e| e.to_string()) {
+                panic!("서버 실행 실패: {}", e);
+            }
+        });
+        bind
+    }
+
+    // 서버 세 대를 A - B - C 사슬로 링크하고, 양 끝의 사용자끼리 접속 상태, 방 메시지, 귓속말이 오가는지 확인
+    #[tokio::test(flavor = "multi_thread")]
+    async fn federation_relays_across_three_servers() {
+        let secret = std::env::temp_dir().join(format!("chat-federation-test-{}.secret", std::process::id()));
+        std::fs::write(&secret, b"test federation secret").unwrap();
+        let (fed_a, fed_b) = (free_port(), free_port());
+        let a = start_server("alpha", &secret, Some(&fed_a), &[]);
+        let b = start_server("bravo", &secret, Some(&fed_b), &[&fed_a]);
+        let c = start_server("charlie", &secret, None, &[&fed_b]);
+
+        let mut alice = TestClient::connect(&a, "alice").await;
+        let mut bob = TestClient::connect(&b, "bob").await;
+        let mut carol = TestClient::connect(&c, "carol").await;
+        // 링크가 나중에 열려도 서로 아는 사용자를 알려주므로, carol이 보이면 사슬 전체가 연결된 것
+        alice.expect("*** carol님이 charlie 서버에 접속했습니다.").await;
+
+        // 방 메시지: 서버마다 Room Key가 다르므로 받은 서버가 다시 암호화한 것을 각자의 Room Key로 열 수 있어야 함
+        alice.say("hello from alpha").await;
+        bob.expect("[alice]: hello from alpha").await;
+        carol.expect("[alice]: hello from alpha").await;
+        carol.say("hello from charlie").await;
+        alice.expect("[carol]: hello from charlie").await;
+
+        // 귓속말은 받는 사람에게만 그대로 전달됨
+        alice.conn.send_line("@carol QUJDRA==").await.unwrap();
+        carol.expect("@alice QUJDRA==").await;
+
+        // 접속 종료도 링크를 따라 전달됨
+        drop(carol);
+        alice.expect("*** carol님이 charlie 서버에서 나갔습니다.").await;
+        bob.expect("*** carol님이 charlie 서버에서 나갔습니다.").await;
+        let _ = std::fs::remove_file(&secret);
+    }
+
+    // 연합 링크 사이에 끼우는 TCP 중계. 끊으면(false) 지금 링크를 닫고, 다시 이을 때까지 새 접속도 바로 닫음
+    fn start_link_proxy(target: String) -> (String, tokio::sync::watch::Sender<bool>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let listener = TcpListener::from_std(listener).unwrap();
+        let (up, rx) = tokio::sync::watch::channel(true);
+        tokio::spawn(async move {
+            while let Ok((mut inbound, _)) = listener.accept().await {
+                let mut up = rx.clone();
+                if !*up.borrow() {
+                    continue;
+                }
+                let target = target.clone();
+                tokio::spawn(async move {
+                    let Ok(mut outbound) = TcpStream::connect(&target).await else { return };
+                    tokio::select! {
+                        _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound) => {}
+                        _ = up.wait_for(|up| !*up) => {}
+                    }
+                });
             }
-            println!("👋 클라이언트 접속 종료: {}", addr);
         });
+        (addr, up)
+    }
+
+    // A - B - C 사슬에서 A - B 링크가 끊기면(netsplit) 양쪽 사용자가 상대 쪽 사용자의 퇴장 안내를 받고,
+    // 링크가 다시 이어지면 접속 상태를 다시 맞춰서 방 메시지와 귓속말이 다시 오가는지 확인
+    #[tokio::test(flavor = "multi_thread")]
+    async fn federation_netsplit_and_relink_resync_presence() {
+        let secret = std::env::temp_dir().join(format!("chat-netsplit-test-{}.secret", std::process::id()));
+        std::fs::write(&secret, b"test federation secret").unwrap();
+        let (fed_a, fed_b) = (free_port(), free_port());
+        let (proxy, link_up) = start_link_proxy(fed_a.clone());
+        let a = start_server("alpha", &secret, Some(&fed_a), &[]);
+        let b = start_server("bravo", &secret, Some(&fed_b), &[&proxy]);
+        let c = start_server("charlie", &secret, None, &[&fed_b]);
+
+        let mut alice = TestClient::connect(&a, "alice").await;
+        let mut bob = TestClient::connect(&b, "bob").await;
+        let mut carol = TestClient::connect(&c, "carol").await;
+        alice.expect("*** carol님이 charlie 서버에 접속했습니다.").await;
+
+        // 링크를 끊으면 A에서는 B 너머의 사용자가 모두 netsplit으로 정리되고, B는 A의 사용자를 정리한 뒤 C에도 퇴장을 알림
+        link_up.send(false).unwrap();
+        alice
+            .expect_all(&["*** netsplit: bob님(bravo 서버)과 연결이 끊겼습니다.", "*** netsplit: carol님(charlie 서버)과 연결이 끊겼습니다."])
+            .await;
+        bob.expect("*** netsplit: alice님(alpha 서버)과 연결이 끊겼습니다.").await;
+        carol.expect("*** alice님이 alpha 서버에서 나갔습니다.").await;
+
+        // 끊긴 동안 들어온 사용자도 다시 연결될 때 함께 알려짐
+        let mut dave = TestClient::connect(&c, "dave").await;
+
+        // 다시 이으면 B가 PEER_RETRY 뒤에 다시 링크하고, 양쪽이 아는 사용자를 모두 다시 알려줌
+        link_up.send(true).unwrap();
+        alice
+            .expect_all(&["*** bob님이 bravo 서버에 접속했습니다.", "*** carol님이 charlie 서버에 접속했습니다.", "*** dave님이 charlie 서버에 접속했습니다."])
+            .await;
+        carol.expect("*** alice님이 alpha 서버에 접속했습니다.").await;
+
+        // 접속 상태가 다시 맞았으므로 방 메시지와 귓속말이 사슬 끝까지 전달됨
+        alice.say("back from the split").await;
+        dave.expect("[alice]: back from the split").await;
+        dave.conn.send_line("@alice QUJDRA==").await.unwrap();
+        alice.expect("@dave QUJDRA==").await;
+        let _ = std::fs::remove_file(&secret);
     }
 }
@@ -2,30 +2,42 @@
 // 이 모듈은 Elliptic Curve Diffie-Hellman (P-256) 키 교환 로직을 담당합니다.
 
 use p256::{
-    ecdh::EphemeralSecret,
-    PublicKey,
+    ecdh::diffie_hellman,
+    PublicKey, SecretKey,
 };
 //use rand_core::OsRng;
 use crate::OsRng;
 use hkdf::Hkdf;
 use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::secret::AesKey;
 
 // 공개키를 주고받기 쉽도록 바이트 배열(SEC1 인코딩)로 정의
 pub type PubKeyBytes = Vec<u8>;
 
+// 비밀키는 한 번의 핸드셰이크에만 쓰고 버림 (SecretKey도 drop될 때 0으로 지워짐)
+// EphemeralSecret 대신 SecretKey를 쓰는 것은 테스트 벡터에서 고정된 비밀키로 같은 결과를 재현하기 위해서입니다.
 pub struct EcdhKey {
-    secret: EphemeralSecret,
+    secret: SecretKey,
     public_key: PublicKey,
 }
 
 impl EcdhKey {
     // 1. 내 일회용 키 쌍(비공개키, 공개키) 생성
     pub fn create() -> Self {
-        let secret = EphemeralSecret::random(&mut OsRng);
-        let public_key = PublicKey::from(&secret);
+        let secret = SecretKey::random(&mut OsRng);
+        let public_key = secret.public_key();
         Self { secret, public_key }
     }
 
+    // 1-1. 정해진 비밀키(32바이트 스칼라)로 생성 (프로토콜 테스트 벡터 전용)
+    pub fn from_secret_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let secret = SecretKey::from_slice(bytes).map_err(|_| "비밀키 형식이 잘못되었습니다.".to_string())?;
+        let public_key = secret.public_key();
+        Ok(Self { secret, public_key })
+    }
+
     // 내 공개키를 바이트로 변환 (상대방에게 전송용)
     pub fn public_key_bytes(&self) -> PubKeyBytes {
         // 압축된 형식(33bytes)으로 변환
@@ -34,20 +46,76 @@ impl EcdhKey {
 
     // 2. 상대방의 공개키와 내 비밀키를 조합하여 공유 비밀(Shared Secret) 생성
     // 생성된 비밀값으로 32바이트 AES 키를 유도하여 반환
-    pub fn derive_aes_key(self, other_pubkey_bytes: &[u8]) -> Result<[u8; 32], String> {
+    pub fn derive_aes_key(self, other_pubkey_bytes: &[u8]) -> Result<AesKey, String> {
+        self.derive_with(other_pubkey_bytes, &[], b"chat-handshake-v1")
+    }
+
+    // 2-1. 하이브리드 키 교환: ECDH 공유 비밀과 ML-KEM 공유 비밀을 이어 붙여 HKDF에 넣음
+    // 둘 중 하나만 안전해도 세션 키가 안전함 (지금 녹화된 트래픽을 나중에 양자 컴퓨터로 푸는 공격 대비)
+    pub fn derive_hybrid_aes_key(self, other_pubkey_bytes: &[u8], pq_secret: &[u8]) -> Result<AesKey, String> {
+        self.derive_with(other_pubkey_bytes, pq_secret, b"chat-handshake-hybrid-v1")
+    }
+
+    fn derive_with(self, other_pubkey_bytes: &[u8], extra_secret: &[u8], info: &[u8]) -> Result<AesKey, String> {
         // 상대방 공개키 디코딩
         let other_pk = PublicKey::from_sec1_bytes(other_pubkey_bytes)
             .map_err(|_| "상대방 공개키 형식이 잘못되었습니다.".to_string())?;
 
         // Diffie-Hellman 연산 수행
-        let shared_secret = self.secret.diffie_hellman(&other_pk);
+        let shared_secret = diffie_hellman(self.secret.to_nonzero_scalar(), other_pk.as_affine());
+        let mut ikm = Zeroizing::new(shared_secret.raw_secret_bytes().to_vec());
+        ikm.extend_from_slice(extra_secret);
 
         // HKDF를 사용하여 공유 비밀에서 안전한 AES-256 키 추출
-        let hkdf = Hkdf::<Sha256>::new(None, shared_secret.raw_secret_bytes());
-        let mut okm = [0u8; 32];
-        hkdf.expand(b"chat-handshake-v1", &mut okm)
+        let hkdf = Hkdf::<Sha256>::new(None, &ikm);
+        let mut okm = Zeroizing::new([0u8; 32]);
+        hkdf.expand(info, okm.as_mut())
             .map_err(|_| "키 유도 실패".to_string())?;
 
-        Ok(okm)
+        Ok(AesKey::from_bytes(okm))
+    }
+}
+
+// 비밀키는 출력하지 않고 공개키만 보여줌
+impl std::fmt::Debug for EcdhKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EcdhKey")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
     }
-}
\ No newline at end of file
+}
+
+// 3. 세션 키에 추가 비밀값(예: PAKE 로그인으로 얻은 공유 비밀)을 섞어 새 세션 키를 유도
+//    두 비밀을 모두 알아야만 같은 키가 나오므로, ECDH만 가로챈 중간자는 이 키를 알 수 없음
+pub fn mix_session_key(session_key: &AesKey, extra_secret: &[u8]) -> Result<AesKey, String> {
+    session_key.derive(extra_secret, b"chat-session-mix-v1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_hides_secret_key() {
+        let key = EcdhKey::create();
+        let debug = format!("{:?}", key);
+        assert!(debug.starts_with("EcdhKey"));
+        crate::secret::assert_redacted(&debug, &key.secret.to_bytes());
+        crate::secret::assert_redacted(&format!("{:#?}", key), &key.secret.to_bytes());
+    }
+
+    #[test]
+    fn public_api_hands_out_no_secret() {
+        type KeyResult = Result<AesKey, String>;
+        // 밖으로 나가는 것은 공개키 바이트와 AesKey뿐: 시그니처가 바뀌면 컴파일되지 않음
+        let _: fn(&EcdhKey) -> PubKeyBytes = EcdhKey::public_key_bytes;
+        let _: fn(EcdhKey, &[u8]) -> KeyResult = EcdhKey::derive_aes_key;
+        let _: fn(EcdhKey, &[u8], &[u8]) -> KeyResult = EcdhKey::derive_hybrid_aes_key;
+        let _: fn(&AesKey, &[u8]) -> KeyResult = mix_session_key;
+
+        // 공개키 바이트에 비밀 스칼라가 섞여 있지 않음
+        let key = EcdhKey::from_secret_bytes(&[0x11; 32]).unwrap();
+        let public = key.public_key_bytes();
+        assert!(!public.windows(8).any(|w| w == [0x11; 8]));
+    }
+}
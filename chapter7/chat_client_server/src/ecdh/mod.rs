@@ -1 +1,2 @@
-pub mod ecdhkey;
\ No newline at end of file
+pub mod ecdhkey;
+pub mod secret;
\ No newline at end of file
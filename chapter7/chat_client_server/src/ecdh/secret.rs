@@ -0,0 +1,145 @@
+// src/ecdh/secret.rs
+// 이 모듈은 AES-256 키(세션 키, Room Key)를 담는 래퍼 타입을 담당합니다.
+// 메모리에서 해제될 때 0으로 지워지고, Debug 출력에서는 값이 가려지며, 원시 바이트를 밖으로 내주지 않습니다.
+// 키가 필요한 작업(암호화 객체 생성, 키 유도, 다른 키로 감싸 전송)은 모두 이 타입의 메서드로만 할 수 있습니다.
+
+use aes_gcm::{aead::KeyInit, Aes256Gcm};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use sha2::Sha256;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::packet;
+
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct AesKey([u8; 32]);
+
+impl AesKey {
+    // 새 랜덤 키 (예: 서버의 Room Key)
+    pub fn random() -> Self {
+        let mut key = Self([0u8; 32]);
+        OsRng.fill_bytes(&mut key.0);
+        key
+    }
+
+    // 키 유도 결과 등 이미 만들어진 바이트로부터 생성. 넘겨받은 버퍼는 drop될 때 0으로 지워짐
+    pub fn from_bytes(bytes: Zeroizing<[u8; 32]>) -> Self {
+        Self(*bytes)
+    }
+
+    // 이 키로 AES-256-GCM 암호화 객체 생성
+    pub fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.0.into())
+    }
+
+    // HKDF(salt = 이 키, ikm = extra)로 새 키 유도
+    pub fn derive(&self, extra: &[u8], info: &[u8]) -> Result<AesKey, String> {
+        let hkdf = Hkdf::<Sha256>::new(Some(&self.0), extra);
+        let mut okm = Zeroizing::new([0u8; 32]);
+        hkdf.expand(info, okm.as_mut())
+            .map_err(|_| "키 유도 실패".to_string())?;
+        Ok(Self::from_bytes(okm))
+    }
+
+    // 다른 키(wrapping)로 이 키를 암호화해서 한 줄 패킷으로 만듦 (Room Key 전달용)
+    pub fn wrap(&self, wrapping: &Aes256Gcm) -> String {
+        packet::seal(wrapping, &self.0)
+    }
+
+    // wrap으로 만든 패킷을 풀어서 키를 복원
+    pub fn unwrap(wrapping: &Aes256Gcm, line: &str) -> Result<AesKey, String> {
+        let plaintext = Zeroizing::new(packet::open(wrapping, line)?);
+        let bytes: [u8; 32] = plaintext
+            .as_slice()
+            .try_into()
+            .map_err(|_| "키 길이가 잘못되었습니다.".to_string())?;
+        Ok(Self::from_bytes(Zeroizing::new(bytes)))
+    }
+}
+
+impl std::fmt::Debug for AesKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AesKey([REDACTED])")
+    }
+}
+
+// Debug 출력에 키 바이트가 흔히 쓰는 형식(16진수, Base64, 바이트 배열)으로 들어 있지 않은지 확인 (다른 키 타입의 테스트에서도 사용)
+#[cfg(test)]
+pub fn assert_redacted(debug: &str, key: &[u8]) {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+    let base64 = general_purpose::STANDARD.encode(key);
+    let list = format!("{:?}", &key[..8]);
+    assert!(!debug.to_lowercase().contains(&hex[..16]), "16진수 키가 보임: {}", debug);
+    assert!(!debug.contains(&base64[..12]), "Base64 키가 보임: {}", debug);
+    assert!(!debug.contains(list.trim_end_matches(']')), "바이트 배열 키가 보임: {}", debug);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_hides_key() {
+        let key = AesKey::random();
+        let debug = format!("{:?}", key);
+        assert_eq!(debug, "AesKey([REDACTED])");
+        assert_redacted(&debug, &key.0);
+        assert_redacted(&format!("{:#?}", Some(&key)), &key.0);
+    }
+
+    // 타입이 어떤 트레이트를 구현하지 않는다는 것을 컴파일 시점에 확인 (구현하면 아래 호출이 모호해져 컴파일 오류)
+    macro_rules! assert_not_impl {
+        ($ty:ty: $($tr:path),+) => {{
+            trait AmbiguousIfImpl<A> {
+                fn check() {}
+            }
+            impl<T: ?Sized> AmbiguousIfImpl<()> for T {}
+            $({
+                struct Invalid;
+                impl<T: ?Sized + $tr> AmbiguousIfImpl<Invalid> for T {}
+            })+
+            let _ = <$ty as AmbiguousIfImpl<_>>::check;
+        }};
+    }
+
+    #[test]
+    fn public_api_hands_out_no_raw_key() {
+        type KeyResult = Result<AesKey, String>;
+        // 공개 메서드의 시그니처를 고정: 어느 것이든 키 바이트를 돌려주도록 바뀌면 컴파일되지 않음
+        let _: fn() -> AesKey = AesKey::random;
+        let _: fn(Zeroizing<[u8; 32]>) -> AesKey = AesKey::from_bytes;
+        let _: fn(&AesKey) -> Aes256Gcm = AesKey::cipher;
+        let _: fn(&AesKey, &[u8], &[u8]) -> KeyResult = AesKey::derive;
+        let _: fn(&AesKey, &Aes256Gcm) -> String = AesKey::wrap;
+        let _: fn(&Aes256Gcm, &str) -> KeyResult = AesKey::unwrap;
+
+        // 바이트로 바꾸거나 빌려 볼 수 있는 트레이트도 구현하지 않아야 함
+        assert_not_impl!(AesKey: AsRef<[u8]>, std::borrow::Borrow<[u8]>, std::ops::Deref<Target = [u8; 32]>);
+        assert_not_impl!(AesKey: Into<[u8; 32]>, Into<Vec<u8>>, Clone, std::fmt::Display);
+    }
+
+    #[test]
+    fn only_unwrap_restores_a_wrapped_key() {
+        let raw = [7u8; 32];
+        let key = AesKey::from_bytes(Zeroizing::new(raw));
+        let wrapping = AesKey::random();
+        let line = key.wrap(&wrapping.cipher());
+
+        // 감싼 결과에 원래 키가 그대로 들어 있으면 안 됨
+        assert_redacted(&line, &raw);
+        let payload = packet::decode(&line).unwrap().1;
+        assert!(!payload.windows(8).any(|w| w == &raw[..8]), "감싼 패킷에 키 바이트가 보임");
+
+        // 다른 키로는 풀 수 없고, 같은 키로 unwrap해야만 쓸 수 있는 키가 돌아옴
+        assert!(AesKey::unwrap(&AesKey::random().cipher(), &line).is_err());
+        let restored = AesKey::unwrap(&wrapping.cipher(), &line).unwrap();
+        let sealed = packet::seal(&key.cipher(), b"hello");
+        assert_eq!(packet::open(&restored.cipher(), &sealed).unwrap(), b"hello");
+
+        // 유도한 키는 원래 키와 다름
+        let derived = key.derive(b"", b"test").unwrap();
+        assert!(packet::open(&derived.cipher(), &sealed).is_err());
+    }
+}
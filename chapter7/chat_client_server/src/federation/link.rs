@@ -0,0 +1,258 @@
+// src/federation/link.rs
+// 이 모듈은 chatserver끼리 연결(링크)해서 방 메시지, 귓속말, 그룹 메시지, 접속 상태를 주고받는 프로토콜을 담당합니다.
+// 링크 핸드셰이크: 임시 ECDH 키를 교환하고, 서버들이 미리 나눠 가진 연합 비밀(federation secret)을 섞어 링크 키를 만듦
+//   연결한 쪽 → "FED1 <공개키>",  받은 쪽 → "FED1 <공개키>"
+//   연결한 쪽 → 암호화된 "HELLO <서버 이름>",  받은 쪽 → 암호화된 "WELCOME <서버 이름>"
+//   (연합 비밀이 다르면 상대의 HELLO/WELCOME을 복호화할 수 없으므로 링크가 거부됨)
+// 이후 모든 줄은 링크 키로 암호화한 "<종류> <출발 서버> <ID> ..." 형식이고,
+// 받은 메시지를 들어온 링크를 뺀 나머지 링크로 다시 보내되(flooding) ID로 이미 본 메시지를 걸러서 고리(loop)를 막습니다.
+// 방 메시지는 서버마다 Room Key가 다르므로, 출발 서버가 복호화한 평문을 링크 키로 보내고 받은 서버가 자기 Room Key로 다시 암호화합니다.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use aes_gcm::Aes256Gcm;
+use base64::{engine::general_purpose, Engine as _};
+use tokio::io::{AsyncBufRead, AsyncWrite};
+use zeroize::Zeroizing;
+
+use crate::accounts;
+use crate::conn::LineConn;
+use crate::ecdhkey::EcdhKey;
+
+const HANDSHAKE_TAG: &str = "FED1 ";
+// 고리 방지를 위해 기억하는 최근 메시지 ID 수
+const SEEN_CAPACITY: usize = 4096;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Body {
+    // 출발 서버에 사용자가 접속함 / 나감
+    Join { nick: String },
+    Part { nick: String },
+    // 방 메시지 (Room Key로 복호화한 평문과 출발 서버가 받은 시각)
+    Room { nick: String, ts: i64, plaintext: Vec<u8> },
+    // 귓속말 (Double Ratchet 암호문 그대로)
+    Dm { from: String, to: String, payload: String },
+    // 그룹 메시지 (TreeKEM 메시지 그대로)
+    Group { name: String, from: String, payload: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FedMessage {
+    pub origin: String,
+    pub id: String,
+    pub body: Body,
+}
+
+impl FedMessage {
+    pub fn to_line(&self) -> String {
+        let head = |kind: &str| format!("{} {} {}", kind, self.origin, self.id);
+        match &self.body {
+            Body::Join { nick } => format!("{} {}", head("JOIN"), nick),
+            Body::Part { nick } => format!("{} {}", head("PART"), nick),
+            Body::Room { nick, ts, plaintext } => {
+                format!("{} {} {} {}", head("MSG"), nick, ts, general_purpose::STANDARD.encode(plaintext))
+            }
+            Body::Dm { from, to, payload } => format!("{} {} {} {}", head("DM"), from, to, payload),
+            Body::Group { name, from, payload } => format!("{} {} {} {}", head("GROUP"), name, from, payload),
+        }
+    }
+
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split(' ');
+        let (kind, origin, id) = (fields.next()?, fields.next()?, fields.next()?);
+        let mut next = || fields.next().map(str::to_string);
+        let body = match kind {
+            "JOIN" => Body::Join { nick: next()? },
+            "PART" => Body::Part { nick: next()? },
+            "MSG" => Body::Room {
+                nick: next()?,
+                ts: next()?.parse().ok()?,
+                plaintext: general_purpose::STANDARD.decode(next()?).ok()?,
+            },
+            "DM" => Body::Dm { from: next()?, to: next()?, payload: next()? },
+            "GROUP" => Body::Group { name: next()?, from: next()?, payload: next()? },
+            _ => return None,
+        };
+        // 서버 이름과 닉네임은 사용자 터미널에 그대로 찍히므로 제어 문자 등이 섞인 메시지는 버림
+        let names: Vec<&str> = match &body {
+            Body::Join { nick } | Body::Part { nick } | Body::Room { nick, .. } => vec![nick],
+            Body::Dm { from, to, .. } => vec![from, to],
+            Body::Group { name, from, .. } => vec![name, from],
+        };
+        if !accounts::valid_nick(origin) || !names.into_iter().all(accounts::valid_nick) {
+            return None;
+        }
+        Some(Self { origin: origin.to_string(), id: id.to_string(), body })
+    }
+}
+
+// 링크를 연 쪽의 핸드셰이크. 링크 암호화 객체와 상대 서버 이름을 돌려줌
+pub async fn connect<R, W>(conn: &mut LineConn<R, W>, name: &str, secret: &[u8]) -> Result<(Aes256Gcm, String), String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let ecdh = EcdhKey::create();
+    let my_pub = ecdh.public_key_bytes();
+    send(conn, &format!("{}{}", HANDSHAKE_TAG, general_purpose::STANDARD.encode(&my_pub))).await?;
+    let peer_pub = recv_public_key(conn).await?;
+
+    let cipher = link_cipher(ecdh, secret, &peer_pub, [&my_pub, &peer_pub])?;
+    conn.send_sealed(&cipher, format!("HELLO {}", name).as_bytes()).await.map_err(|e| e.to_string())?;
+    let peer = recv_greeting(conn, &cipher, "WELCOME ").await?;
+    Ok((cipher, peer))
+}
+
+// 링크를 받은 쪽의 핸드셰이크
+pub async fn accept<R, W>(conn: &mut LineConn<R, W>, name: &str, secret: &[u8]) -> Result<(Aes256Gcm, String), String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let ecdh = EcdhKey::create();
+    let my_pub = ecdh.public_key_bytes();
+    let peer_pub = recv_public_key(conn).await?;
+    send(conn, &format!("{}{}", HANDSHAKE_TAG, general_purpose::STANDARD.encode(&my_pub))).await?;
+
+    let cipher = link_cipher(ecdh, secret, &peer_pub, [&peer_pub, &my_pub])?;
+    let peer = recv_greeting(conn, &cipher, "HELLO ").await?;
+    conn.send_sealed(&cipher, format!("WELCOME {}", name).as_bytes()).await.map_err(|e| e.to_string())?;
+    Ok((cipher, peer))
+}
+
+async fn send<R, W>(conn: &mut LineConn<R, W>, line: &str) -> Result<(), String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    conn.send_line(line).await.map_err(|e| e.to_string())
+}
+
+async fn recv_public_key<R, W>(conn: &mut LineConn<R, W>) -> Result<Vec<u8>, String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let line = conn.recv_line().await.map_err(|e| e.to_string())?;
+    line.strip_prefix(HANDSHAKE_TAG)
+        .and_then(|key| general_purpose::STANDARD.decode(key.trim()).ok())
+        .ok_or_else(|| "연합 링크 요청이 아닙니다.".to_string())
+}
+
+async fn recv_greeting<R, W>(conn: &mut LineConn<R, W>, cipher: &Aes256Gcm, prefix: &str) -> Result<String, String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let greeting = conn
+        .recv_sealed_text(cipher)
+        .await
+        .map_err(|_| "연합 비밀이 다른 서버입니다.".to_string())?;
+    greeting
+        .strip_prefix(prefix)
+        .filter(|name| !name.is_empty() && !name.contains(' '))
+        .map(str::to_string)
+        .ok_or_else(|| "연합 링크 인사 형식이 잘못되었습니다.".to_string())
+}
+
+// 링크 키 = HKDF(ECDH 세션 키, 연합 비밀 || 연결한 쪽 공개키 || 받은 쪽 공개키)
+fn link_cipher(ecdh: EcdhKey, secret: &[u8], peer_pub: &[u8], transcript: [&[u8]; 2]) -> Result<Aes256Gcm, String> {
+    let session_key = ecdh.derive_aes_key(peer_pub)?;
+    let mut extra = Zeroizing::new(secret.to_vec());
+    for public_key in transcript {
+        extra.extend_from_slice(public_key);
+    }
+    Ok(session_key.derive(&extra, b"chat-federation-v1")?.cipher())
+}
+
+// 최근에 본 메시지 ID (같은 메시지가 다른 경로로 다시 오면 무시)
+#[derive(Default)]
+pub struct Seen {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl Seen {
+    // 처음 보는 ID면 기억하고 true
+    pub fn insert(&mut self, origin: &str, id: &str) -> bool {
+        let key = format!("{}/{}", origin, id);
+        if !self.ids.insert(key.clone()) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > SEEN_CAPACITY
+            && let Some(old) = self.order.pop_front()
+        {
+            self.ids.remove(&old);
+        }
+        true
+    }
+}
+
+// 다른 서버에 접속한 사용자들: 닉네임 → (접속한 서버, 그 소식이 들어온 링크)
+#[derive(Default)]
+pub struct Presence {
+    users: HashMap<String, (String, u64)>,
+}
+
+impl Presence {
+    // 새로 알게 된 사용자 기록. 이미 다른 서버의 같은 닉네임이 있으면 false
+    pub fn join(&mut self, nick: &str, server: &str, link: u64) -> bool {
+        if self.users.contains_key(nick) {
+            return false;
+        }
+        self.users.insert(nick.to_string(), (server.to_string(), link));
+        true
+    }
+
+    // 그 서버에서 나간 사용자 삭제. 기록과 서버나 링크가 다르면 false
+    // (링크가 고리를 이루면 끊긴 링크 너머의 사용자도 다른 링크로 닿을 수 있으므로, 그 사용자를 알려준 링크의 소식만 따름)
+    pub fn part(&mut self, nick: &str, server: &str, link: u64) -> bool {
+        if self.users.get(nick).is_none_or(|(s, l)| s != server || *l != link) {
+            return false;
+        }
+        self.users.remove(nick);
+        true
+    }
+
+    // 링크가 끊겼을 때(netsplit) 그 링크로 알게 된 사용자를 모두 지우고 (닉네임, 서버) 목록을 돌려줌
+    pub fn split(&mut self, link: u64) -> Vec<(String, String)> {
+        let lost: Vec<(String, String)> = self
+            .users
+            .iter()
+            .filter(|(_, (_, l))| *l == link)
+            .map(|(nick, (server, _))| (nick.clone(), server.clone()))
+            .collect();
+        for (nick, _) in &lost {
+            self.users.remove(nick);
+        }
+        lost
+    }
+
+    pub fn server_of(&self, nick: &str) -> Option<&str> {
+        self.users.get(nick).map(|(server, _)| server.as_str())
+    }
+
+    // 새 링크에 알려줄 (닉네임, 서버) 목록
+    pub fn snapshot(&self) -> Vec<(String, String)> {
+        self.users.iter().map(|(nick, (server, _))| (nick.clone(), server.clone())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rejects_invalid_names() {
+        assert!(FedMessage::parse("JOIN alpha id1 alice").is_some());
+        assert!(FedMessage::parse("DM alpha id2 alice bob QUJD").is_some());
+        // 제어 문자나 규칙에 맞지 않는 이름이 들어간 메시지는 버림
+        assert!(FedMessage::parse("JOIN alpha id1 al\u{1b}[2Jice").is_none());
+        assert!(FedMessage::parse("JOIN al\u{7}pha id1 alice").is_none());
+        assert!(FedMessage::parse("DM alpha id2 alice b.o.b QUJD").is_none());
+        assert!(FedMessage::parse("DM alpha id2 알리스 bob QUJD").is_none());
+        assert!(FedMessage::parse("GROUP alpha id3 team\u{0} alice QUJD").is_none());
+        assert!(FedMessage::parse(&format!("PART alpha id4 {}", "a".repeat(33))).is_none());
+    }
+}
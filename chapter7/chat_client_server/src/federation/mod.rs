@@ -0,0 +1 @@
+pub mod link;
\ No newline at end of file
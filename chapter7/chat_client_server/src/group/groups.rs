@@ -0,0 +1,146 @@
+// src/group/groups.rs
+// 이 모듈은 클라이언트가 속한 TreeKEM 그룹들과, 서버가 순서를 확정하기 전의 내 변경(Commit)을 관리합니다.
+// 서버는 "#<그룹>" 메시지를 보낸 사람을 포함한 모두에게 같은 순서로 전달하므로,
+//   - 내 Commit이 되돌아오면 그때 새 epoch를 적용하고 새 멤버에게 Welcome을 보냄
+//   - 다른 멤버의 Commit이 먼저 오면 내 Commit은 버림 (같은 epoch에는 먼저 도착한 Commit 하나만 유효)
+
+use std::collections::BTreeMap;
+
+use p256::SecretKey;
+
+use crate::treekem::{self, Group, Peek, Processed, Proposal};
+
+struct PendingCommit {
+    commit: Vec<u8>,
+    welcomes: Vec<Vec<u8>>,
+    next: Group,
+}
+
+// 그룹 메시지를 처리한 결과: 이어서 서버에 보내야 할 메시지(Welcome)와, 받은 평문 (봉투 해석과 출력은 chatclient가 함)
+#[derive(Default)]
+pub struct Received {
+    pub outgoing: Vec<Vec<u8>>,
+    pub plaintext: Option<Vec<u8>>,
+}
+
+pub struct Groups {
+    nick: String,
+    // 다른 멤버가 나를 그룹에 추가할 때 쓰는 초대 키 (공개키는 신원 공지에 서명해서 알림)
+    init_key: SecretKey,
+    groups: BTreeMap<String, Group>,
+    pending: BTreeMap<String, PendingCommit>,
+}
+
+impl Groups {
+    pub fn new(nick: &str) -> Self {
+        Self {
+            nick: nick.to_string(),
+            init_key: treekem::generate_init_key(),
+            groups: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    pub fn init_public_key(&self) -> Vec<u8> {
+        treekem::public_bytes(&self.init_key)
+    }
+
+    pub fn create(&mut self, name: &str) -> Result<(), String> {
+        if self.groups.contains_key(name) {
+            return Err(format!("이미 {} 그룹에 속해 있습니다.", name));
+        }
+        self.groups.insert(name.to_string(), Group::create(name, &self.nick));
+        Ok(())
+    }
+
+    // 멤버 추가/삭제/키 갱신 Commit을 만들어 서버에 보낼 메시지를 돌려줌 (적용은 서버가 되돌려준 뒤)
+    pub fn propose(&mut self, name: &str, proposals: &[Proposal]) -> Result<Vec<u8>, String> {
+        let group = self.groups.get(name).ok_or_else(|| format!("{} 그룹에 속해 있지 않습니다.", name))?;
+        if self.pending.contains_key(name) {
+            return Err("이전 변경이 아직 확정되지 않았습니다.".to_string());
+        }
+        let output = group.commit(proposals)?;
+        let commit = output.commit.clone();
+        self.pending.insert(
+            name.to_string(),
+            PendingCommit {
+                commit: output.commit,
+                welcomes: output.welcomes.into_iter().map(|(_, welcome)| welcome).collect(),
+                next: output.pending,
+            },
+        );
+        Ok(commit)
+    }
+
+    pub fn seal(&self, name: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let group = self.groups.get(name).ok_or_else(|| format!("{} 그룹에 속해 있지 않습니다.", name))?;
+        group.seal(&self.nick, plaintext)
+    }
+
+    // 서버가 전달한 그룹 메시지 처리
+    pub fn receive(&mut self, name: &str, sender: &str, message: &[u8]) -> Result<Received, String> {
+        match treekem::peek(message) {
+            Some(Peek::Commit { epoch }) => {
+                let outgoing = self.receive_commit(name, epoch, message)?;
+                Ok(Received { outgoing, plaintext: None })
+            }
+            Some(Peek::Welcome { recipient }) => {
+                if recipient != self.nick || self.groups.contains_key(name) {
+                    return Ok(Received::default());
+                }
+                let group = Group::join(message, &self.nick, &self.init_key)?;
+                if group.id() != name {
+                    return Err("Welcome의 그룹 이름이 다릅니다.".to_string());
+                }
+                say!("🎉 {}가 {} 그룹에 초대했습니다. 멤버: {}", sender, name, group.members().join(", "));
+                self.groups.insert(name.to_string(), group);
+                Ok(Received::default())
+            }
+            Some(Peek::App) => {
+                // 내가 보낸 메시지도 되돌아오지만, 입력할 때 이미 보였으므로 출력하지 않음
+                let Some(group) = self.groups.get(name).filter(|_| sender != self.nick) else {
+                    return Ok(Received::default());
+                };
+                let plaintext = group.open(sender, message)?;
+                Ok(Received { outgoing: vec![], plaintext: Some(plaintext) })
+            }
+            None => Err("알 수 없는 그룹 메시지입니다.".to_string()),
+        }
+    }
+
+    fn receive_commit(&mut self, name: &str, epoch: u64, message: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        // 내가 없는 그룹이거나, 같은 epoch에 늦게 도착해서 무효가 된 Commit은 조용히 무시
+        let Some(group) = self.groups.get_mut(name).filter(|g| g.epoch() == epoch) else {
+            return Ok(vec![]);
+        };
+
+        if let Some(pending) = self.pending.remove(name) {
+            if pending.commit == message {
+                *group = pending.next;
+                say!("✅ {} 그룹 변경 확정 (epoch {}). 멤버: {}", name, group.epoch(), group.members().join(", "));
+                return Ok(pending.welcomes);
+            }
+            say!("⚠️ 다른 멤버의 변경이 먼저 적용되어 {} 그룹에 대한 내 변경을 취소했습니다. 다시 시도하세요.", name);
+        }
+
+        match group.process_commit(message)? {
+            Processed::Advanced => {
+                say!("🔄 {} 그룹 키가 바뀌었습니다 (epoch {}). 멤버: {}", name, group.epoch(), group.members().join(", "));
+            }
+            Processed::Removed => {
+                self.groups.remove(name);
+                say!("🚪 {} 그룹에서 삭제되었습니다.", name);
+            }
+        }
+        Ok(vec![])
+    }
+
+    pub fn print(&self) {
+        if self.groups.is_empty() {
+            say!("   (속한 그룹이 없습니다)");
+        }
+        for (name, group) in &self.groups {
+            say!("   #{} (epoch {}): {}", name, group.epoch(), group.members().join(", "));
+        }
+    }
+}
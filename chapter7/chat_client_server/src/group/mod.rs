@@ -0,0 +1,2 @@
+pub mod groups;
+pub mod treekem;
\ No newline at end of file
@@ -0,0 +1,940 @@
+// src/group/treekem.rs
+// 이 모듈은 MLS의 TreeKEM을 단순화한 그룹 키 합의(ratchet tree)를 담당합니다.
+//   - 멤버는 이진 트리의 잎(leaf)이고, 각 노드는 P-256 키 쌍을 가짐. 노드 비밀키는 그 아래 멤버만 앎
+//   - 변경(Commit)하는 멤버는 자기 잎부터 루트까지의 경로 키를 새로 만들고, 각 경로 비밀을 형제 쪽 하위 트리의
+//     노드 공개키로만 암호화해서 보냄 → 추가/삭제/갱신 한 번에 O(log n)번의 암호화
+//   - 루트까지 올라간 경로 비밀이 새 epoch 비밀이 되고, 여기서 그룹 메시지 키를 유도
+//   - 삭제된 멤버의 잎과 조상 노드는 비워지므로, 삭제된 멤버는 새 경로 비밀을 받을 수 없음
+//   - 서버는 메시지를 순서대로 전달만 하고 어떤 비밀도 모름 (Commit은 이전 epoch 비밀로 만든 MAC으로 인증)
+// 트리는 MLS와 같은 배열 표현(잎은 짝수 인덱스, 부모 노드는 홀수 인덱스)을 쓰고, 잎 개수는 항상 2의 거듭제곱입니다.
+
+use std::collections::BTreeMap;
+
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Nonce,
+};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use p256::{elliptic_curve::sec1::ToEncodedPoint, PublicKey, SecretKey};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::secret::AesKey;
+
+type Key = Zeroizing<[u8; 32]>;
+
+// 메시지 종류 (첫 바이트)
+const KIND_COMMIT: u8 = 1;
+const KIND_WELCOME: u8 = 2;
+const KIND_APP: u8 = 3;
+
+const PUBLIC_KEY_LEN: usize = 33;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Node {
+    public: Vec<u8>,
+    // 이 노드의 비밀키를 아직 모르는, 나중에 추가된 잎 번호들 (이 노드에 암호화할 때 함께 암호화해야 함)
+    unmerged: Vec<u32>,
+    // 잎 노드일 때 멤버 닉네임
+    nick: Option<String>,
+}
+
+// 그룹 변경 요청
+#[derive(Clone, Debug)]
+pub enum Proposal {
+    Add { nick: String, init_key: Vec<u8> },
+    Remove { nick: String },
+}
+
+// Commit을 받아 처리한 결과
+pub enum Processed {
+    // 새 epoch로 넘어감
+    Advanced,
+    // 내가 그룹에서 삭제됨
+    Removed,
+}
+
+// Commit을 만든 결과. 서버에 보낼 메시지와 새 멤버용 Welcome, 그리고 서버가 순서를 확정하면 적용할 새 상태
+pub struct CommitOutput {
+    pub commit: Vec<u8>,
+    pub welcomes: Vec<(String, Vec<u8>)>,
+    pub pending: Group,
+}
+
+#[derive(Clone)]
+pub struct Group {
+    id: String,
+    epoch: u64,
+    epoch_secret: Key,
+    my_leaf: u32,
+    tree: Vec<Option<Node>>,
+    // 내가 비밀키를 아는 노드들 (내 잎과 조상 노드 일부)
+    privates: BTreeMap<usize, SecretKey>,
+}
+
+impl Group {
+    // 나 혼자인 새 그룹
+    pub fn create(id: &str, my_nick: &str) -> Self {
+        let leaf_key = SecretKey::random(&mut OsRng);
+        let mut epoch_secret = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(epoch_secret.as_mut());
+        let tree = vec![Some(Node { public: public_bytes(&leaf_key), unmerged: vec![], nick: Some(my_nick.to_string()) })];
+        Self {
+            id: id.to_string(),
+            epoch: 0,
+            epoch_secret,
+            my_leaf: 0,
+            tree,
+            privates: BTreeMap::from([(0, leaf_key)]),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    pub fn members(&self) -> Vec<String> {
+        (0..self.leaf_count())
+            .filter_map(|leaf| self.leaf_node(leaf).and_then(|n| n.nick.clone()))
+            .collect()
+    }
+
+    fn leaf_count(&self) -> u32 {
+        self.tree.len().div_ceil(2) as u32
+    }
+
+    fn leaf_node(&self, leaf: u32) -> Option<&Node> {
+        self.tree[2 * leaf as usize].as_ref()
+    }
+
+    fn find_leaf(&self, nick: &str) -> Option<u32> {
+        (0..self.leaf_count()).find(|&leaf| self.leaf_node(leaf).and_then(|n| n.nick.as_deref()) == Some(nick))
+    }
+
+    // 변경 요청을 트리에 반영 (Commit을 만드는 쪽과 받는 쪽이 똑같이 실행)
+    // 추가된 잎 번호 목록을 돌려줌
+    fn apply_proposals(&mut self, proposals: &[Proposal]) -> Result<Vec<u32>, String> {
+        let mut added = vec![];
+        for proposal in proposals {
+            match proposal {
+                Proposal::Add { nick, init_key } => {
+                    if self.find_leaf(nick).is_some() {
+                        return Err(format!("{}는 이미 그룹 멤버입니다.", nick));
+                    }
+                    PublicKey::from_sec1_bytes(init_key).map_err(|_| "초대 키 형식이 잘못되었습니다.".to_string())?;
+                    let leaf = match (0..self.leaf_count()).find(|&l| self.leaf_node(l).is_none()) {
+                        Some(leaf) => leaf,
+                        None => {
+                            // 빈 잎이 없으면 트리를 두 배로 키움 (기존 노드의 인덱스는 그대로)
+                            let leaf = self.leaf_count();
+                            self.tree.resize(self.tree.len() * 2 + 1, None);
+                            leaf
+                        }
+                    };
+                    let node = 2 * leaf as usize;
+                    self.tree[node] = Some(Node { public: init_key.clone(), unmerged: vec![], nick: Some(nick.clone()) });
+                    for ancestor in direct_path(node, self.tree.len()) {
+                        if let Some(parent) = self.tree[ancestor].as_mut() {
+                            parent.unmerged.push(leaf);
+                        }
+                    }
+                    added.push(leaf);
+                }
+                Proposal::Remove { nick } => {
+                    let leaf = self.find_leaf(nick).ok_or_else(|| format!("{}는 그룹 멤버가 아닙니다.", nick))?;
+                    let node = 2 * leaf as usize;
+                    self.tree[node] = None;
+                    self.privates.remove(&node);
+                    for ancestor in direct_path(node, self.tree.len()) {
+                        self.tree[ancestor] = None;
+                        self.privates.remove(&ancestor);
+                    }
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    // 노드의 resolution: 이 노드 아래 멤버 전체에게 암호화하려면 실제로 암호화해야 하는 노드 목록
+    fn resolution(&self, node: usize) -> Vec<usize> {
+        match &self.tree[node] {
+            Some(n) => std::iter::once(node).chain(n.unmerged.iter().map(|&l| 2 * l as usize)).collect(),
+            None if level(node) == 0 => vec![],
+            None => {
+                let mut out = self.resolution(left(node));
+                out.extend(self.resolution(right(node)));
+                out
+            }
+        }
+    }
+
+    // 변경 요청(없으면 내 키만 갱신)을 담은 Commit 생성
+    pub fn commit(&self, proposals: &[Proposal]) -> Result<CommitOutput, String> {
+        let mut next = self.clone();
+        let added = next.apply_proposals(proposals)?;
+        if next.leaf_node(self.my_leaf).is_none() {
+            return Err("자기 자신은 삭제할 수 없습니다.".to_string());
+        }
+        let my_node = 2 * self.my_leaf as usize;
+        let path = direct_path(my_node, next.tree.len());
+
+        // 1. 내 잎과 경로 노드의 새 키 생성 (경로 비밀은 아래에서 위로 한 단계씩 유도)
+        let mut leaf_secret = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(leaf_secret.as_mut());
+        let leaf_key = node_key(&leaf_secret)?;
+        next.privates.clear();
+        next.set_leaf_public(my_node, &leaf_key);
+        next.privates.insert(my_node, leaf_key);
+
+        let mut path_secrets = vec![];
+        let mut secret = derive(&leaf_secret, b"path");
+        for &node in &path {
+            let key = node_key(&secret)?;
+            next.tree[node] = Some(Node { public: public_bytes(&key), unmerged: vec![], nick: None });
+            next.privates.insert(node, key);
+            let following = derive(&secret, b"path");
+            path_secrets.push(std::mem::replace(&mut secret, following));
+        }
+        let commit_secret = secret;
+
+        // 2. 각 경로 비밀을 형제 쪽 하위 트리의 resolution에 암호화 (새 멤버는 Welcome으로 받으므로 제외)
+        let added_nodes: Vec<usize> = added.iter().map(|&l| 2 * l as usize).collect();
+        let aad = self.context_aad();
+        let mut encrypted = vec![];
+        let mut child = my_node;
+        for (&node, path_secret) in path.iter().zip(&path_secrets) {
+            let mut entries = vec![];
+            for recipient in next.resolution(sibling(child, next.tree.len())) {
+                if added_nodes.contains(&recipient) {
+                    continue;
+                }
+                let public = &next.tree[recipient].as_ref().unwrap().public;
+                entries.push((recipient as u32, hpke_seal(public, &aad, path_secret.as_slice())?));
+            }
+            encrypted.push(entries);
+            child = node;
+        }
+
+        // 3. 새 epoch 비밀과 확인 태그
+        next.advance_epoch(&commit_secret);
+        let confirmation = next.confirmation_tag();
+
+        let mut w = Writer::new(KIND_COMMIT);
+        w.bytes(self.id.as_bytes());
+        w.u64(self.epoch);
+        w.u32(self.my_leaf);
+        write_proposals(&mut w, proposals);
+        w.bytes(&next.leaf_node(self.my_leaf).unwrap().public);
+        w.u32(path.len() as u32);
+        for (&node, entries) in path.iter().zip(&encrypted) {
+            w.bytes(&next.tree[node].as_ref().unwrap().public);
+            w.u32(entries.len() as u32);
+            for (recipient, ciphertext) in entries {
+                w.u32(*recipient);
+                w.bytes(ciphertext);
+            }
+        }
+        w.raw(&confirmation);
+        let commit = self.tag_commit(&w.buf);
+
+        // 4. 새 멤버마다 Welcome: 새 epoch 비밀, 트리, 나와 공통 조상 노드의 경로 비밀을 초대 키로 암호화
+        let mut welcomes = vec![];
+        for &leaf in &added {
+            let new_node = 2 * leaf as usize;
+            let (position, _) = path
+                .iter()
+                .enumerate()
+                .find(|&(_, &node)| is_ancestor(node, new_node))
+                .ok_or("공통 조상 노드를 찾을 수 없습니다.")?;
+            let mut plain = Writer::new(KIND_WELCOME);
+            plain.bytes(next.id.as_bytes());
+            plain.u64(next.epoch);
+            plain.raw(next.epoch_secret.as_slice());
+            plain.u32(leaf);
+            plain.u32(self.my_leaf);
+            plain.raw(path_secrets[position].as_slice());
+            next.write_tree(&mut plain);
+            let plain = Zeroizing::new(plain.buf);
+
+            let nick = next.leaf_node(leaf).unwrap().nick.clone().unwrap();
+            let init_key = next.leaf_node(leaf).unwrap().public.clone();
+            let welcome = seal_welcome(&nick, &init_key, &plain)?;
+            welcomes.push((nick, welcome));
+        }
+
+        Ok(CommitOutput { commit, welcomes, pending: next })
+    }
+
+    // 다른 멤버의 Commit 처리
+    pub fn process_commit(&mut self, message: &[u8]) -> Result<Processed, String> {
+        let body_len = message.len().checked_sub(TAG_LEN).ok_or_else(bad_format)?;
+        let (body, membership) = message.split_at(body_len);
+        if !verify_tag(&self.epoch_secret, b"membership", body, membership) {
+            return Err("Commit 인증 실패 (다른 epoch이거나 위조된 메시지)".to_string());
+        }
+
+        let mut r = Reader::new(body, KIND_COMMIT)?;
+        if r.bytes()? != self.id.as_bytes() || r.u64()? != self.epoch {
+            return Err("다른 그룹이나 epoch의 Commit입니다.".to_string());
+        }
+        let committer = r.u32()?;
+        let proposals = read_proposals(&mut r)?;
+
+        let mut next = self.clone();
+        next.apply_proposals(&proposals)?;
+        if next.leaf_node(self.my_leaf).is_none() {
+            return Ok(Processed::Removed);
+        }
+        if committer >= next.leaf_count() || committer == self.my_leaf || next.leaf_node(committer).is_none() {
+            return Err("Commit을 만든 멤버가 잘못되었습니다.".to_string());
+        }
+        let committer_node = 2 * committer as usize;
+        let path = direct_path(committer_node, next.tree.len());
+
+        // 1. 새 공개키 반영. 경로 노드에 대해 예전에 알던 비밀키는 버림
+        let leaf_public = r.bytes()?.to_vec();
+        PublicKey::from_sec1_bytes(&leaf_public).map_err(|_| bad_format())?;
+        next.tree[committer_node].as_mut().unwrap().public = leaf_public;
+        if r.u32()? as usize != path.len() {
+            return Err(bad_format());
+        }
+        let mut publics = vec![];
+        let mut encrypted = vec![];
+        for &node in &path {
+            publics.push(r.bytes()?.to_vec());
+            let mut entries = vec![];
+            for _ in 0..r.u32()? {
+                entries.push((r.u32()? as usize, r.bytes()?.to_vec()));
+            }
+            encrypted.push(entries);
+            next.privates.remove(&node);
+        }
+        let confirmation = r.raw(TAG_LEN)?.to_vec();
+        r.finish()?;
+
+        // 2. 경로에서 나와 처음 만나는 노드의 경로 비밀을 내가 가진 비밀키로 복호화한 뒤 루트까지 유도
+        let my_node = 2 * self.my_leaf as usize;
+        let start = path.iter().position(|&node| is_ancestor(node, my_node)).ok_or_else(bad_format)?;
+        let aad = self.context_aad();
+        let (recipient_key, ciphertext) = encrypted[start]
+            .iter()
+            .find_map(|(recipient, ciphertext)| next.privates.get(recipient).map(|key| (key, ciphertext)))
+            .ok_or("이 Commit에서 내게 온 경로 비밀이 없습니다.")?;
+        let plain = Zeroizing::new(hpke_open(recipient_key, &aad, ciphertext)?);
+        let mut secret: Key = Zeroizing::new(plain.as_slice().try_into().map_err(|_| bad_format())?);
+
+        for (i, &node) in path.iter().enumerate() {
+            next.tree[node] = Some(Node { public: publics[i].clone(), unmerged: vec![], nick: None });
+            if i >= start {
+                let key = node_key(&secret)?;
+                if public_bytes(&key) != publics[i] {
+                    return Err("경로 비밀과 공개키가 일치하지 않습니다.".to_string());
+                }
+                next.privates.insert(node, key);
+                secret = derive(&secret, b"path");
+            }
+        }
+
+        // 3. 새 epoch 비밀 유도 후 확인 태그로 모두 같은 상태인지 검증
+        next.advance_epoch(&secret);
+        if next.confirmation_tag().as_slice() != confirmation.as_slice() {
+            return Err("새 epoch 확인 태그가 일치하지 않습니다.".to_string());
+        }
+        *self = next;
+        Ok(Processed::Advanced)
+    }
+
+    // Welcome을 받아 그룹에 합류. init_key는 초대 키로 공개했던 키의 비밀키
+    pub fn join(message: &[u8], my_nick: &str, init_key: &SecretKey) -> Result<Group, String> {
+        let mut r = Reader::new(message, KIND_WELCOME)?;
+        if r.bytes()? != my_nick.as_bytes() {
+            return Err("다른 멤버에게 온 Welcome입니다.".to_string());
+        }
+        let sealed = r.bytes()?.to_vec();
+        r.finish()?;
+        let plain = Zeroizing::new(hpke_open(init_key, my_nick.as_bytes(), &sealed)?);
+
+        let mut r = Reader::new(&plain, KIND_WELCOME)?;
+        let id = String::from_utf8(r.bytes()?.to_vec()).map_err(|_| bad_format())?;
+        let epoch = r.u64()?;
+        let epoch_secret: Key = Zeroizing::new(r.raw(32)?.try_into().unwrap());
+        let my_leaf = r.u32()?;
+        let committer = r.u32()?;
+        let mut secret: Key = Zeroizing::new(r.raw(32)?.try_into().unwrap());
+        let tree = read_tree(&mut r)?;
+        r.finish()?;
+
+        let my_node = 2 * my_leaf as usize;
+        let committer_node = 2 * committer as usize;
+        if my_node >= tree.len() || committer_node >= tree.len() {
+            return Err(bad_format());
+        }
+        let mut group = Group { id, epoch, epoch_secret, my_leaf, tree, privates: BTreeMap::new() };
+        match group.leaf_node(my_leaf) {
+            Some(node) if node.public == public_bytes(init_key) && node.nick.as_deref() == Some(my_nick) => {}
+            _ => return Err("Welcome의 트리에 내 잎이 없습니다.".to_string()),
+        }
+        group.privates.insert(my_node, init_key.clone());
+
+        // 공통 조상부터 루트까지의 비밀키 복원
+        let path = direct_path(committer_node, group.tree.len());
+        let start = path.iter().position(|&node| is_ancestor(node, my_node)).ok_or_else(bad_format)?;
+        for &node in &path[start..] {
+            let key = node_key(&secret)?;
+            if group.tree[node].as_ref().map(|n| &n.public) != Some(&public_bytes(&key)) {
+                return Err("경로 비밀과 공개키가 일치하지 않습니다.".to_string());
+            }
+            group.privates.insert(node, key);
+            secret = derive(&secret, b"path");
+        }
+        Ok(group)
+    }
+
+    // 그룹 메시지 암호화 (epoch 비밀에서 유도한 키 사용). 보낸 사람은 AAD에 넣어 바꿔치기를 막음
+    pub fn seal(&self, sender: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        self.seal_with_nonce(sender, plaintext, nonce)
+    }
+
+    // 정해진 nonce로 암호화 (프로토콜 테스트 벡터 전용)
+    pub fn seal_with_nonce(&self, sender: &str, plaintext: &[u8], nonce: [u8; NONCE_LEN]) -> Result<Vec<u8>, String> {
+        let ciphertext = AesKey::from_bytes(derive(&self.epoch_secret, b"app"))
+            .cipher()
+            .encrypt(&Nonce::from(nonce), Payload { msg: plaintext, aad: &self.app_aad(sender) })
+            .map_err(|_| "그룹 메시지 암호화 실패".to_string())?;
+        let mut w = Writer::new(KIND_APP);
+        w.u64(self.epoch);
+        w.raw(&nonce);
+        w.raw(&ciphertext);
+        Ok(w.buf)
+    }
+
+    pub fn open(&self, sender: &str, message: &[u8]) -> Result<Vec<u8>, String> {
+        let mut r = Reader::new(message, KIND_APP)?;
+        if r.u64()? != self.epoch {
+            return Err("다른 epoch의 그룹 메시지입니다.".to_string());
+        }
+        let nonce: [u8; NONCE_LEN] = r.raw(NONCE_LEN)?.try_into().unwrap();
+        let ciphertext = r.rest();
+        AesKey::from_bytes(derive(&self.epoch_secret, b"app"))
+            .cipher()
+            .decrypt(&Nonce::from(nonce), Payload { msg: ciphertext, aad: &self.app_aad(sender) })
+            .map_err(|_| "그룹 메시지 복호화 실패".to_string())
+    }
+
+    // 새 epoch 비밀 = HKDF(이전 epoch 비밀, commit 비밀, 그룹 id || epoch || 트리 해시)
+    fn advance_epoch(&mut self, commit_secret: &Key) {
+        self.epoch += 1;
+        let mut info = b"chat-treekem-epoch-v1".to_vec();
+        info.extend_from_slice(&self.context_aad());
+        info.extend_from_slice(&self.tree_hash());
+        let mut next = Zeroizing::new([0u8; 32]);
+        Hkdf::<Sha256>::new(Some(self.epoch_secret.as_slice()), commit_secret.as_slice())
+            .expand(&info, next.as_mut())
+            .expect("32바이트 출력은 항상 가능");
+        self.epoch_secret = next;
+    }
+
+    fn context_aad(&self) -> Vec<u8> {
+        let mut aad = (self.id.len() as u16).to_be_bytes().to_vec();
+        aad.extend_from_slice(self.id.as_bytes());
+        aad.extend_from_slice(&self.epoch.to_be_bytes());
+        aad
+    }
+
+    fn app_aad(&self, sender: &str) -> Vec<u8> {
+        let mut aad = self.context_aad();
+        aad.extend_from_slice(sender.as_bytes());
+        aad
+    }
+
+    fn tree_hash(&self) -> [u8; 32] {
+        let mut w = Writer::new(0);
+        self.write_tree(&mut w);
+        Sha256::digest(&w.buf).into()
+    }
+
+    fn confirmation_tag(&self) -> [u8; TAG_LEN] {
+        tag(&self.epoch_secret, b"confirm", &self.tree_hash())
+    }
+
+    fn membership_tag(&self, body: &[u8]) -> [u8; TAG_LEN] {
+        tag(&self.epoch_secret, b"membership", body)
+    }
+
+    // Commit 본문 뒤에 멤버십 태그를 붙임 (그룹 멤버라면 누구나 임의의 본문으로 만들 수 있으므로 퍼징에서도 사용)
+    pub fn tag_commit(&self, body: &[u8]) -> Vec<u8> {
+        [body, &self.membership_tag(body)].concat()
+    }
+
+    fn set_leaf_public(&mut self, node: usize, key: &SecretKey) {
+        if let Some(leaf) = self.tree[node].as_mut() {
+            leaf.public = public_bytes(key);
+        }
+    }
+
+    fn write_tree(&self, w: &mut Writer) {
+        w.u32(self.tree.len() as u32);
+        for node in &self.tree {
+            match node {
+                None => w.u8(0),
+                Some(node) => {
+                    w.u8(1);
+                    w.bytes(&node.public);
+                    w.u32(node.unmerged.len() as u32);
+                    for &leaf in &node.unmerged {
+                        w.u32(leaf);
+                    }
+                    w.bytes(node.nick.as_deref().unwrap_or("").as_bytes());
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Group {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Group")
+            .field("id", &self.id)
+            .field("epoch", &self.epoch)
+            .field("members", &self.members())
+            .finish_non_exhaustive()
+    }
+}
+
+// 받은 그룹 메시지의 종류 (복호화 전에 라우팅용으로 보는 암호화되지 않은 부분)
+pub enum Peek {
+    Commit { epoch: u64 },
+    Welcome { recipient: String },
+    App,
+}
+
+pub fn peek(message: &[u8]) -> Option<Peek> {
+    match *message.first()? {
+        KIND_COMMIT => {
+            let mut r = Reader::new(message, KIND_COMMIT).ok()?;
+            r.bytes().ok()?;
+            Some(Peek::Commit { epoch: r.u64().ok()? })
+        }
+        KIND_WELCOME => {
+            let mut r = Reader::new(message, KIND_WELCOME).ok()?;
+            Some(Peek::Welcome { recipient: String::from_utf8(r.bytes().ok()?.to_vec()).ok()? })
+        }
+        KIND_APP => Some(Peek::App),
+        _ => None,
+    }
+}
+
+// 새 멤버의 초대 키로 Welcome 내용을 암호화 (초대 키는 공개되어 있으므로 누구나 임의의 내용으로 만들 수 있고, 퍼징에서도 사용)
+pub fn seal_welcome(nick: &str, init_key: &[u8], plain: &[u8]) -> Result<Vec<u8>, String> {
+    let mut w = Writer::new(KIND_WELCOME);
+    w.bytes(nick.as_bytes());
+    w.bytes(&hpke_seal(init_key, nick.as_bytes(), plain)?);
+    Ok(w.buf)
+}
+
+// 초대 키 (다른 멤버가 나를 그룹에 추가할 때 쓰는 공개키)
+pub fn generate_init_key() -> SecretKey {
+    SecretKey::random(&mut OsRng)
+}
+
+pub fn public_bytes(key: &SecretKey) -> Vec<u8> {
+    key.public_key().to_encoded_point(true).as_bytes().to_vec()
+}
+
+fn write_proposals(w: &mut Writer, proposals: &[Proposal]) {
+    w.u32(proposals.len() as u32);
+    for proposal in proposals {
+        match proposal {
+            Proposal::Add { nick, init_key } => {
+                w.u8(1);
+                w.bytes(nick.as_bytes());
+                w.bytes(init_key);
+            }
+            Proposal::Remove { nick } => {
+                w.u8(2);
+                w.bytes(nick.as_bytes());
+            }
+        }
+    }
+}
+
+fn read_proposals(r: &mut Reader) -> Result<Vec<Proposal>, String> {
+    let mut proposals = vec![];
+    for _ in 0..r.u32()? {
+        let kind = r.u8()?;
+        let nick = String::from_utf8(r.bytes()?.to_vec()).map_err(|_| bad_format())?;
+        proposals.push(match kind {
+            1 => Proposal::Add { nick, init_key: r.bytes()?.to_vec() },
+            2 => Proposal::Remove { nick },
+            _ => return Err(bad_format()),
+        });
+    }
+    Ok(proposals)
+}
+
+fn read_tree(r: &mut Reader) -> Result<Vec<Option<Node>>, String> {
+    let len = r.u32()? as usize;
+    // 잎 개수가 2의 거듭제곱인 트리만 허용
+    if len == 0 || !(len + 1).is_power_of_two() || len > 1 << 16 {
+        return Err(bad_format());
+    }
+    let mut tree = Vec::with_capacity(len);
+    for _ in 0..len {
+        tree.push(match r.u8()? {
+            0 => None,
+            1 => {
+                let public = r.bytes()?.to_vec();
+                let unmerged = (0..r.u32()?).map(|_| r.u32()).collect::<Result<_, _>>()?;
+                let nick = String::from_utf8(r.bytes()?.to_vec()).map_err(|_| bad_format())?;
+                Some(Node { public, unmerged, nick: (!nick.is_empty()).then_some(nick) })
+            }
+            _ => return Err(bad_format()),
+        });
+    }
+    // unmerged에는 그 노드 아래에 실제로 있는 잎만 올 수 있음 (나중에 내 Commit을 만들 때 그 잎에 암호화함)
+    for (node, entry) in tree.iter().enumerate() {
+        let Some(entry) = entry else { continue };
+        let valid = |&leaf: &u32| {
+            let leaf_node = 2 * leaf as usize;
+            level(node) > 0 && is_ancestor(node, leaf_node) && tree.get(leaf_node).is_some_and(Option::is_some)
+        };
+        if !entry.unmerged.iter().all(valid) {
+            return Err(bad_format());
+        }
+    }
+    Ok(tree)
+}
+
+// ---------------- 트리 인덱스 계산 (MLS 배열 표현) ----------------
+
+fn level(node: usize) -> u32 {
+    node.trailing_ones()
+}
+
+fn left(node: usize) -> usize {
+    node ^ (1 << (level(node) - 1))
+}
+
+fn right(node: usize) -> usize {
+    node ^ (3 << (level(node) - 1))
+}
+
+fn root(len: usize) -> usize {
+    len / 2
+}
+
+fn parent(node: usize) -> usize {
+    let k = level(node);
+    let b = (node >> (k + 1)) & 1;
+    (node | (1 << k)) ^ (b << (k + 1))
+}
+
+fn sibling(node: usize, len: usize) -> usize {
+    debug_assert!(node != root(len));
+    let p = parent(node);
+    if node < p { right(p) } else { left(p) }
+}
+
+// 잎에서 루트까지의 조상 노드들 (잎 자신은 제외)
+fn direct_path(node: usize, len: usize) -> Vec<usize> {
+    let mut path = vec![];
+    let mut x = node;
+    while x != root(len) {
+        x = parent(x);
+        path.push(x);
+    }
+    path
+}
+
+fn is_ancestor(ancestor: usize, node: usize) -> bool {
+    let span = (1usize << level(ancestor)) - 1;
+    ancestor - span <= node && node <= ancestor + span
+}
+
+// ---------------- 키 유도와 공개키 암호화 ----------------
+
+fn derive(secret: &Key, label: &[u8]) -> Key {
+    let mut info = b"chat-treekem-".to_vec();
+    info.extend_from_slice(label);
+    let mut out = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(None, secret.as_slice())
+        .expand(&info, out.as_mut())
+        .expect("32바이트 출력은 항상 가능");
+    out
+}
+
+// 경로 비밀에서 노드 키 쌍을 결정적으로 만듦 (유효 범위를 벗어나면 카운터를 바꿔 다시 시도)
+fn node_key(secret: &Key) -> Result<SecretKey, String> {
+    for counter in 0u8..=255 {
+        let bytes = derive(secret, &[b"node".as_slice(), &[counter]].concat());
+        if let Ok(key) = SecretKey::from_slice(bytes.as_slice()) {
+            return Ok(key);
+        }
+    }
+    Err("노드 키 생성 실패".to_string())
+}
+
+fn tag(secret: &Key, label: &[u8], data: &[u8]) -> [u8; TAG_LEN] {
+    let key = derive(secret, label);
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_slice()).expect("HMAC은 모든 키 길이를 허용");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+fn verify_tag(secret: &Key, label: &[u8], data: &[u8], expected: &[u8]) -> bool {
+    let key = derive(secret, label);
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_slice()).expect("HMAC은 모든 키 길이를 허용");
+    mac.update(data);
+    mac.verify_slice(expected).is_ok()
+}
+
+// 공개키 암호화 (HPKE를 단순화): 임시 키와 받는 쪽 공개키로 ECDH → HKDF → AES-GCM
+// 출력: 임시 공개키(33) || 암호문. 키가 매번 새로 만들어지므로 nonce는 0으로 고정
+fn hpke_seal(recipient: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let recipient_key = PublicKey::from_sec1_bytes(recipient).map_err(|_| "공개키 형식이 잘못되었습니다.".to_string())?;
+    let ephemeral = SecretKey::random(&mut OsRng);
+    let ephemeral_public = public_bytes(&ephemeral);
+    let key = hpke_key(&ephemeral, &recipient_key, &ephemeral_public, recipient);
+    let ciphertext = key
+        .cipher()
+        .encrypt(&Nonce::from([0u8; NONCE_LEN]), Payload { msg: plaintext, aad })
+        .map_err(|_| "암호화 실패".to_string())?;
+    Ok([ephemeral_public, ciphertext].concat())
+}
+
+fn hpke_open(secret: &SecretKey, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, String> {
+    if sealed.len() < PUBLIC_KEY_LEN {
+        return Err(bad_format());
+    }
+    let (ephemeral_public, ciphertext) = sealed.split_at(PUBLIC_KEY_LEN);
+    let ephemeral = PublicKey::from_sec1_bytes(ephemeral_public).map_err(|_| bad_format())?;
+    let key = hpke_key(secret, &ephemeral, ephemeral_public, &public_bytes(secret));
+    key.cipher()
+        .decrypt(&Nonce::from([0u8; NONCE_LEN]), Payload { msg: ciphertext, aad })
+        .map_err(|_| "복호화 실패".to_string())
+}
+
+fn hpke_key(secret: &SecretKey, public: &PublicKey, ephemeral_public: &[u8], recipient: &[u8]) -> AesKey {
+    let shared = p256::ecdh::diffie_hellman(secret.to_nonzero_scalar(), public.as_affine());
+    let info = [b"chat-treekem-hpke-v1".as_slice(), ephemeral_public, recipient].concat();
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(None, shared.raw_secret_bytes())
+        .expand(&info, key.as_mut())
+        .expect("32바이트 출력은 항상 가능");
+    AesKey::from_bytes(key)
+}
+
+// ---------------- 바이너리 인코딩 ----------------
+
+fn bad_format() -> String {
+    "그룹 메시지 형식이 잘못되었습니다.".to_string()
+}
+
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn new(kind: u8) -> Self {
+        Self { buf: vec![kind] }
+    }
+    fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+    fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+    }
+    fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+    }
+    fn raw(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.raw(bytes);
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8], kind: u8) -> Result<Self, String> {
+        match buf.split_first() {
+            Some((&k, rest)) if k == kind => Ok(Self { buf: rest }),
+            _ => Err(bad_format()),
+        }
+    }
+    fn raw(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.buf.len() < len {
+            return Err(bad_format());
+        }
+        let (head, tail) = self.buf.split_at(len);
+        self.buf = tail;
+        Ok(head)
+    }
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.raw(1)?[0])
+    }
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.raw(4)?.try_into().unwrap()))
+    }
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_be_bytes(self.raw(8)?.try_into().unwrap()))
+    }
+    fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u32()? as usize;
+        self.raw(len)
+    }
+    fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.buf)
+    }
+    fn finish(&self) -> Result<(), String> {
+        if self.buf.is_empty() { Ok(()) } else { Err(bad_format()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 서버처럼 Commit을 모든 멤버에게 순서대로 전달하는 그룹 시뮬레이션
+    struct Sim {
+        members: BTreeMap<String, Group>,
+    }
+
+    impl Sim {
+        fn new(creator: &str) -> Self {
+            Self { members: BTreeMap::from([(creator.to_string(), Group::create("team", creator))]) }
+        }
+
+        // committer가 Commit을 만들고, 다른 멤버는 처리, 새 멤버는 Welcome으로 참가. 삭제된 멤버의 마지막 상태를 돌려줌
+        fn commit(&mut self, committer: &str, adds: &[String], removes: &[&str]) -> Vec<(String, Group)> {
+            let init_keys: Vec<(String, SecretKey)> = adds.iter().map(|nick| (nick.clone(), generate_init_key())).collect();
+            let mut proposals: Vec<Proposal> = init_keys
+                .iter()
+                .map(|(nick, key)| Proposal::Add { nick: nick.clone(), init_key: public_bytes(key) })
+                .collect();
+            proposals.extend(removes.iter().map(|nick| Proposal::Remove { nick: nick.to_string() }));
+            let output = self.members[committer].commit(&proposals).unwrap();
+
+            let mut removed = vec![];
+            for (nick, group) in self.members.iter_mut().filter(|(nick, _)| *nick != committer) {
+                match group.process_commit(&output.commit).unwrap() {
+                    Processed::Advanced => assert!(!removes.contains(&nick.as_str()), "{}는 삭제되어야 함", nick),
+                    Processed::Removed => {
+                        assert!(removes.contains(&nick.as_str()), "{}는 남아 있어야 함", nick);
+                        removed.push((nick.clone(), group.clone()));
+                    }
+                }
+            }
+            for (nick, _) in &removed {
+                self.members.remove(nick);
+            }
+            self.members.insert(committer.to_string(), output.pending);
+            for (nick, key) in &init_keys {
+                let welcome = &output.welcomes.iter().find(|(to, _)| to == nick).expect("새 멤버의 Welcome").1;
+                self.members.insert(nick.clone(), Group::join(welcome, nick, key).unwrap());
+            }
+            self.assert_agreement();
+            removed
+        }
+
+        // 모든 멤버가 같은 epoch, 같은 epoch 비밀, 같은 멤버 목록을 가져야 함
+        fn assert_agreement(&self) {
+            let (_, first) = self.members.iter().next().unwrap();
+            let mut names: Vec<String> = first.members();
+            names.sort();
+            assert_eq!(names, self.members.keys().cloned().collect::<Vec<_>>());
+            for (nick, group) in &self.members {
+                assert_eq!(group.epoch(), first.epoch(), "{}의 epoch", nick);
+                assert_eq!(group.epoch_secret, first.epoch_secret, "{}의 epoch 비밀", nick);
+                assert_eq!(group.tree_hash(), first.tree_hash(), "{}의 트리", nick);
+            }
+        }
+    }
+
+    fn nicks(range: std::ops::Range<usize>) -> Vec<String> {
+        range.map(|i| format!("m{:02}", i)).collect()
+    }
+
+    #[test]
+    fn dozens_of_members_join_and_leave() {
+        let mut sim = Sim::new("m00");
+        // 여러 멤버가 돌아가며 한 번에 1~5명씩 추가 (잎 개수가 2의 거듭제곱을 여러 번 넘음)
+        let mut next = 1;
+        for round in 0..10 {
+            let count = round % 5 + 1;
+            let committer = sim.members.keys().nth(round * 7 % sim.members.len()).unwrap().clone();
+            sim.commit(&committer, &nicks(next..next + count), &[]);
+            next += count;
+        }
+        assert_eq!(sim.members.len(), 31);
+
+        // 삭제와 추가를 섞고, 빈 Commit(키 갱신)도 처리
+        for round in 0..8 {
+            let names: Vec<String> = sim.members.keys().cloned().collect();
+            let committer = names[round * 3 % names.len()].clone();
+            let victims: Vec<&str> = names.iter().map(String::as_str).filter(|n| *n != committer).skip(round).step_by(9).take(2).collect();
+            sim.commit(&committer, &nicks(next..next + 1), &victims);
+            next += 1;
+        }
+        let committer = sim.members.keys().last().unwrap().clone();
+        sim.commit(&committer, &[], &[]);
+        assert!(sim.members.len() >= 20);
+    }
+
+    #[test]
+    fn removed_member_cannot_follow_later_epochs() {
+        let mut sim = Sim::new("m00");
+        sim.commit("m00", &nicks(1..12), &[]);
+        let removed = sim.commit("m03", &[], &["m07"]);
+        let (_, mut stale) = removed.into_iter().next().expect("m07의 마지막 상태");
+
+        // 삭제된 뒤의 Commit과 그룹 메시지는 처리할 수 없어야 함
+        let output = sim.members["m05"].commit(&[]).unwrap();
+        for (nick, group) in sim.members.iter_mut().filter(|(nick, _)| *nick != "m05") {
+            assert!(matches!(group.process_commit(&output.commit), Ok(Processed::Advanced)), "{}", nick);
+        }
+        sim.members.insert("m05".to_string(), output.pending);
+        sim.assert_agreement();
+        assert!(stale.process_commit(&output.commit).is_err());
+
+        let message = sim.members["m01"].seal("m01", "m07 없이".as_bytes()).unwrap();
+        assert_eq!(sim.members["m02"].open("m01", &message).unwrap(), "m07 없이".as_bytes());
+        assert!(stale.open("m01", &message).is_err());
+        // 삭제 직전 epoch의 비밀로 지금 epoch의 메시지 키를 만들 수도 없음
+        assert_ne!(stale.epoch_secret, sim.members["m01"].epoch_secret);
+    }
+
+    #[test]
+    fn app_message_binds_sender() {
+        let mut sim = Sim::new("alice");
+        sim.commit("alice", &["bob".to_string(), "carol".to_string()], &[]);
+        let message = sim.members["alice"].seal("alice", b"hi").unwrap();
+        assert!(sim.members["bob"].open("carol", &message).is_err());
+        assert_eq!(sim.members["bob"].open("alice", &message).unwrap(), b"hi");
+    }
+
+    // cargo-fuzz가 찾은 입력 (첫 바이트 1: 초대 키로 암호화할 Welcome 내용). 트리 밖 leaf를 unmerged로 가리키는 Welcome은 거부해야 함
+    #[test]
+    fn fuzz_regression_welcome_unmerged_leaf_out_of_tree() {
+        let data = include_bytes!("../../fuzz/regressions/treekem/welcome-unmerged-leaf-out-of-tree");
+        let [1, input @ ..] = &data[..] else { panic!("Welcome 내용 입력이어야 함") };
+        let init_key = SecretKey::from_slice(&[9; 32]).unwrap();
+        let welcome = seal_welcome("bob", &public_bytes(&init_key), input).unwrap();
+        assert!(Group::join(&welcome, "bob", &init_key).is_err());
+    }
+}
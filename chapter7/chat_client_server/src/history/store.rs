@@ -0,0 +1,234 @@
+// src/history/store.rs
+// 이 모듈은 chatclient가 주고받은 메시지를 로컬 SQLite 파일에 암호화해서 보관하고, 검색하는 일을 담당합니다.
+// 키: Argon2id(기록 암호, 솔트) → HKDF로 암호화 키와 태그 키를 따로 유도 (솔트와 암호 확인값은 meta 테이블에 저장)
+// 메시지 한 건 = 한 행: (태그, 방 태그, AES-256-GCM(JSON {방, 시각, 보낸 사람, 내용}))
+//   - 태그 = HMAC(태그 키, 메시지 ID): 같은 메시지가 다시 와도 한 번만 저장하고, 수정/삭제할 행을 찾는 데 사용
+//   - 방 태그 = HMAC(태그 키, 방 이름): 방 이름을 드러내지 않고 방별 최근 기록을 찾는 데 사용
+//   - 행의 태그를 AAD로 묶어서, 파일을 고쳐 다른 행의 암호문과 바꿔치기하면 복호화가 실패함
+// 내용이 모두 암호문이므로 검색은 기록을 하나씩 복호화해서 찾습니다 (개인 대화 기록 규모에서는 충분히 빠름).
+
+use std::path::Path;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{AeadCore, Aes256Gcm, Nonce};
+use argon2::Argon2;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::packet::NONCE_LEN;
+
+const SALT_LEN: usize = 16;
+// 암호가 맞는지 확인하려고 암호화해 두는 값
+const CHECK_PLAINTEXT: &[u8] = b"chat-history-check-v1";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub room: String,
+    pub ts: i64,
+    pub sender: String,
+    pub body: String,
+}
+
+pub struct History {
+    db: Connection,
+    cipher: Aes256Gcm,
+    tag_key: Zeroizing<[u8; 32]>,
+}
+
+impl History {
+    // 기록 파일을 열거나 새로 만듦. 기존 파일인데 암호가 다르면 오류
+    pub fn open(path: &Path, passphrase: &str) -> Result<Self, String> {
+        let db = Connection::open(path).map_err(|e| format!("기록 파일을 열 수 없습니다: {}", e))?;
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value BLOB NOT NULL);
+             CREATE TABLE IF NOT EXISTS messages (
+                 seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                 tag BLOB NOT NULL UNIQUE,
+                 room_tag BLOB NOT NULL,
+                 data BLOB NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS messages_room ON messages (room_tag, seq);",
+        )
+        .map_err(db_error)?;
+        restrict_permissions(path);
+
+        let salt: Option<Vec<u8>> = db
+            .query_row("SELECT value FROM meta WHERE key = 'salt'", [], |row| row.get(0))
+            .optional()
+            .map_err(db_error)?;
+        let is_new = salt.is_none();
+        let salt = salt.unwrap_or_else(|| {
+            let mut salt = vec![0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            salt
+        });
+
+        let mut master = Zeroizing::new([0u8; 32]);
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, master.as_mut())
+            .map_err(|e| format!("Argon2 키 유도 실패: {}", e))?;
+        let hkdf = Hkdf::<Sha256>::new(Some(&salt), master.as_slice());
+        let mut enc_key = Zeroizing::new([0u8; 32]);
+        let mut tag_key = Zeroizing::new([0u8; 32]);
+        hkdf.expand(b"chat-history-enc-v1", enc_key.as_mut()).expect("HKDF 출력 길이");
+        hkdf.expand(b"chat-history-tag-v1", tag_key.as_mut()).expect("HKDF 출력 길이");
+
+        let history = Self { db, cipher: Aes256Gcm::new(&(*enc_key).into()), tag_key };
+        if is_new {
+            let check = history.seal(b"check", CHECK_PLAINTEXT);
+            history
+                .db
+                .execute("INSERT INTO meta (key, value) VALUES ('salt', ?1), ('check', ?2)", params![salt, check])
+                .map_err(db_error)?;
+        } else {
+            let check: Vec<u8> = history
+                .db
+                .query_row("SELECT value FROM meta WHERE key = 'check'", [], |row| row.get(0))
+                .map_err(db_error)?;
+            if history.open_data(b"check", &check).is_none_or(|check| check.as_slice() != CHECK_PLAINTEXT) {
+                return Err("기록 암호가 맞지 않습니다.".to_string());
+            }
+        }
+        Ok(history)
+    }
+
+    // 메시지 한 건 저장. 이미 저장된 ID면 무시
+    pub fn record(&self, id: &str, record: &Record) -> Result<(), String> {
+        let tag = self.tag("id", id);
+        let data = self.seal(&tag, &serde_json::to_vec(record).expect("기록 직렬화는 실패하지 않음"));
+        self.db
+            .execute(
+                "INSERT OR IGNORE INTO messages (tag, room_tag, data) VALUES (?1, ?2, ?3)",
+                params![tag, self.tag("room", &record.room), data],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    // 저장된 메시지의 내용을 고침. 원래 보낸 사람이 아니면 무시
+    pub fn edit(&self, id: &str, sender: &str, body: &str) -> Result<(), String> {
+        let tag = self.tag("id", id);
+        let Some(mut record) = self.get(&tag)?.filter(|r| r.sender == sender) else {
+            return Ok(());
+        };
+        record.body = body.to_string();
+        let data = self.seal(&tag, &serde_json::to_vec(&record).expect("기록 직렬화는 실패하지 않음"));
+        self.db
+            .execute("UPDATE messages SET data = ?1 WHERE tag = ?2", params![data, tag])
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    // 저장된 메시지를 지움. 원래 보낸 사람이 아니면 무시
+    pub fn delete(&self, id: &str, sender: &str) -> Result<(), String> {
+        let tag = self.tag("id", id);
+        if self.get(&tag)?.is_some_and(|r| r.sender == sender) {
+            self.db.execute("DELETE FROM messages WHERE tag = ?1", params![tag]).map_err(db_error)?;
+        }
+        Ok(())
+    }
+
+    // 방의 최근 메시지 n개 (오래된 것부터)
+    pub fn recent(&self, room: &str, n: usize) -> Result<Vec<Record>, String> {
+        let mut statement = self
+            .db
+            .prepare("SELECT tag, data FROM messages WHERE room_tag = ?1 ORDER BY seq DESC LIMIT ?2")
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![self.tag("room", room), n as i64], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(db_error)?;
+        let mut records = self.decrypt_rows(rows)?;
+        records.reverse();
+        Ok(records)
+    }
+
+    // 내용이나 보낸 사람에 text가 들어 있는 메시지 (대소문자 구분 없이, 최근 것 limit개를 오래된 것부터)
+    pub fn search(&self, text: &str, limit: usize) -> Result<Vec<Record>, String> {
+        let needle = text.to_lowercase();
+        let mut statement = self.db.prepare("SELECT tag, data FROM messages ORDER BY seq DESC").map_err(db_error)?;
+        let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(db_error)?;
+        let mut found: Vec<Record> = self
+            .decrypt_rows(rows)?
+            .into_iter()
+            .filter(|r| r.body.to_lowercase().contains(&needle) || r.sender.to_lowercase().contains(&needle))
+            .take(limit)
+            .collect();
+        found.reverse();
+        Ok(found)
+    }
+
+    fn get(&self, tag: &[u8]) -> Result<Option<Record>, String> {
+        let data: Option<Vec<u8>> = self
+            .db
+            .query_row("SELECT data FROM messages WHERE tag = ?1", params![tag], |row| row.get(0))
+            .optional()
+            .map_err(db_error)?;
+        Ok(data.and_then(|data| self.decrypt_record(tag, &data)))
+    }
+
+    fn decrypt_rows<I>(&self, rows: I) -> Result<Vec<Record>, String>
+    where
+        I: Iterator<Item = rusqlite::Result<(Vec<u8>, Vec<u8>)>>,
+    {
+        let mut records = vec![];
+        for row in rows {
+            let (tag, data) = row.map_err(db_error)?;
+            let record = self
+                .decrypt_record(&tag, &data)
+                .ok_or("기록 파일이 손상되었거나 다른 암호로 만든 행이 있습니다.")?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    fn decrypt_record(&self, tag: &[u8], data: &[u8]) -> Option<Record> {
+        serde_json::from_slice(&self.open_data(tag, data)?).ok()
+    }
+
+    fn tag(&self, kind: &str, value: &str) -> Vec<u8> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.tag_key.as_slice()).expect("HMAC 키 길이");
+        mac.update(kind.as_bytes());
+        mac.update(b":");
+        mac.update(value.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    // nonce || 암호문. 혼자 쓰는 로컬 파일이라 저장하는 메시지 수가 적으므로 랜덤 nonce 사용
+    fn seal(&self, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload { msg: plaintext, aad })
+            .expect("AES-GCM 암호화 실패");
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+        data
+    }
+
+    fn open_data(&self, aad: &[u8], data: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+        if data.len() <= NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().unwrap();
+        self.cipher
+            .decrypt(&Nonce::from(nonce), Payload { msg: ciphertext, aad })
+            .ok()
+            .map(Zeroizing::new)
+    }
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("기록 파일 오류: {}", e)
+}
+
+// 기록 파일은 본인만 읽을 수 있게 (내용은 암호화되어 있지만 메시지 수와 크기는 드러남)
+fn restrict_permissions(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+}
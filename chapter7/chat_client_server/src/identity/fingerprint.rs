@@ -0,0 +1,51 @@
+// src/identity/fingerprint.rs
+// 이 모듈은 공개키를 사람이 소리 내어 비교할 수 있는 숫자(안전 번호)로 바꾸는 로직을 담당합니다.
+// Signal의 안전 번호처럼 한 사람당 30자리, 두 사람을 합쳐 60자리를 5자리씩 끊어 보여줍니다.
+
+use sha2::{Digest, Sha256, Sha512};
+
+const FINGERPRINT_VERSION: &[u8] = b"chat-fingerprint-v1";
+// 해시를 반복해서 무차별 대입으로 비슷한 번호를 찾기 어렵게 함
+const ITERATIONS: usize = 1024;
+
+// 한 사람(닉네임 + 공개키)의 30자리 지문
+pub fn fingerprint(nick: &str, public_key: &[u8]) -> String {
+    let mut hash = Sha512::new()
+        .chain_update(FINGERPRINT_VERSION)
+        .chain_update(public_key)
+        .chain_update(nick.as_bytes())
+        .finalize();
+    for _ in 1..ITERATIONS {
+        hash = Sha512::new().chain_update(hash).chain_update(public_key).finalize();
+    }
+    digits(&hash[..30])
+}
+
+// 두 사람 사이의 60자리 안전 번호. 정렬해서 이어 붙이므로 양쪽에서 같은 번호가 나옴
+pub fn safety_number(my_nick: &str, my_key: &[u8], peer_nick: &str, peer_key: &[u8]) -> String {
+    let mut parts = [fingerprint(my_nick, my_key), fingerprint(peer_nick, peer_key)];
+    parts.sort();
+    format!("{} {}", parts[0], parts[1])
+}
+
+// 서버와의 세션 번호. 서버 로그에 찍힌 번호와 비교하면 중간자가 없었는지 확인할 수 있음
+// binding은 핸드셰이크를 식별하는 값 (ECDH: 서버 공개키 || 클라이언트 공개키, Noise: 핸드셰이크 해시)
+pub fn session_number(binding: &[u8]) -> String {
+    let hash = Sha256::new()
+        .chain_update(b"chat-session-number-v1")
+        .chain_update(binding)
+        .finalize();
+    digits(&hash[..20])
+}
+
+// 5바이트마다 5자리 숫자 하나를 만들어 공백으로 구분
+fn digits(bytes: &[u8]) -> String {
+    bytes
+        .chunks(5)
+        .map(|chunk| {
+            let value = chunk.iter().fold(0u64, |acc, &b| (acc << 8) | u64::from(b));
+            format!("{:05}", value % 100_000)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
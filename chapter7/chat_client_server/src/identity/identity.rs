@@ -0,0 +1,244 @@
+// src/identity/identity.rs
+// 이 모듈은 클라이언트의 장기 신원 키(P-256 ECDSA)의 생성, 저장, 서명/검증을 담당합니다.
+// 신원 키는 서명에만 쓰고, DM 세션을 시작할 때의 ECDH에는 신원 키로 서명한 별도의 prekey(P-256 ECDH)를 씁니다.
+// (신원 키가 새어도 지난 세션의 첫 메시지들을 풀 수 없도록)
+
+use std::path::Path;
+
+use base64::{engine::general_purpose, Engine as _};
+use p256::ecdsa::{signature::Signer, signature::Verifier, Signature, SigningKey, VerifyingKey};
+use p256::{elliptic_curve::sec1::ToEncodedPoint, FieldBytes, PublicKey, SecretKey};
+use rand::rngs::OsRng;
+use zeroize::Zeroizing;
+
+// 신원 공지 서명에 붙이는 도메인 구분 문자열
+const ANNOUNCE_CONTEXT: &[u8] = b"chat-identity-v1";
+// 그룹 초대 키 서명에 붙이는 도메인 구분 문자열
+const KEY_PACKAGE_CONTEXT: &[u8] = b"chat-keypackage-v1";
+// DM prekey 서명에 붙이는 도메인 구분 문자열
+const PREKEY_CONTEXT: &[u8] = b"chat-prekey-v1";
+
+pub struct IdentityKey {
+    signing_key: SigningKey,
+}
+
+impl IdentityKey {
+    // 이번 실행에만 쓰는 일회용 신원 키
+    pub fn generate() -> Self {
+        Self { signing_key: SigningKey::random(&mut OsRng) }
+    }
+
+    // 파일에서 신원 키를 읽고, 없으면 새로 만들어 저장
+    pub fn load_or_create(path: &Path) -> Result<Self, String> {
+        let bytes = load_or_create_secret(path, "신원 키", || Zeroizing::new(Self::generate().signing_key.to_bytes().into()))?;
+        Self::from_secret_bytes(&bytes)
+    }
+
+    // 정해진 비밀키(32바이트 스칼라)로 생성 (키 파일 읽기와 프로토콜 테스트 벡터에서 사용)
+    pub fn from_secret_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let bytes: Zeroizing<[u8; 32]> =
+            Zeroizing::new(bytes.try_into().map_err(|_| "신원 키 길이가 잘못되었습니다.".to_string())?);
+        let signing_key = SigningKey::from_bytes(&FieldBytes::from(*bytes))
+            .map_err(|_| "신원 키 값이 잘못되었습니다.".to_string())?;
+        Ok(Self { signing_key })
+    }
+
+    // 공개키 (SEC1 압축 형식 33바이트)
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        self.signing_key.verifying_key().to_encoded_point(true).as_bytes().to_vec()
+    }
+
+    // "이 닉네임이 이 공개키의 주인"이라는 공지에 서명
+    pub fn sign_announcement(&self, nick: &str) -> Vec<u8> {
+        let signature: Signature = self.signing_key.sign(&announcement(nick, &self.public_key_bytes()));
+        signature.to_bytes().to_vec()
+    }
+
+    // "이 닉네임을 그룹에 추가할 때 이 초대 키를 쓰라"는 값에 서명
+    pub fn sign_key_package(&self, nick: &str, init_key: &[u8]) -> Vec<u8> {
+        let signature: Signature = self.signing_key.sign(&key_package(nick, &self.public_key_bytes(), init_key));
+        signature.to_bytes().to_vec()
+    }
+
+    // "이 닉네임에게 DM 세션을 시작할 때 이 prekey를 쓰라"는 값에 서명
+    pub fn sign_prekey(&self, nick: &str, prekey: &[u8]) -> Vec<u8> {
+        let signature: Signature = self.signing_key.sign(&prekey_message(nick, &self.public_key_bytes(), prekey));
+        signature.to_bytes().to_vec()
+    }
+}
+
+// DM 세션 시작에 쓰는 ECDH 키. 신원 키로 서명해서 신원 공지와 함께 알림
+// 응답자는 이 키를 첫 래칫 키로도 씀 (상대가 온라인이 아니어도 첫 메시지를 보낼 수 있도록)
+pub struct PreKey {
+    secret: SecretKey,
+}
+
+impl PreKey {
+    // 이번 실행에만 쓰는 일회용 prekey
+    pub fn generate() -> Self {
+        Self { secret: SecretKey::random(&mut OsRng) }
+    }
+
+    // 파일에서 prekey를 읽고, 없으면 새로 만들어 저장 (파일을 지우면 다음 실행부터 새 prekey를 씀)
+    pub fn load_or_create(path: &Path) -> Result<Self, String> {
+        let bytes = load_or_create_secret(path, "prekey", || Zeroizing::new(Self::generate().secret.to_bytes().into()))?;
+        Self::from_secret_bytes(&bytes)
+    }
+
+    // 정해진 비밀키(32바이트 스칼라)로 생성 (키 파일 읽기와 프로토콜 테스트 벡터에서 사용)
+    pub fn from_secret_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let secret = SecretKey::from_slice(bytes).map_err(|_| "prekey 값이 잘못되었습니다.".to_string())?;
+        Ok(Self { secret })
+    }
+
+    // 공개키 (SEC1 압축 형식 33바이트)
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        self.secret.public_key().to_encoded_point(true).as_bytes().to_vec()
+    }
+
+    // 상대 prekey와 ECDH (DM 세션의 첫 공유 비밀에 사용)
+    pub fn diffie_hellman(&self, peer_public: &[u8]) -> Result<Zeroizing<[u8; 32]>, String> {
+        let peer = PublicKey::from_sec1_bytes(peer_public)
+            .map_err(|_| "상대 공개키 형식이 잘못되었습니다.".to_string())?;
+        let shared = p256::ecdh::diffie_hellman(self.secret.to_nonzero_scalar(), peer.as_affine());
+        Ok(Zeroizing::new((*shared.raw_secret_bytes()).into()))
+    }
+
+    // 응답자의 첫 래칫 키 (double_ratchet 모듈이 세션 상태에 넣어 둠)
+    pub fn ratchet_key(&self) -> SecretKey {
+        self.secret.clone()
+    }
+}
+
+// 비밀키는 출력하지 않고 공개키만 보여줌 (SecretKey는 drop될 때 스스로 0으로 지움)
+impl std::fmt::Debug for PreKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PreKey")
+            .field("public_key", &general_purpose::STANDARD.encode(self.public_key_bytes()))
+            .finish_non_exhaustive()
+    }
+}
+
+// 비밀키는 출력하지 않고 공개키만 보여줌 (SigningKey는 drop될 때 스스로 0으로 지움)
+impl std::fmt::Debug for IdentityKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdentityKey")
+            .field("public_key", &general_purpose::STANDARD.encode(self.public_key_bytes()))
+            .finish_non_exhaustive()
+    }
+}
+
+// 다른 사람의 신원 공지 서명 검증
+pub fn verify_announcement(nick: &str, public_key: &[u8], signature: &[u8]) -> Result<(), String> {
+    let verifying_key = VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|_| "신원 공개키 형식이 잘못되었습니다.".to_string())?;
+    let signature = Signature::from_slice(signature)
+        .map_err(|_| "신원 서명 형식이 잘못되었습니다.".to_string())?;
+    verifying_key
+        .verify(&announcement(nick, public_key), &signature)
+        .map_err(|_| "신원 서명 검증 실패".to_string())
+}
+
+// 다른 사람의 그룹 초대 키 서명 검증 (public_key는 이미 확인한 신원 공개키)
+pub fn verify_key_package(nick: &str, public_key: &[u8], init_key: &[u8], signature: &[u8]) -> Result<(), String> {
+    let verifying_key = VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|_| "신원 공개키 형식이 잘못되었습니다.".to_string())?;
+    let signature = Signature::from_slice(signature)
+        .map_err(|_| "초대 키 서명 형식이 잘못되었습니다.".to_string())?;
+    verifying_key
+        .verify(&key_package(nick, public_key, init_key), &signature)
+        .map_err(|_| "초대 키 서명 검증 실패".to_string())
+}
+
+// 다른 사람의 prekey 서명 검증 (public_key는 이미 확인한 신원 공개키)
+pub fn verify_prekey(nick: &str, public_key: &[u8], prekey: &[u8], signature: &[u8]) -> Result<(), String> {
+    let verifying_key = VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|_| "신원 공개키 형식이 잘못되었습니다.".to_string())?;
+    let signature = Signature::from_slice(signature)
+        .map_err(|_| "prekey 서명 형식이 잘못되었습니다.".to_string())?;
+    verifying_key
+        .verify(&prekey_message(nick, public_key, prekey), &signature)
+        .map_err(|_| "prekey 서명 검증 실패".to_string())
+}
+
+// 비밀키 파일(Base64 한 줄)을 읽고, 없으면 generate로 만들어 저장
+fn load_or_create_secret(
+    path: &Path,
+    what: &str,
+    generate: impl FnOnce() -> Zeroizing<[u8; 32]>,
+) -> Result<Zeroizing<Vec<u8>>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => {
+            let text = Zeroizing::new(text);
+            let bytes = general_purpose::STANDARD
+                .decode(text.trim())
+                .map_err(|_| format!("{} 파일 형식이 잘못되었습니다.", what))?;
+            Ok(Zeroizing::new(bytes))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let secret = generate();
+            let text = Zeroizing::new(format!("{}\n", general_purpose::STANDARD.encode(secret.as_slice())));
+            std::fs::write(path, text.as_bytes()).map_err(|e| format!("{} 저장 실패: {}", what, e))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+            }
+            Ok(Zeroizing::new(secret.to_vec()))
+        }
+        Err(e) => Err(format!("{}를 읽을 수 없습니다: {}", what, e)),
+    }
+}
+
+fn announcement(nick: &str, public_key: &[u8]) -> Vec<u8> {
+    signed_message(ANNOUNCE_CONTEXT, &[nick.as_bytes(), public_key])
+}
+
+fn key_package(nick: &str, public_key: &[u8], init_key: &[u8]) -> Vec<u8> {
+    signed_message(KEY_PACKAGE_CONTEXT, &[nick.as_bytes(), public_key, init_key])
+}
+
+fn prekey_message(nick: &str, public_key: &[u8], prekey: &[u8]) -> Vec<u8> {
+    signed_message(PREKEY_CONTEXT, &[nick.as_bytes(), public_key, prekey])
+}
+
+fn signed_message(context: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    let mut msg = context.to_vec();
+    for part in parts {
+        msg.extend_from_slice(&(part.len() as u16).to_be_bytes());
+        msg.extend_from_slice(part);
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_hides_secret_keys() {
+        let identity = IdentityKey::generate();
+        let debug = format!("{:?}", identity);
+        assert!(debug.starts_with("IdentityKey"));
+        crate::secret::assert_redacted(&debug, &identity.signing_key.to_bytes());
+
+        let prekey = PreKey::generate();
+        let debug = format!("{:?}", prekey);
+        assert!(debug.starts_with("PreKey"));
+        crate::secret::assert_redacted(&debug, &prekey.secret.to_bytes());
+        crate::secret::assert_redacted(&format!("{:#?}", prekey), &prekey.secret.to_bytes());
+    }
+
+    #[test]
+    fn prekey_signature_binds_nick_and_identity() {
+        let (alice, mallory) = (IdentityKey::generate(), IdentityKey::generate());
+        let prekey = PreKey::generate().public_key_bytes();
+        let signature = alice.sign_prekey("alice", &prekey);
+        assert!(verify_prekey("alice", &alice.public_key_bytes(), &prekey, &signature).is_ok());
+        assert!(verify_prekey("bob", &alice.public_key_bytes(), &prekey, &signature).is_err());
+        assert!(verify_prekey("alice", &mallory.public_key_bytes(), &prekey, &signature).is_err());
+        // 다른 용도(그룹 초대 키)의 서명을 prekey 서명으로 쓸 수 없어야 함
+        let key_package = alice.sign_key_package("alice", &prekey);
+        assert!(verify_prekey("alice", &alice.public_key_bytes(), &prekey, &key_package).is_err());
+    }
+}
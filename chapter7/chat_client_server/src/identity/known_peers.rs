@@ -0,0 +1,114 @@
+// src/identity/known_peers.rs
+// 이 모듈은 지금까지 본 다른 사용자들의 신원 키와 확인(verified) 여부를 저장하는 로직을 담당합니다.
+// 처음 본 키는 그대로 믿고 기록(TOFU)하고, 이후 키가 바뀌면 알려줍니다.
+// 파일 형식: 한 줄에 `닉네임 verified|unverified 공개키(Base64) [prekey(Base64)]`
+// prekey는 신원 공지에서 서명을 확인한 값으로, 접속하지 않은 사람에게 귓속말 세션을 시작할 때 씁니다.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use base64::{engine::general_purpose, Engine as _};
+
+pub struct KnownPeer {
+    pub public_key: Vec<u8>,
+    pub verified: bool,
+    pub prekey: Option<Vec<u8>>,
+}
+
+// 새로 받은 신원 키를 저장된 기록과 비교한 결과
+#[derive(Debug, PartialEq, Eq)]
+pub enum Observation {
+    // 처음 보는 사용자
+    New,
+    // 저장된 키와 같음
+    Unchanged { verified: bool },
+    // 저장된 키와 다름. 이전 키를 확인했었다면 was_verified = true
+    Changed { was_verified: bool },
+}
+
+pub struct KnownPeers {
+    // None이면 파일에 저장하지 않고 이번 실행 동안만 기억
+    path: Option<PathBuf>,
+    peers: BTreeMap<String, KnownPeer>,
+}
+
+impl KnownPeers {
+    pub fn load(path: Option<PathBuf>) -> Result<Self, String> {
+        let mut peers = BTreeMap::new();
+        if let Some(path) = &path {
+            let text = match std::fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(format!("확인된 사용자 파일을 읽을 수 없습니다: {}", e)),
+            };
+            for line in text.lines() {
+                let mut fields = line.split_whitespace();
+                let (Some(nick), Some(state), Some(key)) = (fields.next(), fields.next(), fields.next()) else {
+                    continue;
+                };
+                let Ok(public_key) = general_purpose::STANDARD.decode(key) else {
+                    continue;
+                };
+                let prekey = fields.next().and_then(|k| general_purpose::STANDARD.decode(k).ok());
+                peers.insert(nick.to_string(), KnownPeer { public_key, verified: state == "verified", prekey });
+            }
+        }
+        Ok(Self { path, peers })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut text = String::new();
+        for (nick, peer) in &self.peers {
+            let state = if peer.verified { "verified" } else { "unverified" };
+            text.push_str(&format!("{} {} {}", nick, state, general_purpose::STANDARD.encode(&peer.public_key)));
+            if let Some(prekey) = &peer.prekey {
+                text.push_str(&format!(" {}", general_purpose::STANDARD.encode(prekey)));
+            }
+            text.push('\n');
+        }
+        std::fs::write(path, text).map_err(|e| format!("확인된 사용자 파일 저장 실패: {}", e))
+    }
+
+    pub fn get(&self, nick: &str) -> Option<&KnownPeer> {
+        self.peers.get(nick)
+    }
+
+    // 받은 키를 기록하고 이전 기록과 비교한 결과를 돌려줌. 바뀐 키는 '확인 안 됨'으로 저장
+    pub fn observe(&mut self, nick: &str, public_key: &[u8]) -> Result<Observation, String> {
+        let observation = match self.peers.get(nick) {
+            None => Observation::New,
+            Some(peer) if peer.public_key == public_key => {
+                return Ok(Observation::Unchanged { verified: peer.verified });
+            }
+            Some(peer) => Observation::Changed { was_verified: peer.verified },
+        };
+        self.peers.insert(nick.to_string(), KnownPeer { public_key: public_key.to_vec(), verified: false, prekey: None });
+        self.save()?;
+        Ok(observation)
+    }
+
+    // 지금 기록된 신원 키로 서명을 확인한 prekey를 기록 (observe 다음에 호출)
+    pub fn set_prekey(&mut self, nick: &str, prekey: &[u8]) -> Result<(), String> {
+        let Some(peer) = self.peers.get_mut(nick) else {
+            return Ok(());
+        };
+        if peer.prekey.as_deref() == Some(prekey) {
+            return Ok(());
+        }
+        peer.prekey = Some(prekey.to_vec());
+        self.save()
+    }
+
+    // 안전 번호를 직접 비교한 사용자를 '확인됨'으로 표시
+    pub fn mark_verified(&mut self, nick: &str) -> Result<(), String> {
+        let peer = self
+            .peers
+            .get_mut(nick)
+            .ok_or_else(|| format!("{}의 신원 키를 아직 받지 못했습니다.", nick))?;
+        peer.verified = true;
+        self.save()
+    }
+}
@@ -0,0 +1,3 @@
+pub mod fingerprint;
+pub mod identity;
+pub mod known_peers;
\ No newline at end of file
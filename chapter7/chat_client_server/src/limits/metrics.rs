@@ -0,0 +1,38 @@
+// src/limits/metrics.rs
+// 이 모듈은 서버 전체의 메시지 처리 및 남용(abuse) 통계를 모아두는 카운터를 담당합니다.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Default)]
+pub struct Metrics {
+    pub connections: AtomicU64,
+    pub auth_failures: AtomicU64,
+    pub messages_relayed: AtomicU64,
+    pub rate_limited: AtomicU64,
+    pub oversized: AtomicU64,
+    pub warned: AtomicU64,
+    pub dropped: AtomicU64,
+    pub disconnected: AtomicU64,
+}
+
+impl Metrics {
+    pub fn incr(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // 로그 한 줄로 출력하기 위한 요약 문자열
+    pub fn summary(&self) -> String {
+        let get = |c: &AtomicU64| c.load(Ordering::Relaxed);
+        format!(
+            "접속={} 로그인실패={} 전달={} 속도초과={} 크기초과={} 경고={} 버림={} 강제종료={}",
+            get(&self.connections),
+            get(&self.auth_failures),
+            get(&self.messages_relayed),
+            get(&self.rate_limited),
+            get(&self.oversized),
+            get(&self.warned),
+            get(&self.dropped),
+            get(&self.disconnected),
+        )
+    }
+}
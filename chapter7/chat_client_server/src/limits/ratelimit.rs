@@ -0,0 +1,105 @@
+// src/limits/ratelimit.rs
+// 이 모듈은 연결별 토큰 버킷 속도 제한과, 위반 횟수에 따른 단계적 대응(경고 → 버림 → 연결 종료)을 담당합니다.
+
+use std::time::{Duration, Instant};
+
+// 마지막 위반 이후 이 시간 동안 조용하면 위반 횟수를 초기화
+const STRIKE_DECAY: Duration = Duration::from_secs(30);
+
+// 토큰 버킷: 초당 rate 개씩 토큰이 차고, 최대 burst 개까지 모아둘 수 있음
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self { rate, burst, tokens: burst, last: Instant::now() }
+    }
+
+    // 메시지 하나를 보낼 토큰이 있으면 소비하고 true 반환
+    pub fn try_take(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// 위반이 발생했을 때 서버가 취할 조치
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    // 경고만 보내고 메시지는 전달
+    Warn,
+    // 경고와 함께 메시지를 버림
+    Drop,
+    // 연결을 끊음
+    Disconnect,
+}
+
+// 위반 유형 (로그와 통계용)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    RateLimited,
+    Oversized,
+}
+
+// 위반 횟수에 따라 단계적으로 대응 수준을 높이는 정책
+pub struct AbusePolicy {
+    warn_strikes: u32,
+    drop_strikes: u32,
+    strikes: u32,
+    last_violation: Option<Instant>,
+}
+
+impl AbusePolicy {
+    // warn_strikes 회까지는 경고, drop_strikes 회까지는 버림, 그 이후는 연결 종료
+    pub fn new(warn_strikes: u32, drop_strikes: u32) -> Self {
+        Self {
+            warn_strikes,
+            drop_strikes: drop_strikes.max(warn_strikes),
+            strikes: 0,
+            last_violation: None,
+        }
+    }
+
+    pub fn strikes(&self) -> u32 {
+        self.strikes
+    }
+
+    // 위반을 기록하고 취할 조치를 돌려줌
+    pub fn record(&mut self, violation: Violation) -> Verdict {
+        let now = Instant::now();
+        if let Some(last) = self.last_violation
+            && now.duration_since(last) > STRIKE_DECAY
+        {
+            self.strikes = 0;
+        }
+        self.last_violation = Some(now);
+        self.strikes += 1;
+
+        let verdict = if self.strikes <= self.warn_strikes {
+            Verdict::Warn
+        } else if self.strikes <= self.drop_strikes {
+            Verdict::Drop
+        } else {
+            Verdict::Disconnect
+        };
+
+        // 잘린 메시지는 전달할 수 없으므로 경고 단계라도 버림
+        match (violation, verdict) {
+            (Violation::Oversized, Verdict::Warn) => Verdict::Drop,
+            _ => verdict,
+        }
+    }
+}
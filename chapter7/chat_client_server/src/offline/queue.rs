@@ -0,0 +1,294 @@
+// src/offline/queue.rs
+// 이 모듈은 접속하지 않은 등록 사용자에게 온 귓속말과 멘션을 보관했다가 다음 로그인 때 전달하는 큐를 담당합니다.
+// 파일 형식: 한 줄에 메시지 하나
+//   `<받는 사람> <보관 시각> dm <보낸 사람> <Double Ratchet 메시지(Base64)>`
+//   `<받는 사람> <보관 시각> mention <보낸 사람> <서버 시각> <큐 키로 암호화한 패킷>`
+// 귓속말은 서버가 풀 수 없는 종단간 암호문 그대로 보관합니다.
+// 멘션은 Room Key가 서버를 다시 시작할 때마다 바뀔 수 있으므로 (--keystore 없이 실행하면), 파일에 따로 저장한 큐 키(<큐 파일>.key)로 다시 암호화해 둡니다.
+// 사용자마다 보관 용량과 기간에 한도가 있고, 넘으면 오래된 메시지부터 버립니다.
+// 전달은 peek으로 꺼내 보고, 연결에 다 쓴 메시지만 ack로 지웁니다. (전달 중에 연결이 끊겨도 남은 메시지는 다음 로그인 때 다시 전달)
+
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use aes_gcm::Aes256Gcm;
+use base64::{engine::general_purpose, Engine as _};
+use rand::{rngs::OsRng, RngCore};
+use zeroize::Zeroizing;
+
+use crate::packet;
+use crate::secret::AesKey;
+
+enum Stored {
+    Dm { payload: String },
+    Mention { ts: i64, sealed: String },
+}
+
+struct Item {
+    // 이번 실행 동안만 쓰는 번호 (ack로 지울 메시지를 가리킴, 파일에는 저장하지 않음)
+    seq: u64,
+    queued_at: i64,
+    from: String,
+    stored: Stored,
+}
+
+impl Item {
+    // 용량 한도 계산에 쓰는 크기 (암호문 길이)
+    fn size(&self) -> usize {
+        match &self.stored {
+            Stored::Dm { payload } => payload.len(),
+            Stored::Mention { sealed, .. } => sealed.len(),
+        }
+    }
+}
+
+// 로그인한 사용자에게 전달할 보관 메시지
+pub enum Delivery {
+    // 귓속말 원문 그대로 ("@<보낸 사람> <payload>"로 전달)
+    Dm { from: String, payload: String },
+    // 방 메시지 평문 (지금의 Room Key로 다시 암호화해서 전달)
+    Mention { from: String, ts: i64, plaintext: Zeroizing<Vec<u8>> },
+}
+
+pub struct OfflineQueue {
+    path: PathBuf,
+    cipher: Aes256Gcm,
+    max_bytes: usize,
+    max_age_ms: i64,
+    queues: BTreeMap<String, VecDeque<Item>>,
+    next_seq: u64,
+}
+
+impl OfflineQueue {
+    // 큐 파일과 큐 키를 읽어옴. 없으면 빈 큐와 새 키로 시작
+    pub fn load(path: &Path, max_bytes: usize, max_age_ms: i64) -> Result<Self, String> {
+        let key = load_or_create_key(&path.with_extension("key"))?;
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("오프라인 큐 파일을 읽을 수 없습니다: {}", e)),
+        };
+
+        let mut queues: BTreeMap<String, VecDeque<Item>> = BTreeMap::new();
+        let mut next_seq = 0;
+        for (i, line) in text.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (to, item) = parse_line(line, next_seq).ok_or_else(|| format!("오프라인 큐 파일 {}번째 줄의 형식이 잘못되었습니다.", i + 1))?;
+            queues.entry(to).or_default().push_back(item);
+            next_seq += 1;
+        }
+
+        let mut queue = Self { path: path.to_path_buf(), cipher: key.cipher(), max_bytes, max_age_ms, queues, next_seq };
+        queue.prune();
+        Ok(queue)
+    }
+
+    // 귓속말 보관. 용량 한도 때문에 버린 오래된 메시지 수를 돌려줌
+    pub fn push_dm(&mut self, to: &str, from: &str, payload: &str) -> Result<usize, String> {
+        // 파일은 공백으로 필드를 나누므로 Base64가 아닌 내용은 다시 읽을 때 잘림
+        if general_purpose::STANDARD.decode(payload).is_err() {
+            return Err("귓속말 내용이 Base64 형식이 아닙니다.".to_string());
+        }
+        self.push(to, from, Stored::Dm { payload: payload.to_string() })
+    }
+
+    // 멘션 보관. plaintext는 Room Key로 복호화한 방 메시지
+    pub fn push_mention(&mut self, to: &str, from: &str, ts: i64, plaintext: &[u8]) -> Result<usize, String> {
+        let sealed = packet::seal(&self.cipher, plaintext);
+        self.push(to, from, Stored::Mention { ts, sealed })
+    }
+
+    // 사용자의 보관 메시지를 번호와 함께 모두 돌려줌 (큐에서는 지우지 않음)
+    // 풀 수 없는 멘션은 그 메시지만 Err로 돌려주므로, 전달하는 쪽이 기록을 남기고 ack로 지우면 됨
+    pub fn peek(&mut self, nick: &str) -> Vec<(u64, Result<Delivery, String>)> {
+        self.prune();
+        let Some(items) = self.queues.get(nick) else {
+            return vec![];
+        };
+        items
+            .iter()
+            .map(|item| {
+                let delivery = match &item.stored {
+                    Stored::Dm { payload } => Ok(Delivery::Dm { from: item.from.clone(), payload: payload.clone() }),
+                    Stored::Mention { ts, sealed } => packet::open(&self.cipher, sealed)
+                        .map(|plaintext| Delivery::Mention { from: item.from.clone(), ts: *ts, plaintext: Zeroizing::new(plaintext) }),
+                };
+                (item.seq, delivery)
+            })
+            .collect()
+    }
+
+    // 전달을 마친 메시지를 큐와 파일에서 지움
+    pub fn ack(&mut self, nick: &str, seqs: &[u64]) -> Result<(), String> {
+        let Some(queue) = self.queues.get_mut(nick) else {
+            return Ok(());
+        };
+        queue.retain(|item| !seqs.contains(&item.seq));
+        self.queues.retain(|_, queue| !queue.is_empty());
+        self.save()
+    }
+
+    fn push(&mut self, to: &str, from: &str, stored: Stored) -> Result<usize, String> {
+        let item = Item { seq: self.next_seq, queued_at: crate::envelope::now_millis(), from: from.to_string(), stored };
+        if item.size() > self.max_bytes {
+            return Err("보관 한도보다 큰 메시지입니다.".to_string());
+        }
+        self.prune();
+        self.next_seq += 1;
+
+        let queue = self.queues.entry(to.to_string()).or_default();
+        queue.push_back(item);
+        let mut dropped = 0;
+        while queue.iter().map(Item::size).sum::<usize>() > self.max_bytes {
+            queue.pop_front();
+            dropped += 1;
+        }
+        self.save()?;
+        Ok(dropped)
+    }
+
+    // 보관 기간이 지난 메시지를 버림
+    fn prune(&mut self) {
+        let oldest = crate::envelope::now_millis() - self.max_age_ms;
+        for queue in self.queues.values_mut() {
+            queue.retain(|item| item.queued_at >= oldest);
+        }
+        self.queues.retain(|_, queue| !queue.is_empty());
+    }
+
+    // 임시 파일에 쓴 뒤 이름을 바꿔서 중간에 깨진 파일이 남지 않도록 저장
+    fn save(&self) -> Result<(), String> {
+        let mut text = String::from("# chatserver offline queue\n");
+        for (to, queue) in &self.queues {
+            for item in queue {
+                match &item.stored {
+                    Stored::Dm { payload } => {
+                        text.push_str(&format!("{} {} dm {} {}\n", to, item.queued_at, item.from, payload));
+                    }
+                    Stored::Mention { ts, sealed } => {
+                        text.push_str(&format!("{} {} mention {} {} {}\n", to, item.queued_at, item.from, ts, sealed));
+                    }
+                }
+            }
+        }
+
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, text).map_err(|e| format!("오프라인 큐 저장 실패: {}", e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600));
+        }
+        std::fs::rename(&tmp, &self.path).map_err(|e| format!("오프라인 큐 저장 실패: {}", e))
+    }
+}
+
+fn parse_line(line: &str, seq: u64) -> Option<(String, Item)> {
+    let mut fields = line.split(' ');
+    let (to, queued_at, kind, from) = (fields.next()?, fields.next()?.parse().ok()?, fields.next()?, fields.next()?);
+    let stored = match kind {
+        "dm" => Stored::Dm { payload: fields.next()?.to_string() },
+        "mention" => Stored::Mention { ts: fields.next()?.parse().ok()?, sealed: fields.next()?.to_string() },
+        _ => return None,
+    };
+    Some((to.to_string(), Item { seq, queued_at, from: from.to_string(), stored }))
+}
+
+// 큐 키 파일(Base64 한 줄)을 읽고, 없으면 새로 만들어 저장
+fn load_or_create_key(path: &Path) -> Result<AesKey, String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => {
+            let text = Zeroizing::new(text);
+            let bytes = Zeroizing::new(
+                general_purpose::STANDARD
+                    .decode(text.trim())
+                    .map_err(|_| "오프라인 큐 키 파일 형식이 잘못되었습니다.".to_string())?,
+            );
+            let bytes: [u8; 32] = bytes
+                .as_slice()
+                .try_into()
+                .map_err(|_| "오프라인 큐 키 길이가 잘못되었습니다.".to_string())?;
+            Ok(AesKey::from_bytes(Zeroizing::new(bytes)))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let mut bytes = Zeroizing::new([0u8; 32]);
+            OsRng.fill_bytes(bytes.as_mut());
+            let text = Zeroizing::new(format!("{}\n", general_purpose::STANDARD.encode(bytes.as_slice())));
+            std::fs::write(path, text.as_bytes()).map_err(|e| format!("오프라인 큐 키 저장 실패: {}", e))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+            }
+            Ok(AesKey::from_bytes(bytes))
+        }
+        Err(e) => Err(format!("오프라인 큐 키를 읽을 수 없습니다: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dm_payload_survives_reload_and_spaces_are_rejected() {
+        let path = std::env::temp_dir().join(format!("chat-offline-test-{}.txt", std::process::id()));
+        let mut queue = OfflineQueue::load(&path, 1024, 60_000).unwrap();
+        assert!(queue.push_dm("bob", "alice", "QUJD REVG").is_err());
+        assert!(queue.push_dm("bob", "alice", "not base64!").is_err());
+        queue.push_dm("bob", "alice", "QUJDREVG").unwrap();
+
+        // 다시 읽어도 내용이 그대로여야 함
+        let mut reloaded = OfflineQueue::load(&path, 1024, 60_000).unwrap();
+        let deliveries = reloaded.peek("bob");
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("key"));
+        assert_eq!(deliveries.len(), 1);
+        match &deliveries[0].1 {
+            Ok(Delivery::Dm { from, payload }) => assert_eq!((from.as_str(), payload.as_str()), ("alice", "QUJDREVG")),
+            _ => panic!("귓속말이어야 함"),
+        }
+    }
+
+    #[test]
+    fn interrupted_delivery_keeps_unsent_items() {
+        let path = std::env::temp_dir().join(format!("chat-offline-partial-{}.txt", std::process::id()));
+        let mut queue = OfflineQueue::load(&path, 4096, 60_000).unwrap();
+        queue.push_dm("bob", "alice", "MQ==").unwrap();
+        queue.push_mention("bob", "carol", 7, b"@bob hi").unwrap();
+        queue.push_dm("bob", "dave", "Mw==").unwrap();
+        // 파일이 손상되어 풀 수 없는 멘션이 섞여 있어도 다른 메시지는 그대로 꺼낼 수 있어야 함
+        queue.queues.get_mut("bob").unwrap().push_back(Item {
+            seq: 99,
+            queued_at: crate::envelope::now_millis(),
+            from: "eve".to_string(),
+            stored: Stored::Mention { ts: 8, sealed: "AAAA".to_string() },
+        });
+
+        let pending = queue.peek("bob");
+        assert_eq!(pending.len(), 4);
+        assert!(pending[..3].iter().all(|(_, delivery)| delivery.is_ok()));
+        assert!(pending[3].1.is_err());
+
+        // 첫 메시지를 쓴 뒤 연결이 끊긴 경우: 쓴 것과 풀 수 없는 것만 지움
+        queue.ack("bob", &[pending[0].0, pending[3].0]).unwrap();
+        let mut reloaded = OfflineQueue::load(&path, 4096, 60_000).unwrap();
+        let remaining = reloaded.peek("bob");
+        assert_eq!(remaining.len(), 2);
+        match &remaining[0].1 {
+            Ok(Delivery::Mention { from, ts, plaintext }) => assert_eq!((from.as_str(), *ts, plaintext.as_slice()), ("carol", 7, &b"@bob hi"[..])),
+            _ => panic!("멘션이어야 함"),
+        }
+        assert!(matches!(&remaining[1].1, Ok(Delivery::Dm { from, .. }) if from == "dave"));
+
+        // 나머지를 전달하고 나면 큐가 비어야 함
+        let seqs: Vec<u64> = remaining.iter().map(|(seq, _)| *seq).collect();
+        reloaded.ack("bob", &seqs).unwrap();
+        let empty = OfflineQueue::load(&path, 4096, 60_000).unwrap().peek("bob").len();
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("key"));
+        assert_eq!(empty, 0);
+    }
+}
@@ -0,0 +1 @@
+pub mod spake2;
\ No newline at end of file
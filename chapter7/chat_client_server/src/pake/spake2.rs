@@ -0,0 +1,209 @@
+// src/pake/spake2.rs
+// 이 모듈은 P-256 위의 SPAKE2 (RFC 9382) 비밀번호 인증 키 교환을 담당합니다.
+// 서버는 비밀번호 대신 Argon2id로 유도한 스칼라 w만 저장하므로, 로그인 중에 비밀번호가 서버로 전달되지 않습니다.
+// (w는 이 서버에 대해서는 비밀번호와 동등한 값이므로 계정 파일은 여전히 비밀로 보관해야 합니다.)
+
+use argon2::Argon2;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use p256::{
+    elliptic_curve::{group::Group, ops::Reduce, sec1::ToEncodedPoint, Field, PrimeField},
+    FieldBytes, ProjectivePoint, PublicKey, Scalar, U256,
+};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::ecdhkey;
+use crate::secret::AesKey;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// RFC 9382에 정의된 P-256용 고정 점 M, N (SEC1 압축 형식)
+const M_BYTES: &str = "02886e2f97ace46e55ba9dd7242579f2993b64e16ef3dcab95afd497333d8fa12f";
+const N_BYTES: &str = "03d8bbd6c639c62937b04d997f38c3770719c629d7014d49a24b4f98baa1292b49";
+
+pub const SALT_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    // 프로토콜의 A (먼저 공유값을 보내는 쪽)
+    Client,
+    // 프로토콜의 B
+    Server,
+}
+
+// 비밀번호와 솔트에서 Argon2id로 w를 유도 (w = Argon2id(pw, salt) mod n)
+pub fn password_to_w(password: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, String> {
+    let mut okm = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, okm.as_mut())
+        .map_err(|e| format!("Argon2 키 유도 실패: {}", e))?;
+    Ok(reduce_to_w(&okm))
+}
+
+// 임의의 32바이트를 n으로 나눈 나머지로 줄여서 w로 사용할 수 있는 값으로 만듦
+pub fn reduce_to_w(bytes: &[u8; 32]) -> Zeroizing<[u8; 32]> {
+    let mut w = <Scalar as Reduce<U256>>::reduce_bytes(&FieldBytes::from(*bytes));
+    let out = Zeroizing::new(w.to_bytes().into());
+    w.zeroize();
+    out
+}
+
+pub struct Spake2 {
+    role: Role,
+    secret: Scalar,
+    w: Scalar,
+    my_share: Vec<u8>,
+    id_client: Vec<u8>,
+    id_server: Vec<u8>,
+    // 확인 키 유도에 섞는 추가 데이터 (바깥쪽 ECDH 공개키를 넣어 핸드셰이크와 묶음)
+    aad: Vec<u8>,
+}
+
+// 비밀 스칼라는 drop될 때 0으로 지움
+impl Drop for Spake2 {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+        self.w.zeroize();
+    }
+}
+
+// 교환이 끝난 뒤 얻는 키와 확인값
+pub struct Spake2Keys {
+    shared_key: Zeroizing<Vec<u8>>,
+    my_confirmation: Vec<u8>,
+    peer_confirm_key: Zeroizing<Vec<u8>>,
+    // TT에는 K와 w가 들어 있으므로 함께 지움
+    transcript: Zeroizing<Vec<u8>>,
+}
+
+impl Spake2 {
+    pub fn start(role: Role, w: &[u8; 32], id_client: &[u8], id_server: &[u8], aad: &[u8]) -> Result<Self, String> {
+        let w = Option::<Scalar>::from(Scalar::from_repr(FieldBytes::from(*w)))
+            .ok_or_else(|| "w 값이 올바르지 않습니다.".to_string())?;
+        let secret = Scalar::random(&mut OsRng);
+
+        // A: X = x*G + w*M,  B: Y = y*G + w*N
+        let blind = match role {
+            Role::Client => fixed_point(M_BYTES),
+            Role::Server => fixed_point(N_BYTES),
+        };
+        let share = ProjectivePoint::GENERATOR * secret + blind * w;
+
+        Ok(Self {
+            role,
+            secret,
+            w,
+            my_share: encode_point(&share),
+            id_client: id_client.to_vec(),
+            id_server: id_server.to_vec(),
+            aad: aad.to_vec(),
+        })
+    }
+
+    // 상대방에게 보낼 내 공유값 (SEC1 비압축 형식)
+    pub fn share(&self) -> &[u8] {
+        &self.my_share
+    }
+
+    pub fn finish(self, peer_share: &[u8]) -> Result<Spake2Keys, String> {
+        let peer = PublicKey::from_sec1_bytes(peer_share)
+            .map_err(|_| "상대방 SPAKE2 공유값 형식이 잘못되었습니다.".to_string())?
+            .to_projective();
+
+        // 상대방이 섞은 w*M 또는 w*N을 제거한 뒤 내 비밀 스칼라를 곱함
+        let peer_blind = match self.role {
+            Role::Client => fixed_point(N_BYTES),
+            Role::Server => fixed_point(M_BYTES),
+        };
+        let mut k = (peer - peer_blind * self.w) * self.secret;
+        if bool::from(k.is_identity()) {
+            return Err("SPAKE2 공유 비밀이 올바르지 않습니다.".to_string());
+        }
+        let k_bytes = Zeroizing::new(encode_point(&k));
+        k.zeroize();
+
+        let (share_a, share_b) = match self.role {
+            Role::Client => (self.my_share.as_slice(), peer_share),
+            Role::Server => (peer_share, self.my_share.as_slice()),
+        };
+
+        // TT = len(A)||A || len(B)||B || len(X)||X || len(Y)||Y || len(K)||K || len(w)||w
+        let mut transcript = Zeroizing::new(Vec::new());
+        let w_bytes: Zeroizing<[u8; 32]> = Zeroizing::new(self.w.to_bytes().into());
+        for part in [
+            self.id_client.as_slice(),
+            self.id_server.as_slice(),
+            share_a,
+            share_b,
+            k_bytes.as_slice(),
+            w_bytes.as_slice(),
+        ] {
+            transcript.extend_from_slice(&(part.len() as u64).to_le_bytes());
+            transcript.extend_from_slice(part);
+        }
+
+        // Hash(TT) = Ke || Ka,  KDF(Ka, "ConfirmationKeys" || AAD) = KcA || KcB
+        let digest = Zeroizing::new(Sha256::digest(transcript.as_slice()));
+        let (ke, ka) = digest.split_at(16);
+        let mut info = b"ConfirmationKeys".to_vec();
+        info.extend_from_slice(&self.aad);
+        let mut kc = Zeroizing::new([0u8; 32]);
+        Hkdf::<Sha256>::new(None, ka)
+            .expand(&info, kc.as_mut())
+            .map_err(|_| "확인 키 유도 실패".to_string())?;
+        let (kc_a, kc_b) = kc.split_at(16);
+
+        let (my_key, peer_key) = match self.role {
+            Role::Client => (kc_a, kc_b),
+            Role::Server => (kc_b, kc_a),
+        };
+        let my_confirmation = mac(my_key, &transcript);
+
+        Ok(Spake2Keys {
+            shared_key: Zeroizing::new(ke.to_vec()),
+            my_confirmation,
+            peer_confirm_key: Zeroizing::new(peer_key.to_vec()),
+            transcript,
+        })
+    }
+}
+
+impl Spake2Keys {
+    // 상대방에게 보낼 내 확인값 (cA 또는 cB)
+    pub fn confirmation(&self) -> &[u8] {
+        &self.my_confirmation
+    }
+
+    // 상대방 확인값 검증. 실패하면 비밀번호가 틀렸거나 중간자가 끼어든 것
+    pub fn verify_peer(&self, confirmation: &[u8]) -> Result<(), String> {
+        let mut mac = HmacSha256::new_from_slice(&self.peer_confirm_key).expect("HMAC 키 길이");
+        mac.update(self.transcript.as_slice());
+        mac.verify_slice(confirmation)
+            .map_err(|_| "SPAKE2 확인값이 일치하지 않습니다.".to_string())
+    }
+
+    // 공유 비밀(Ke)을 ECDH 세션 키에 섞은 새 키. 확인값 검증(verify_peer)이 끝난 뒤에만 사용할 것
+    pub fn mix_into(&self, session_key: &AesKey) -> Result<AesKey, String> {
+        ecdhkey::mix_session_key(session_key, &self.shared_key)
+    }
+}
+
+fn mac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC 키 길이");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn fixed_point(hex_bytes: &str) -> ProjectivePoint {
+    let bytes: Vec<u8> = (0..hex_bytes.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex_bytes[i..i + 2], 16).unwrap())
+        .collect();
+    PublicKey::from_sec1_bytes(&bytes).expect("RFC 9382 고정 점").to_projective()
+}
+
+fn encode_point(point: &ProjectivePoint) -> Vec<u8> {
+    point.to_affine().to_encoded_point(false).as_bytes().to_vec()
+}
@@ -0,0 +1,521 @@
+// src/pq/mlkem.rs
+// 이 모듈은 양자 내성 키 캡슐화 방식인 ML-KEM-768 (FIPS 203, Kyber)을 담당합니다.
+// 오프라인 빌드 환경에서 쓸 수 있는 ML-KEM 크레이트가 없어서 sha3 크레이트만으로 표준을 그대로 구현했습니다.
+//   - 받는 쪽: generate()로 (캡슐 해제 키, 캡슐화 키 ek) 생성 후 ek를 상대에게 전송
+//   - 보내는 쪽: encapsulate(ek)로 (공유 비밀 32바이트, 암호문) 생성 후 암호문 전송
+//   - 받는 쪽: decapsulate(암호문)으로 같은 공유 비밀 복원
+// 주의: 교육용 구현이라 상수 시간(constant-time) 최적화는 비교 단계 외에는 하지 않았습니다.
+
+use rand::{rngs::OsRng, RngCore};
+use sha3::{
+    digest::{ExtendableOutput, Update, XofReader},
+    Digest, Sha3_256, Sha3_512, Shake128, Shake256,
+};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+// ML-KEM-768 매개변수
+const N: usize = 256;
+const Q: u32 = 3329;
+const K: usize = 3;
+const ETA1: usize = 2;
+const ETA2: usize = 2;
+const DU: usize = 10;
+const DV: usize = 4;
+
+const POLY_BYTES: usize = 384;
+pub const EK_LEN: usize = POLY_BYTES * K + 32; // 1184
+pub const DK_LEN: usize = POLY_BYTES * K * 2 + 96; // 2400
+pub const CT_LEN: usize = 32 * (DU * K + DV); // 1088
+pub const SHARED_LEN: usize = 32;
+
+type Poly = [u16; N];
+
+// 캡슐 해제 키 (drop될 때 0으로 지워짐)
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct DecapsKey(Vec<u8>);
+
+impl std::fmt::Debug for DecapsKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DecapsKey([REDACTED])")
+    }
+}
+
+// 새 키 쌍 생성: (캡슐 해제 키, 캡슐화 키 ek)
+pub fn generate() -> (DecapsKey, Vec<u8>) {
+    let mut d = Zeroizing::new([0u8; 32]);
+    let mut z = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(d.as_mut());
+    OsRng.fill_bytes(z.as_mut());
+    keygen_internal(&d, &z)
+}
+
+// 시드(d, z)로부터 결정적으로 키 쌍 생성 (ML-KEM.KeyGen_internal, 테스트 벡터 비교용)
+pub fn keygen_internal(d: &[u8; 32], z: &[u8; 32]) -> (DecapsKey, Vec<u8>) {
+    let (ek, dk_pke) = pke_keygen(d);
+    let mut dk = Vec::with_capacity(DK_LEN);
+    dk.extend_from_slice(&dk_pke);
+    dk.extend_from_slice(&ek);
+    dk.extend_from_slice(&h(&ek));
+    dk.extend_from_slice(z);
+    (DecapsKey(dk), ek)
+}
+
+// 상대의 캡슐화 키로 공유 비밀과 암호문 생성
+pub fn encapsulate(ek: &[u8]) -> Result<(Zeroizing<[u8; SHARED_LEN]>, Vec<u8>), String> {
+    let mut m = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(m.as_mut());
+    encapsulate_internal(ek, &m)
+}
+
+// 메시지 m을 지정한 결정적 캡슐화 (ML-KEM.Encaps_internal, 테스트 벡터 비교용)
+pub fn encapsulate_internal(ek: &[u8], m: &[u8; 32]) -> Result<(Zeroizing<[u8; SHARED_LEN]>, Vec<u8>), String> {
+    check_ek(ek)?;
+    let (shared, r) = g(&[m.as_slice(), &h(ek)]);
+    let ct = pke_encrypt(ek, m, &r);
+    Ok((shared, ct))
+}
+
+impl DecapsKey {
+    // 암호문에서 공유 비밀 복원. 변조된 암호문이면 에러 대신 엉뚱한(암묵적 거부) 비밀이 나옴
+    pub fn decapsulate(&self, ct: &[u8]) -> Result<Zeroizing<[u8; SHARED_LEN]>, String> {
+        if ct.len() != CT_LEN {
+            return Err("ML-KEM 암호문 길이가 잘못되었습니다.".to_string());
+        }
+        let dk_pke = &self.0[..POLY_BYTES * K];
+        let ek = &self.0[POLY_BYTES * K..POLY_BYTES * K * 2 + 32];
+        let hash = &self.0[POLY_BYTES * K * 2 + 32..POLY_BYTES * K * 2 + 64];
+        let z = &self.0[POLY_BYTES * K * 2 + 64..];
+
+        let m = pke_decrypt(dk_pke, ct);
+        let (shared, r) = g(&[m.as_slice(), hash]);
+        let rejected = j(z, ct);
+        let ct2 = pke_encrypt(ek, &m, &r);
+
+        // 다시 암호화한 결과가 다르면 암묵적 거부 값을 사용 (비교와 선택은 상수 시간)
+        let diff = ct.iter().zip(&ct2).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        let mask = ((diff as u16).wrapping_sub(1) >> 8) as u8; // 같으면 0xff, 다르면 0x00
+        let mut out = Zeroizing::new([0u8; SHARED_LEN]);
+        for i in 0..SHARED_LEN {
+            out[i] = (shared[i] & mask) | (rejected[i] & !mask);
+        }
+        Ok(out)
+    }
+}
+
+// 캡슐화 키 검사: 길이, 그리고 모든 계수가 q 미만인지 (FIPS 203 7.2 입력 검사)
+fn check_ek(ek: &[u8]) -> Result<(), String> {
+    if ek.len() != EK_LEN {
+        return Err("ML-KEM 캡슐화 키 길이가 잘못되었습니다.".to_string());
+    }
+    for chunk in ek[..POLY_BYTES * K].chunks(POLY_BYTES) {
+        if byte_encode(&byte_decode(chunk, 12), 12) != chunk {
+            return Err("ML-KEM 캡슐화 키 값이 범위를 벗어났습니다.".to_string());
+        }
+    }
+    Ok(())
+}
+
+// ---------------- K-PKE (내부 공개키 암호) ----------------
+
+fn pke_keygen(d: &[u8; 32]) -> (Vec<u8>, Zeroizing<Vec<u8>>) {
+    let (rho, sigma) = g(&[d.as_slice(), &[K as u8]]);
+    let a = sample_matrix(&rho);
+
+    let mut n = 0u8;
+    let mut s = [[0u16; N]; K];
+    let mut e = [[0u16; N]; K];
+    for poly in s.iter_mut() {
+        *poly = sample_cbd(&prf(&sigma, n, ETA1), ETA1);
+        ntt(poly);
+        n += 1;
+    }
+    for poly in e.iter_mut() {
+        *poly = sample_cbd(&prf(&sigma, n, ETA1), ETA1);
+        ntt(poly);
+        n += 1;
+    }
+
+    let mut ek = Vec::with_capacity(EK_LEN);
+    for i in 0..K {
+        let mut t = e[i];
+        for (a_ij, s_j) in a[i].iter().zip(&s) {
+            t = add(&t, &multiply_ntts(a_ij, s_j));
+        }
+        ek.extend_from_slice(&byte_encode(&t, 12));
+    }
+    ek.extend_from_slice(rho.as_slice());
+
+    let mut dk = Zeroizing::new(Vec::with_capacity(POLY_BYTES * K));
+    for poly in &s {
+        dk.extend_from_slice(&byte_encode(poly, 12));
+    }
+    s.zeroize();
+    e.zeroize();
+    (ek, dk)
+}
+
+fn pke_encrypt(ek: &[u8], m: &[u8; 32], r: &[u8; 32]) -> Vec<u8> {
+    let t: Vec<Poly> = ek[..POLY_BYTES * K].chunks(POLY_BYTES).map(|c| byte_decode(c, 12)).collect();
+    let rho: [u8; 32] = ek[POLY_BYTES * K..].try_into().unwrap();
+    let a = sample_matrix(&rho);
+
+    let mut n = 0u8;
+    let mut y = [[0u16; N]; K];
+    for poly in y.iter_mut() {
+        *poly = sample_cbd(&prf(r, n, ETA1), ETA1);
+        ntt(poly);
+        n += 1;
+    }
+    let mut e1 = [[0u16; N]; K];
+    for poly in e1.iter_mut() {
+        *poly = sample_cbd(&prf(r, n, ETA2), ETA2);
+        n += 1;
+    }
+    let e2 = sample_cbd(&prf(r, n, ETA2), ETA2);
+
+    let mut ct = Vec::with_capacity(CT_LEN);
+    // u = NTT^-1(A^T * y) + e1
+    for i in 0..K {
+        let mut u = [0u16; N];
+        for (row, y_j) in a.iter().zip(&y) {
+            u = add(&u, &multiply_ntts(&row[i], y_j));
+        }
+        inv_ntt(&mut u);
+        let u = add(&u, &e1[i]);
+        ct.extend_from_slice(&byte_encode(&compress(&u, DU), DU));
+    }
+    // v = NTT^-1(t^T * y) + e2 + Decompress_1(m)
+    let mut v = [0u16; N];
+    for (t_j, y_j) in t.iter().zip(&y) {
+        v = add(&v, &multiply_ntts(t_j, y_j));
+    }
+    inv_ntt(&mut v);
+    let mu = decompress(&byte_decode(m, 1), 1);
+    let v = add(&add(&v, &e2), &mu);
+    ct.extend_from_slice(&byte_encode(&compress(&v, DV), DV));
+
+    y.zeroize();
+    ct
+}
+
+fn pke_decrypt(dk_pke: &[u8], ct: &[u8]) -> Zeroizing<[u8; 32]> {
+    let (c1, c2) = ct.split_at(32 * DU * K);
+    let v = decompress(&byte_decode(c2, DV), DV);
+
+    let mut w = [0u16; N];
+    for (s_chunk, u_chunk) in dk_pke.chunks(POLY_BYTES).zip(c1.chunks(32 * DU)) {
+        let s = byte_decode(s_chunk, 12);
+        let mut u = decompress(&byte_decode(u_chunk, DU), DU);
+        ntt(&mut u);
+        w = add(&w, &multiply_ntts(&s, &u));
+    }
+    inv_ntt(&mut w);
+    let w = sub(&v, &w);
+
+    let mut m = Zeroizing::new([0u8; 32]);
+    m.copy_from_slice(&byte_encode(&compress(&w, 1), 1));
+    m
+}
+
+// A[i][j] = SampleNTT(rho || j || i)
+fn sample_matrix(rho: &[u8; 32]) -> [[Poly; K]; K] {
+    let mut a = [[[0u16; N]; K]; K];
+    for (i, row) in a.iter_mut().enumerate() {
+        for (j, poly) in row.iter_mut().enumerate() {
+            *poly = sample_ntt(rho, j as u8, i as u8);
+        }
+    }
+    a
+}
+
+// ---------------- 해시 함수들 ----------------
+
+fn h(data: &[u8]) -> [u8; 32] {
+    Sha3_256::digest(data).into()
+}
+
+fn g(parts: &[&[u8]]) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+    let mut hasher = Sha3_512::new();
+    for part in parts {
+        Digest::update(&mut hasher, part);
+    }
+    let out = Zeroizing::new(<[u8; 64]>::from(hasher.finalize()));
+    let mut a = Zeroizing::new([0u8; 32]);
+    let mut b = Zeroizing::new([0u8; 32]);
+    a.copy_from_slice(&out[..32]);
+    b.copy_from_slice(&out[32..]);
+    (a, b)
+}
+
+fn j(z: &[u8], ct: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut hasher = Shake256::default();
+    hasher.update(z);
+    hasher.update(ct);
+    let mut out = Zeroizing::new([0u8; 32]);
+    hasher.finalize_xof().read(out.as_mut());
+    out
+}
+
+fn prf(seed: &[u8; 32], n: u8, eta: usize) -> Zeroizing<Vec<u8>> {
+    let mut hasher = Shake256::default();
+    hasher.update(seed);
+    hasher.update(&[n]);
+    let mut out = Zeroizing::new(vec![0u8; 64 * eta]);
+    hasher.finalize_xof().read(&mut out);
+    out
+}
+
+// ---------------- 샘플링 ----------------
+
+// SHAKE128 출력에서 q 미만인 12비트 값만 골라 NTT 영역의 다항식을 만듦
+fn sample_ntt(rho: &[u8; 32], j: u8, i: u8) -> Poly {
+    let mut xof = Shake128::default();
+    xof.update(rho);
+    xof.update(&[j, i]);
+    let mut reader = xof.finalize_xof();
+
+    let mut poly = [0u16; N];
+    let mut count = 0;
+    let mut buf = [0u8; 3];
+    while count < N {
+        reader.read(&mut buf);
+        let d1 = buf[0] as u16 | ((buf[1] as u16 & 0x0f) << 8);
+        let d2 = (buf[1] as u16 >> 4) | ((buf[2] as u16) << 4);
+        if (d1 as u32) < Q {
+            poly[count] = d1;
+            count += 1;
+        }
+        if (d2 as u32) < Q && count < N {
+            poly[count] = d2;
+            count += 1;
+        }
+    }
+    poly
+}
+
+// 중심 이항 분포(CBD)에서 작은 계수를 뽑음
+fn sample_cbd(bytes: &[u8], eta: usize) -> Poly {
+    let bit = |k: usize| ((bytes[k / 8] >> (k % 8)) & 1) as u32;
+    let mut poly = [0u16; N];
+    for (i, coeff) in poly.iter_mut().enumerate() {
+        let x: u32 = (0..eta).map(|k| bit(2 * i * eta + k)).sum();
+        let y: u32 = (0..eta).map(|k| bit(2 * i * eta + eta + k)).sum();
+        *coeff = ((x + Q - y) % Q) as u16;
+    }
+    poly
+}
+
+// ---------------- 다항식 연산 (NTT) ----------------
+
+// zeta = 17 (q에 대한 원시 256제곱근), ZETAS[i] = 17^BitRev7(i) mod q
+const ZETAS: [u16; 128] = {
+    let mut table = [0u16; 128];
+    let mut i = 0;
+    while i < 128 {
+        table[i] = pow_mod(17, (i as u8).reverse_bits() as u32 >> 1);
+        i += 1;
+    }
+    table
+};
+
+// 기본 곱셈에 쓰는 gamma_i = 17^(2*BitRev7(i)+1) mod q
+const GAMMAS: [u16; 128] = {
+    let mut table = [0u16; 128];
+    let mut i = 0;
+    while i < 128 {
+        table[i] = pow_mod(17, 2 * ((i as u8).reverse_bits() as u32 >> 1) + 1);
+        i += 1;
+    }
+    table
+};
+
+const fn pow_mod(base: u32, mut exp: u32) -> u16 {
+    let mut result = 1u32;
+    let mut b = base % Q;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * b % Q;
+        }
+        b = b * b % Q;
+        exp >>= 1;
+    }
+    result as u16
+}
+
+fn mul(a: u16, b: u16) -> u16 {
+    (a as u32 * b as u32 % Q) as u16
+}
+
+fn add(a: &Poly, b: &Poly) -> Poly {
+    std::array::from_fn(|i| ((a[i] as u32 + b[i] as u32) % Q) as u16)
+}
+
+fn sub(a: &Poly, b: &Poly) -> Poly {
+    std::array::from_fn(|i| ((a[i] as u32 + Q - b[i] as u32) % Q) as u16)
+}
+
+fn ntt(f: &mut Poly) {
+    let mut k = 1;
+    let mut len = 128;
+    while len >= 2 {
+        for start in (0..N).step_by(2 * len) {
+            let zeta = ZETAS[k];
+            k += 1;
+            for j in start..start + len {
+                let t = mul(zeta, f[j + len]);
+                f[j + len] = ((f[j] as u32 + Q - t as u32) % Q) as u16;
+                f[j] = ((f[j] as u32 + t as u32) % Q) as u16;
+            }
+        }
+        len /= 2;
+    }
+}
+
+fn inv_ntt(f: &mut Poly) {
+    let mut k = 127;
+    let mut len = 2;
+    while len <= 128 {
+        for start in (0..N).step_by(2 * len) {
+            let zeta = ZETAS[k];
+            k -= 1;
+            for j in start..start + len {
+                let t = f[j];
+                f[j] = ((t as u32 + f[j + len] as u32) % Q) as u16;
+                f[j + len] = mul(zeta, ((f[j + len] as u32 + Q - t as u32) % Q) as u16);
+            }
+        }
+        len *= 2;
+    }
+    // 128^-1 mod q = 3303
+    for coeff in f.iter_mut() {
+        *coeff = mul(*coeff, 3303);
+    }
+}
+
+// NTT 영역에서의 곱셈: 128개의 1차 다항식 쌍을 X^2 - gamma_i 로 나눈 나머지끼리 곱함
+fn multiply_ntts(f: &Poly, g: &Poly) -> Poly {
+    let mut out = [0u16; N];
+    for i in 0..N / 2 {
+        let (a0, a1, b0, b1) = (f[2 * i], f[2 * i + 1], g[2 * i], g[2 * i + 1]);
+        out[2 * i] = ((mul(a0, b0) as u32 + mul(mul(a1, b1), GAMMAS[i]) as u32) % Q) as u16;
+        out[2 * i + 1] = ((mul(a0, b1) as u32 + mul(a1, b0) as u32) % Q) as u16;
+    }
+    out
+}
+
+// ---------------- 압축과 인코딩 ----------------
+
+// Compress_d(x) = round(2^d / q * x) mod 2^d
+fn compress(f: &Poly, d: usize) -> Poly {
+    std::array::from_fn(|i| (((((f[i] as u32) << (d + 1)) + Q) / (2 * Q)) & ((1 << d) - 1)) as u16)
+}
+
+// Decompress_d(y) = round(q / 2^d * y)
+fn decompress(f: &Poly, d: usize) -> Poly {
+    std::array::from_fn(|i| ((f[i] as u32 * Q + (1 << (d - 1))) >> d) as u16)
+}
+
+// 계수마다 d비트씩 리틀 엔디언 비트 순서로 이어 붙임
+fn byte_encode(f: &Poly, d: usize) -> Vec<u8> {
+    let mut out = vec![0u8; 32 * d];
+    let mut bit = 0;
+    for &coeff in f {
+        for k in 0..d {
+            out[bit / 8] |= (((coeff >> k) & 1) as u8) << (bit % 8);
+            bit += 1;
+        }
+    }
+    out
+}
+
+// byte_encode의 역. d = 12이면 mod q를 취함
+fn byte_decode(bytes: &[u8], d: usize) -> Poly {
+    let mut poly = [0u16; N];
+    let mut bit = 0;
+    for coeff in poly.iter_mut() {
+        let mut value = 0u16;
+        for k in 0..d {
+            value |= (((bytes[bit / 8] >> (bit % 8)) & 1) as u16) << k;
+            bit += 1;
+        }
+        *coeff = if d == 12 { (value as u32 % Q) as u16 } else { value };
+    }
+    poly
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // vectors/mlkem768-kat.json (독립 구현인 OpenSSL로 만든 기대값)
+    const KAT: &str = include_str!("../../vectors/mlkem768-kat.json");
+
+    fn hex(value: &serde_json::Value) -> Vec<u8> {
+        let text = value.as_str().expect("16진수 문자열");
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).expect("16진수")).collect()
+    }
+
+    fn seed(value: &serde_json::Value) -> [u8; 32] {
+        hex(value).try_into().expect("32바이트 시드")
+    }
+
+    fn vectors() -> Vec<serde_json::Value> {
+        let file: serde_json::Value = serde_json::from_str(KAT).expect("KAT 파일 형식");
+        file["vectors"].as_array().expect("vectors 배열").clone()
+    }
+
+    #[test]
+    fn keygen_matches_kat() {
+        for vector in vectors() {
+            let (dk, ek) = keygen_internal(&seed(&vector["d"]), &seed(&vector["z"]));
+            assert_eq!(ek.len(), EK_LEN);
+            assert_eq!(dk.0.len(), DK_LEN);
+            assert_eq!(Sha3_256::digest(&ek).to_vec(), hex(&vector["ek_sha3_256"]));
+        }
+    }
+
+    #[test]
+    fn encapsulate_matches_kat() {
+        for vector in vectors() {
+            let (dk, ek) = keygen_internal(&seed(&vector["d"]), &seed(&vector["z"]));
+            let (k, ct) = encapsulate_internal(&ek, &seed(&vector["m"])).unwrap();
+            assert_eq!(k.to_vec(), hex(&vector["k"]));
+            assert_eq!(ct.len(), CT_LEN);
+            assert_eq!(Sha3_256::digest(&ct).to_vec(), hex(&vector["ct_sha3_256"]));
+            assert_eq!(dk.decapsulate(&ct).unwrap().to_vec(), hex(&vector["k"]));
+        }
+    }
+
+    #[test]
+    fn decapsulate_matches_kat() {
+        for vector in vectors() {
+            let (dk, _) = keygen_internal(&seed(&vector["d"]), &seed(&vector["z"]));
+            let mut ct = hex(&vector["ct"]);
+            assert_eq!(dk.decapsulate(&ct).unwrap().to_vec(), hex(&vector["ct_k"]));
+            // 변조된 암호문은 에러 없이 암묵적 거부 값 J(z, ct)를 돌려줌
+            *ct.last_mut().unwrap() ^= 1;
+            assert_eq!(dk.decapsulate(&ct).unwrap().to_vec(), hex(&vector["reject_k"]));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_inputs() {
+        let (dk, mut ek) = generate();
+        assert!(dk.decapsulate(&[0; CT_LEN - 1]).is_err());
+        assert!(encapsulate(&ek[..EK_LEN - 1]).is_err());
+        // 계수가 q 이상인 캡슐화 키 (첫 12비트 = 0xfff)
+        ek[0] = 0xff;
+        ek[1] |= 0x0f;
+        assert!(encapsulate(&ek).is_err());
+    }
+
+    #[test]
+    fn debug_hides_decaps_key() {
+        let (dk, _) = generate();
+        let debug = format!("{:?}", dk);
+        assert_eq!(debug, "DecapsKey([REDACTED])");
+        // 앞부분은 비밀 다항식, 끝 32바이트는 암시적 거부용 비밀값 z
+        crate::secret::assert_redacted(&debug, &dk.0[..POLY_BYTES * K]);
+        crate::secret::assert_redacted(&debug, &dk.0[DK_LEN - 32..]);
+    }
+}
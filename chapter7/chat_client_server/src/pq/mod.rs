@@ -0,0 +1 @@
+pub mod mlkem;
\ No newline at end of file
@@ -0,0 +1,132 @@
+// src/proto/cipherstate.rs
+// 이 모듈은 여러 사람이 함께 쓰는 Room Key의 nonce 관리와 키 갱신을 담당합니다.
+// 96비트 랜덤 nonce는 같은 키로 보내는 메시지가 아주 많아지면 충돌 확률을 무시할 수 없고, GCM은 nonce가 한 번만 겹쳐도 안전성이 깨집니다.
+// 그래서 nonce를 정해진 규칙으로 만듭니다: nonce = 발신자 번호(4바이트) || 세대(4바이트) || 카운터(4바이트)
+//   - 발신자 번호: 서버가 로그인할 때 연결마다 겹치지 않게 나눠 줌 (0은 서버 자신)
+//   - 암호화 키: Room Key에서 HKDF로 (발신자, 세대)마다 따로 유도 → 발신자끼리 같은 키를 쓰지 않음
+//   - 카운터가 정해진 한도에 닿으면 세대를 올려 새 키로 바꾸고(key update) 카운터는 0부터 다시 시작
+// 카운터와 세대는 밖에서 바꿀 수 없고 seal()에서만 늘어나므로, 같은 키로 같은 nonce를 두 번 쓰는 일은 구조적으로 생기지 않습니다.
+// 받는 쪽은 nonce에 적힌 (발신자, 세대)로 같은 키를 유도하므로 따로 키 갱신을 맞출 필요가 없습니다.
+// 서버가 키 저장소(--keystore)로 같은 Room Key를 다시 쓸 때는, 지난 실행에서 쓴 세대 다음부터 시작(resume)해서 nonce가 겹치지 않게 합니다.
+
+use std::collections::HashMap;
+
+use aes_gcm::Aes256Gcm;
+
+use crate::packet::{self, NONCE_LEN};
+use crate::secret::AesKey;
+
+// 서버가 다시 암호화해서 보내는 메시지(오프라인 멘션, 연합)의 발신자 번호
+pub const SERVER_SENDER: u32 = 0;
+// 로그인 응답에서 발신자 번호와 키 갱신 한도를 알리는 머리말
+pub const SENDER_TAG: &str = "sender=";
+pub const REKEY_TAG: &str = "rekey=";
+// 받는 쪽이 기억하는 (발신자, 세대) 키의 최대 개수
+const RECEIVE_CACHE: usize = 4096;
+
+pub struct CipherState {
+    // Room Key에서 유도한, 발신자별 키를 만드는 뿌리 키
+    root: AesKey,
+    sender: u32,
+    epoch: u32,
+    counter: u32,
+    // 한 키로 보낼 수 있는 최대 메시지 수
+    rekey_after: u32,
+    sending: Aes256Gcm,
+    receiving: HashMap<(u32, u32), Aes256Gcm>,
+}
+
+impl CipherState {
+    pub fn new(room_key: &AesKey, sender: u32, rekey_after: u32) -> Result<Self, String> {
+        Self::resume(room_key, sender, 0, rekey_after)
+    }
+
+    // 정해진 세대부터 보내기 시작 (그 세대의 카운터는 0부터)
+    pub fn resume(room_key: &AesKey, sender: u32, epoch: u32, rekey_after: u32) -> Result<Self, String> {
+        if rekey_after == 0 {
+            return Err("키 갱신 한도는 1 이상이어야 합니다.".to_string());
+        }
+        let root = room_key.derive(b"", b"chat-room-nonce-v1")?;
+        let sending = sender_key(&root, sender, epoch)?;
+        Ok(Self { root, sender, epoch, counter: 0, rekey_after, sending, receiving: HashMap::new() })
+    }
+
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    // 다음 seal()이 쓸 세대 (한도에 닿았으면 다음 세대)
+    pub fn next_epoch(&self) -> u32 {
+        if self.counter == self.rekey_after {
+            self.epoch.saturating_add(1)
+        } else {
+            self.epoch
+        }
+    }
+
+    // 다음 nonce로 암호화. 한도에 닿았으면 먼저 다음 세대 키로 바꿈
+    pub fn seal(&mut self, plaintext: &[u8]) -> Result<String, String> {
+        if self.counter == self.rekey_after {
+            self.epoch = self.epoch.checked_add(1).ok_or("Room Key의 키 갱신 한도를 모두 썼습니다.")?;
+            self.sending = sender_key(&self.root, self.sender, self.epoch)?;
+            self.counter = 0;
+        }
+        let nonce = make_nonce(self.sender, self.epoch, self.counter);
+        self.counter += 1;
+        Ok(packet::seal_with_nonce(&self.sending, nonce, plaintext))
+    }
+
+    // 복호화하고 보낸 사람의 발신자 번호를 함께 돌려줌
+    pub fn open(&mut self, line: &str) -> Result<(u32, Vec<u8>), String> {
+        let (nonce, ciphertext) = packet::decode(line)?;
+        let sender = u32::from_be_bytes(nonce[0..4].try_into().unwrap());
+        let epoch = u32::from_be_bytes(nonce[4..8].try_into().unwrap());
+
+        if !self.receiving.contains_key(&(sender, epoch)) {
+            if self.receiving.len() >= RECEIVE_CACHE {
+                self.receiving.clear();
+            }
+            self.receiving.insert((sender, epoch), sender_key(&self.root, sender, epoch)?);
+        }
+        let plaintext = packet::open_with_nonce(&self.receiving[&(sender, epoch)], nonce, &ciphertext)?;
+        Ok((sender, plaintext))
+    }
+}
+
+fn make_nonce(sender: u32, epoch: u32, counter: u32) -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[0..4].copy_from_slice(&sender.to_be_bytes());
+    nonce[4..8].copy_from_slice(&epoch.to_be_bytes());
+    nonce[8..12].copy_from_slice(&counter.to_be_bytes());
+    nonce
+}
+
+fn sender_key(root: &AesKey, sender: u32, epoch: u32) -> Result<Aes256Gcm, String> {
+    let mut label = sender.to_be_bytes().to_vec();
+    label.extend_from_slice(&epoch.to_be_bytes());
+    Ok(root.derive(&label, b"chat-room-sender-v1")?.cipher())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_api_hands_out_no_key() {
+        type Opened = Result<(u32, Vec<u8>), String>;
+        // Room Key는 빌려 쓰기만 하고, 밖으로 나가는 것은 암호문과 평문뿐: 시그니처가 바뀌면 컴파일되지 않음
+        let _: fn(&AesKey, u32, u32) -> Result<CipherState, String> = CipherState::new;
+        let _: fn(&AesKey, u32, u32, u32) -> Result<CipherState, String> = CipherState::resume;
+        let _: fn(&mut CipherState, &[u8]) -> Result<String, String> = CipherState::seal;
+        let _: fn(&mut CipherState, &str) -> Opened = CipherState::open;
+
+        // 같은 Room Key로 만든 상태끼리만 풀 수 있음
+        let room_key = AesKey::random();
+        let mut sending = CipherState::new(&room_key, 7, 2).unwrap();
+        let mut receiving = CipherState::new(&room_key, SERVER_SENDER, 2).unwrap();
+        let line = sending.seal(b"hello").unwrap();
+        assert_eq!(receiving.open(&line).unwrap(), (7, b"hello".to_vec()));
+        let mut other = CipherState::new(&AesKey::random(), SERVER_SENDER, 2).unwrap();
+        assert!(other.open(&line).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+// src/proto/conn.rs
+// 이 모듈은 줄 단위 프로토콜 연결(읽기/쓰기 반쪽 + 프레임 리더)을 하나로 묶어,
+// 평문 줄과 암호화된 줄(packet 형식)을 주고받는 로직을 담당합니다.
+// Noise 전송 상태가 설정되면 모든 줄을 한 번 더 감싸서 주고받습니다. (최대 길이 제한은 감싼 줄 기준)
+// 기록기(transcript)가 설정되면 주고받는 줄을 Noise 안쪽 기준으로 기록하고, 세션 키로 암호화한 줄은 평문으로 기록합니다.
+
+use aes_gcm::Aes256Gcm;
+use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt};
+use zeroize::Zeroizing;
+
+use crate::frame::{Frame, FrameReader};
+use crate::noise::NoiseTransport;
+use crate::packet;
+use crate::transcript::{Dir, Layer, Recorder};
+
+pub struct LineConn<R, W> {
+    reader: R,
+    writer: W,
+    frames: FrameReader,
+    transport: Option<NoiseTransport>,
+    recorder: Option<Recorder>,
+}
+
+impl<R: AsyncBufRead + Unpin, W: AsyncWrite + Unpin> LineConn<R, W> {
+    pub fn new(reader: R, writer: W, max_line_bytes: usize) -> Self {
+        Self { reader, writer, frames: FrameReader::new(max_line_bytes), transport: None, recorder: None }
+    }
+
+    // 이후 주고받는 줄을 기록
+    pub fn set_recorder(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    // 호출한 쪽이 직접 남기는 기록 (예: 받은 Room Key 메시지를 복호화한 평문)
+    pub fn record(&mut self, dir: Dir, layer: Layer, from: Option<&str>, text: &str) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.write(dir, layer, from, text);
+        }
+    }
+
+    // Noise 핸드셰이크가 끝난 뒤 호출. 이후 모든 줄이 Noise 전송 상태로 암호화됨
+    pub fn set_transport(&mut self, transport: NoiseTransport) {
+        self.transport = Some(transport);
+    }
+
+    // 다음 프레임 수신 (select! 안에서 사용해도 안전)
+    pub async fn recv_frame(&mut self) -> std::io::Result<Frame> {
+        let frame = self.next_frame().await?;
+        if let Frame::Line(line) = &frame {
+            self.record(Dir::Recv, Layer::Raw, None, line);
+        }
+        Ok(frame)
+    }
+
+    async fn next_frame(&mut self) -> std::io::Result<Frame> {
+        let frame = self.frames.next(&mut self.reader).await?;
+        match (frame, &mut self.transport) {
+            (Frame::Line(line), Some(transport)) => transport
+                .decrypt_line(&line)
+                .map(Frame::Line)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            (frame, _) => Ok(frame),
+        }
+    }
+
+    // 한 줄 수신. 연결 종료나 크기 초과는 오류로 처리 (핸드셰이크 단계용)
+    pub async fn recv_line(&mut self) -> std::io::Result<String> {
+        let line = self.next_line().await?;
+        self.record(Dir::Recv, Layer::Raw, None, &line);
+        Ok(line)
+    }
+
+    async fn next_line(&mut self) -> std::io::Result<String> {
+        match self.next_frame().await? {
+            Frame::Line(line) => Ok(line),
+            Frame::TooLong(len) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("최대 길이를 넘는 줄 수신: {} 바이트", len),
+            )),
+            Frame::Eof => Err(std::io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+
+    // 한 줄 전송 (줄바꿈은 여기서 붙임)
+    pub async fn send_line(&mut self, line: &str) -> std::io::Result<()> {
+        self.record(Dir::Send, Layer::Raw, None, line);
+        self.write_line(line).await
+    }
+
+    // 암호화한 줄을 전송하되, 기록에는 평문을 남김
+    pub async fn send_recorded(&mut self, line: &str, layer: Layer, plaintext: &[u8]) -> std::io::Result<()> {
+        self.record(Dir::Send, layer, None, &String::from_utf8_lossy(plaintext));
+        self.write_line(line).await
+    }
+
+    async fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let line = match &mut self.transport {
+            Some(transport) => transport
+                .encrypt_line(line)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?,
+            None => line.to_string(),
+        };
+        self.writer.write_all(format!("{}\n", line).as_bytes()).await
+    }
+
+    // 평문을 암호화해서 한 줄로 전송
+    pub async fn send_sealed(&mut self, cipher: &Aes256Gcm, plaintext: &[u8]) -> std::io::Result<()> {
+        let line = packet::seal(cipher, plaintext);
+        self.send_recorded(&line, Layer::Sealed, plaintext).await
+    }
+
+    // 한 줄을 받아 복호화
+    pub async fn recv_sealed(&mut self, cipher: &Aes256Gcm) -> std::io::Result<Vec<u8>> {
+        let line = self.next_line().await?;
+        let plaintext = packet::open(cipher, &line).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        self.record(Dir::Recv, Layer::Sealed, None, &String::from_utf8_lossy(&plaintext));
+        Ok(plaintext)
+    }
+
+    // 암호화된 텍스트 한 줄 수신 (로그인 요청/응답용). 비밀번호가 들어 있을 수 있으므로 Zeroizing으로 돌려줌
+    pub async fn recv_sealed_text(&mut self, cipher: &Aes256Gcm) -> std::io::Result<Zeroizing<String>> {
+        let plaintext = Zeroizing::new(self.recv_sealed(cipher).await?);
+        Ok(Zeroizing::new(String::from_utf8_lossy(&plaintext).into_owned()))
+    }
+}
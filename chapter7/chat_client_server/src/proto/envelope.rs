@@ -0,0 +1,79 @@
+// src/proto/envelope.rs
+// 이 모듈은 Room Key로 암호화하기 전의 채팅 메시지 봉투(envelope)를 정의합니다.
+// 봉투는 JSON으로 직렬화됩니다. 예: {"id":"3f2a...","sender":"alice","kind":"edit","target":"9c01...","body":"고친 내용"}
+// 시각(ts)은 보낸 사람이 정하지 않고, 서버가 전달할 때 줄 끝에 붙인 값(" t=<유닉스 밀리초>")을 받는 쪽이 채워 넣습니다.
+// 귓속말(Double Ratchet)의 평문도 같은 봉투를 사용합니다.
+
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+// 서버가 붙이는 시각 표시의 머리말 (chatserver와 chatclient가 같이 사용)
+pub const TIMESTAMP_TAG: &str = " t=";
+// 서버가 방 메시지를 받아들였다는 확인: "ACK <메시지 ID> <서버 시각>"
+pub const ACK_PREFIX: &str = "ACK ";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    pub id: String,
+    pub sender: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ts: Option<i64>,
+    #[serde(flatten)]
+    pub kind: Kind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Kind {
+    Text { body: String },
+    Edit { target: String, body: String },
+    Delete { target: String },
+    Reaction { target: String, emoji: String },
+    Typing,
+    // 받은 메시지들에 대한 수신/읽음 확인 (방에 뿌리지 않고 원래 보낸 사람에게 귓속말로만 보냄)
+    Receipt { targets: Vec<String>, read: bool },
+}
+
+impl Envelope {
+    pub fn new(sender: &str, kind: Kind) -> Self {
+        Self { id: new_id(), sender: sender.to_string(), ts: None, kind }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("봉투 직렬화는 실패하지 않음")
+    }
+
+    // 봉투가 아니면(예전 클라이언트가 보낸 평문 등) None
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.first() != Some(&b'{') {
+            return None;
+        }
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+// 메시지 ID: 64비트 난수의 16진수 (수정/삭제/반응에서 가리킬 때 앞부분만 써도 됨)
+pub fn new_id() -> String {
+    format!("{:016x}", OsRng.next_u64())
+}
+
+// 서버가 전달할 때 붙이는 시각 (유닉스 밀리초)
+pub fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+// 서버가 전달한 방 메시지 "[<보낸 사람>]: <내용>" → (보낸 사람, 내용). 방 메시지가 아니면 None
+pub fn parse_room_line(line: &str) -> Option<(&str, &str)> {
+    line.strip_prefix('[')?.split_once("]: ")
+}
+
+// "<패킷> t=<밀리초>" → (패킷, 시각). 시각이 없으면 (원래 줄, None)
+pub fn split_timestamp(content: &str) -> (&str, Option<i64>) {
+    match content.rsplit_once(TIMESTAMP_TAG) {
+        Some((packet, ts)) => match ts.parse() {
+            Ok(ts) => (packet, Some(ts)),
+            Err(_) => (content, None),
+        },
+        None => (content, None),
+    }
+}
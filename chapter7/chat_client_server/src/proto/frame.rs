@@ -0,0 +1,73 @@
+// src/proto/frame.rs
+// 이 모듈은 소켓에서 한 줄(프레임)씩 읽되, 최대 길이를 넘는 줄은 메모리에 쌓지 않고 버리는 로직을 담당합니다.
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+// 한 번의 읽기 결과
+#[derive(Debug, PartialEq, Eq)]
+pub enum Frame {
+    // 정상적인 한 줄 (줄바꿈 문자 제외)
+    Line(String),
+    // 최대 길이를 넘은 줄. 실제로 수신된 전체 바이트 수를 함께 돌려줌
+    TooLong(usize),
+    // 연결 종료
+    Eof,
+}
+
+pub struct FrameReader {
+    max_len: usize,
+    buf: Vec<u8>,
+    // 최대 길이를 넘긴 줄의 나머지를 버리는 중인지 여부
+    discarding: bool,
+    discarded: usize,
+}
+
+impl FrameReader {
+    pub fn new(max_len: usize) -> Self {
+        Self { max_len, buf: Vec::new(), discarding: false, discarded: 0 }
+    }
+
+    // 다음 프레임을 읽어옵니다.
+    // await 지점은 fill_buf 하나뿐이고 그 이후는 동기 처리이므로,
+    // tokio::select! 안에서 취소되어도 이미 읽은 데이터를 잃지 않습니다.
+    pub async fn next<R: AsyncBufRead + Unpin>(&mut self, reader: &mut R) -> std::io::Result<Frame> {
+        loop {
+            let available = reader.fill_buf().await?;
+            if available.is_empty() {
+                // 끝에 줄바꿈 없이 남은 데이터는 버리고 종료로 처리
+                self.buf.clear();
+                self.discarding = false;
+                return Ok(Frame::Eof);
+            }
+
+            let newline = available.iter().position(|&b| b == b'\n');
+            let chunk_len = newline.map(|i| i + 1).unwrap_or(available.len());
+            let content = &available[..newline.unwrap_or(available.len())];
+
+            if self.discarding {
+                self.discarded += chunk_len;
+            } else if self.buf.len() + content.len() > self.max_len {
+                // 최대 길이 초과: 지금까지 모은 데이터를 버리고 줄 끝까지 건너뜀
+                self.discarded = self.buf.len() + chunk_len;
+                self.buf.clear();
+                self.discarding = true;
+            } else {
+                self.buf.extend_from_slice(content);
+            }
+            reader.consume(chunk_len);
+
+            if newline.is_some() {
+                if self.discarding {
+                    self.discarding = false;
+                    return Ok(Frame::TooLong(std::mem::take(&mut self.discarded)));
+                }
+                let mut line = String::from_utf8_lossy(&self.buf).into_owned();
+                self.buf.clear();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+                return Ok(Frame::Line(line));
+            }
+        }
+    }
+}
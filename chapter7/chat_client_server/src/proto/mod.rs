@@ -0,0 +1,8 @@
+pub mod cipherstate;
+pub mod conn;
+pub mod envelope;
+pub mod frame;
+pub mod noise;
+pub mod packet;
+pub mod padding;
+pub mod transcript;
\ No newline at end of file
@@ -0,0 +1,251 @@
+// src/proto/noise.rs
+// 이 모듈은 직접 만든 ECDH 핸드셰이크 대신 쓸 수 있는 Noise 프로토콜 핸드셰이크(snow 크레이트)를 담당합니다.
+//   - 클라이언트가 서버의 정적 공개키를 모르면 Noise_XX (서로의 정적 키를 암호화해서 교환)
+//   - 미리 알고 있으면 Noise_IK (첫 메시지부터 서버 인증, 1-RTT)
+// 핸드셰이크가 끝나면 이후의 모든 줄은 Noise 전송 상태로 암호화되고,
+// 로그인과 Room Key 전달에 쓰는 세션 키는 핸드셰이크 해시에서 유도합니다.
+
+use std::path::Path;
+
+use aes_gcm::Aes256Gcm;
+use base64::{engine::general_purpose, Engine as _};
+use curve25519_dalek::MontgomeryPoint;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use snow::{Builder, HandshakeState, TransportState};
+use tokio::io::{AsyncBufRead, AsyncWrite};
+use zeroize::Zeroizing;
+
+use crate::conn::LineConn;
+use crate::packet;
+use crate::secret::AesKey;
+
+pub const PATTERN_XX: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+pub const PATTERN_IK: &str = "Noise_IK_25519_ChaChaPoly_BLAKE2s";
+
+// Noise 메시지 하나의 최대 크기 (AEAD 태그 16바이트 포함)
+const MAX_MESSAGE_LEN: usize = 65535;
+const TAG_LEN: usize = 16;
+
+// Noise 정적 키 쌍 (X25519)
+pub struct StaticKey {
+    private: Zeroizing<Vec<u8>>,
+    public: Vec<u8>,
+}
+
+impl StaticKey {
+    pub fn generate() -> Result<Self, String> {
+        let keypair = Builder::new(PATTERN_XX.parse().unwrap())
+            .generate_keypair()
+            .map_err(|e| format!("Noise 키 생성 실패: {}", e))?;
+        Ok(Self { private: Zeroizing::new(keypair.private), public: keypair.public })
+    }
+
+    // 파일에서 비밀키를 읽고, 없으면 새로 만들어 저장
+    pub fn load_or_create(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                let text = Zeroizing::new(text);
+                let private = Zeroizing::new(
+                    general_purpose::STANDARD
+                        .decode(text.trim())
+                        .map_err(|_| "Noise 키 파일 형식이 잘못되었습니다.".to_string())?,
+                );
+                Self::from_private(&private)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key = Self::generate()?;
+                let text = Zeroizing::new(format!("{}\n", general_purpose::STANDARD.encode(key.private.as_slice())));
+                std::fs::write(path, text.as_bytes()).map_err(|e| format!("Noise 키 저장 실패: {}", e))?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+                }
+                Ok(key)
+            }
+            Err(e) => Err(format!("Noise 키를 읽을 수 없습니다: {}", e)),
+        }
+    }
+
+    // 다른 키(wrapping)로 비밀키를 암호화해서 한 줄 패킷으로 만듦 (서버 키 저장소용)
+    pub fn wrap(&self, wrapping: &Aes256Gcm) -> String {
+        packet::seal(wrapping, &self.private)
+    }
+
+    // wrap으로 만든 패킷을 풀어서 키 쌍을 복원
+    pub fn unwrap(wrapping: &Aes256Gcm, line: &str) -> Result<Self, String> {
+        Self::from_private(&Zeroizing::new(packet::open(wrapping, line)?))
+    }
+
+    fn from_private(private: &[u8]) -> Result<Self, String> {
+        let private: Zeroizing<[u8; 32]> =
+            Zeroizing::new(private.try_into().map_err(|_| "Noise 키 길이가 잘못되었습니다.".to_string())?);
+        let public = MontgomeryPoint::mul_base_clamped(*private).to_bytes();
+        Ok(Self { private: Zeroizing::new(private.to_vec()), public: public.to_vec() })
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public
+    }
+}
+
+impl std::fmt::Debug for StaticKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StaticKey")
+            .field("public", &general_purpose::STANDARD.encode(&self.public))
+            .finish_non_exhaustive()
+    }
+}
+
+// 핸드셰이크 결과
+pub struct NoiseSession {
+    pub transport: NoiseTransport,
+    // 로그인과 Room Key 전달에 쓸 세션 키 (핸드셰이크 해시에서 유도)
+    pub session_key: AesKey,
+    // 이 세션을 식별하는 값 (PAKE 바인딩, 세션 번호 계산용)
+    pub handshake_hash: Vec<u8>,
+    pub remote_static: Vec<u8>,
+}
+
+// 핸드셰이크 이후 한 줄 단위로 암호화/복호화하는 전송 상태
+pub struct NoiseTransport {
+    state: TransportState,
+    buf: Vec<u8>,
+}
+
+impl NoiseTransport {
+    // 평문 한 줄을 Noise 메시지로 암호화해서 Base64 한 줄로 반환
+    pub fn encrypt_line(&mut self, line: &str) -> Result<String, String> {
+        if line.len() > MAX_MESSAGE_LEN - TAG_LEN {
+            return Err("Noise 메시지 최대 크기를 넘었습니다.".to_string());
+        }
+        let len = self
+            .state
+            .write_message(line.as_bytes(), &mut self.buf)
+            .map_err(|e| format!("Noise 암호화 실패: {}", e))?;
+        Ok(general_purpose::STANDARD.encode(&self.buf[..len]))
+    }
+
+    pub fn decrypt_line(&mut self, line: &str) -> Result<String, String> {
+        let message = general_purpose::STANDARD
+            .decode(line.trim())
+            .map_err(|_| "Noise 메시지의 Base64 형식이 잘못되었습니다.".to_string())?;
+        if message.len() > MAX_MESSAGE_LEN {
+            return Err("Noise 메시지 최대 크기를 넘었습니다.".to_string());
+        }
+        let len = self
+            .state
+            .read_message(&message, &mut self.buf)
+            .map_err(|e| format!("Noise 복호화 실패: {}", e))?;
+        String::from_utf8(self.buf[..len].to_vec()).map_err(|_| "Noise 평문이 UTF-8이 아닙니다.".to_string())
+    }
+}
+
+// 서버 쪽: 클라이언트가 고른 패턴("NOISE XX" 또는 "NOISE IK")으로 응답자 핸드셰이크 진행
+pub async fn accept<R, W>(conn: &mut LineConn<R, W>, local: &StaticKey) -> Result<NoiseSession, String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let preamble = conn.recv_line().await.map_err(|e| e.to_string())?;
+    let pattern = match preamble.trim() {
+        "NOISE XX" => PATTERN_XX,
+        "NOISE IK" => PATTERN_IK,
+        _ => return Err("알 수 없는 Noise 핸드셰이크 요청입니다.".to_string()),
+    };
+    let state = Builder::new(pattern.parse().unwrap())
+        .local_private_key(&local.private)
+        .build_responder()
+        .map_err(|e| format!("Noise 핸드셰이크 준비 실패: {}", e))?;
+    run(conn, state).await
+}
+
+// 클라이언트 쪽: 서버 정적 공개키를 알면 IK, 모르면 XX로 개시자 핸드셰이크 진행
+pub async fn initiate<R, W>(
+    conn: &mut LineConn<R, W>,
+    local: &StaticKey,
+    server_static: Option<&[u8]>,
+) -> Result<NoiseSession, String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let (preamble, pattern) = match server_static {
+        Some(_) => ("NOISE IK", PATTERN_IK),
+        None => ("NOISE XX", PATTERN_XX),
+    };
+    let builder = Builder::new(pattern.parse().unwrap()).local_private_key(&local.private);
+    let builder = match server_static {
+        Some(key) => builder.remote_public_key(key),
+        None => builder,
+    };
+    let state = builder
+        .build_initiator()
+        .map_err(|e| format!("Noise 핸드셰이크 준비 실패: {}", e))?;
+
+    conn.send_line(preamble).await.map_err(|e| e.to_string())?;
+    run(conn, state).await
+}
+
+// 차례에 따라 핸드셰이크 메시지를 주고받음 (XX는 3번, IK는 2번)
+async fn run<R, W>(conn: &mut LineConn<R, W>, mut state: HandshakeState) -> Result<NoiseSession, String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0u8; MAX_MESSAGE_LEN];
+    while !state.is_handshake_finished() {
+        if state.is_my_turn() {
+            let len = state
+                .write_message(&[], &mut buf)
+                .map_err(|e| format!("Noise 핸드셰이크 실패: {}", e))?;
+            conn.send_line(&general_purpose::STANDARD.encode(&buf[..len]))
+                .await
+                .map_err(|e| e.to_string())?;
+        } else {
+            let line = conn.recv_line().await.map_err(|e| e.to_string())?;
+            let message = general_purpose::STANDARD
+                .decode(line.trim())
+                .map_err(|_| "Noise 핸드셰이크 메시지 형식이 잘못되었습니다.".to_string())?;
+            if message.len() > MAX_MESSAGE_LEN {
+                return Err("Noise 핸드셰이크 메시지가 너무 깁니다.".to_string());
+            }
+            state
+                .read_message(&message, &mut buf)
+                .map_err(|e| format!("Noise 핸드셰이크 실패: {}", e))?;
+        }
+    }
+
+    let handshake_hash = state.get_handshake_hash().to_vec();
+    let remote_static = state.get_remote_static().map(|k| k.to_vec()).unwrap_or_default();
+    let mut okm = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(None, &handshake_hash)
+        .expand(b"chat-noise-session-v1", okm.as_mut())
+        .map_err(|_| "키 유도 실패".to_string())?;
+
+    let transport = state
+        .into_transport_mode()
+        .map_err(|e| format!("Noise 전송 모드 전환 실패: {}", e))?;
+    Ok(NoiseSession {
+        transport: NoiseTransport { state: transport, buf },
+        session_key: AesKey::from_bytes(okm),
+        handshake_hash,
+        remote_static,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_hides_private_key() {
+        let key = StaticKey::generate().unwrap();
+        let debug = format!("{:?}", key);
+        // 공개키는 보여도 되지만 비밀키는 보이면 안 됨
+        assert!(debug.contains(&general_purpose::STANDARD.encode(key.public_key())));
+        crate::secret::assert_redacted(&debug, &key.private);
+        crate::secret::assert_redacted(&format!("{:#?}", key), &key.private);
+    }
+}
@@ -0,0 +1,50 @@
+// src/proto/packet.rs
+// 이 모듈은 AES-256-GCM으로 암호화한 데이터를 한 줄짜리 텍스트 패킷으로 만들고 푸는 로직을 담당합니다.
+// 패킷 형식: Base64( Nonce(12바이트) + 암호문 )
+
+use aes_gcm::{aead::Aead, AeadCore, Aes256Gcm, Nonce};
+use base64::{engine::general_purpose, Engine as _};
+use rand::rngs::OsRng;
+
+pub const NONCE_LEN: usize = 12;
+
+// 평문을 암호화하여 Base64 한 줄로 반환 (줄바꿈 미포함)
+pub fn seal(cipher: &Aes256Gcm, plaintext: &[u8]) -> String {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng); // 96-bits unique
+    seal_with_nonce(cipher, nonce.into(), plaintext)
+}
+
+// 정해진 nonce로 암호화 (nonce가 겹치지 않게 하는 것은 호출하는 쪽 책임, 예: CipherState)
+pub fn seal_with_nonce(cipher: &Aes256Gcm, nonce: [u8; NONCE_LEN], plaintext: &[u8]) -> String {
+    let ciphertext = cipher.encrypt(&Nonce::from(nonce), plaintext).expect("AES-GCM 암호화 실패");
+
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    general_purpose::STANDARD.encode(payload)
+}
+
+// Base64 한 줄을 복호화하여 평문을 반환
+pub fn open(cipher: &Aes256Gcm, line: &str) -> Result<Vec<u8>, String> {
+    let (nonce, ciphertext) = decode(line)?;
+    open_with_nonce(cipher, nonce, &ciphertext)
+}
+
+// Base64 한 줄을 nonce와 암호문으로 나눔 (복호화 키를 nonce로 고를 때 사용)
+pub fn decode(line: &str) -> Result<([u8; NONCE_LEN], Vec<u8>), String> {
+    let mut data = general_purpose::STANDARD
+        .decode(line.trim())
+        .map_err(|_| "패킷의 Base64 형식이 잘못되었습니다.".to_string())?;
+    if data.len() <= NONCE_LEN {
+        return Err("패킷이 너무 짧습니다.".to_string());
+    }
+
+    let ciphertext = data.split_off(NONCE_LEN);
+    let nonce: [u8; NONCE_LEN] = data.try_into().unwrap();
+    Ok((nonce, ciphertext))
+}
+
+pub fn open_with_nonce(cipher: &Aes256Gcm, nonce: [u8; NONCE_LEN], ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    cipher
+        .decrypt(&Nonce::from(nonce), ciphertext)
+        .map_err(|_| "패킷 복호화 실패".to_string())
+}
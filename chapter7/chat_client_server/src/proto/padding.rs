@@ -0,0 +1,166 @@
+// src/proto/padding.rs
+// 이 모듈은 방 메시지의 길이를 숨기는 패딩 정책을 담당합니다.
+// AES-GCM 암호문은 평문보다 정확히 16바이트(태그) 길기 때문에, 그대로 보내면 "ㅇㅋ" 같은 짧은 답장을 길이만 보고 짐작할 수 있습니다.
+// 그래서 Room Key로 암호화하기 전에 평문을 구간(bucket) 경계까지 늘립니다: 평문 || 0x80 || 0x00... (ISO/IEC 7816-4 방식)
+//   - pow2: 2의 거듭제곱 길이 (최소 MIN_BUCKET 바이트)
+//   - block:<N>: N바이트의 배수
+// 정책은 방(서버)마다 정하고, 로그인 응답 "OK <닉네임> pad=<정책>"으로 클라이언트에게 알려줍니다.
+// 귓속말과 그룹 메시지는 서버가 내용을 모르는 채로 그대로 전달하는 별도 암호문이라 여기에 해당하지 않습니다.
+
+use std::fmt;
+use std::str::FromStr;
+
+// 로그인 응답에서 정책을 알리는 머리말
+pub const PADDING_TAG: &str = "pad=";
+// pow2 정책의 가장 작은 구간
+const MIN_BUCKET: usize = 32;
+// 패딩 시작 표시
+const MARKER: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Padding {
+    #[default]
+    None,
+    PowerOfTwo,
+    Block(usize),
+}
+
+impl Padding {
+    // 평문 길이 len을 담을 구간의 크기 (표시 바이트 1개 포함)
+    pub fn bucket(&self, len: usize) -> usize {
+        match self {
+            Padding::None => len,
+            Padding::PowerOfTwo => (len + 1).next_power_of_two().max(MIN_BUCKET),
+            Padding::Block(block) => (len + 1).div_ceil(*block) * block,
+        }
+    }
+
+    pub fn pad(&self, plaintext: &[u8]) -> Vec<u8> {
+        if *self == Padding::None {
+            return plaintext.to_vec();
+        }
+        let mut padded = Vec::with_capacity(self.bucket(plaintext.len()));
+        padded.extend_from_slice(plaintext);
+        padded.push(MARKER);
+        padded.resize(self.bucket(plaintext.len()), 0);
+        padded
+    }
+
+    // 복호화한 평문에서 패딩을 떼어냄. 정책을 쓰는데 표시가 없으면 오류
+    pub fn unpad(&self, mut padded: Vec<u8>) -> Result<Vec<u8>, String> {
+        if *self == Padding::None {
+            return Ok(padded);
+        }
+        let end = padded
+            .iter()
+            .rposition(|&b| b != 0)
+            .filter(|&i| padded[i] == MARKER)
+            .ok_or_else(|| "패딩 형식이 잘못되었습니다.".to_string())?;
+        padded.truncate(end);
+        Ok(padded)
+    }
+}
+
+impl fmt::Display for Padding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Padding::None => write!(f, "none"),
+            Padding::PowerOfTwo => write!(f, "pow2"),
+            Padding::Block(block) => write!(f, "block:{}", block),
+        }
+    }
+}
+
+impl FromStr for Padding {
+    type Err = String;
+
+    // "none", "pow2", "block:<바이트 수>"
+    fn from_str(text: &str) -> Result<Self, String> {
+        match text {
+            "none" => Ok(Padding::None),
+            "pow2" => Ok(Padding::PowerOfTwo),
+            _ => text
+                .strip_prefix("block:")
+                .and_then(|n| n.parse().ok())
+                .filter(|n| (1..=65536).contains(n))
+                .map(Padding::Block)
+                .ok_or_else(|| format!("알 수 없는 패딩 정책입니다 (none, pow2, block:<N>): {}", text)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipherstate::CipherState;
+    use crate::secret::AesKey;
+
+    #[test]
+    fn bucket_boundaries() {
+        let pow2 = Padding::PowerOfTwo;
+        // 표시 바이트 1개가 들어갈 자리까지 포함한 크기
+        assert_eq!(pow2.bucket(0), MIN_BUCKET);
+        assert_eq!(pow2.bucket(MIN_BUCKET - 1), MIN_BUCKET);
+        assert_eq!(pow2.bucket(MIN_BUCKET), 2 * MIN_BUCKET);
+        assert_eq!(pow2.bucket(63), 64);
+        assert_eq!(pow2.bucket(64), 128);
+
+        let block = Padding::Block(16);
+        assert_eq!(block.bucket(0), 16);
+        assert_eq!(block.bucket(15), 16);
+        assert_eq!(block.bucket(16), 32);
+        assert_eq!(Padding::Block(1).bucket(10), 11);
+        assert_eq!(Padding::None.bucket(10), 10);
+    }
+
+    #[test]
+    fn pad_and_unpad_round_trip() {
+        for policy in [Padding::None, Padding::PowerOfTwo, Padding::Block(16), Padding::Block(1)] {
+            for len in 0..200 {
+                // 끝이 0인 평문도 표시 바이트 덕분에 그대로 돌아와야 함
+                let plaintext: Vec<u8> = (0..len).map(|i| if i % 3 == 0 { 0 } else { i as u8 }).collect();
+                let padded = policy.pad(&plaintext);
+                assert_eq!(padded.len(), policy.bucket(len), "{} {}", policy, len);
+                assert_eq!(policy.unpad(padded).unwrap(), plaintext, "{} {}", policy, len);
+            }
+        }
+    }
+
+    #[test]
+    fn unpad_rejects_malformed_padding() {
+        let pow2 = Padding::PowerOfTwo;
+        assert!(pow2.unpad(vec![]).is_err());
+        assert!(pow2.unpad(vec![0; 32]).is_err());
+        // 마지막 0이 아닌 바이트가 표시(0x80)가 아님
+        let mut padded = pow2.pad(b"hello");
+        padded[5] = 0x81;
+        assert!(pow2.unpad(padded).is_err());
+        assert!(pow2.unpad(b"hello".to_vec()).is_err());
+        // 정책이 없으면 그대로 돌려줌
+        assert_eq!(Padding::None.unpad(vec![0; 4]).unwrap(), vec![0; 4]);
+    }
+
+    #[test]
+    fn parse_policies() {
+        assert_eq!("none".parse::<Padding>().unwrap(), Padding::None);
+        assert_eq!("pow2".parse::<Padding>().unwrap(), Padding::PowerOfTwo);
+        assert_eq!("block:64".parse::<Padding>().unwrap(), Padding::Block(64));
+        for bad in ["block:0", "block:65537", "block:", "pow3"] {
+            assert!(bad.parse::<Padding>().is_err(), "{}", bad);
+        }
+    }
+
+    // 같은 구간에 드는 평문은 길이가 달라도 암호화한 방 메시지 줄의 길이가 같아야 함
+    #[test]
+    fn sealed_lines_in_same_bucket_have_equal_length() {
+        let key = AesKey::random();
+        for policy in [Padding::PowerOfTwo, Padding::Block(64)] {
+            let mut room = CipherState::new(&key, 1, 1 << 24).unwrap();
+            let mut seal = |text: &str| room.seal(&policy.pad(text.as_bytes())).unwrap().len();
+            let short = seal("ㅇㅋ");
+            assert_eq!(seal("a somewhat longer reply"), short, "{}", policy);
+            assert_eq!(seal(""), short, "{}", policy);
+            assert_ne!(seal(&"x".repeat(100)), short, "{}", policy);
+        }
+    }
+}
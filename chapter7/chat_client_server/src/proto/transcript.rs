@@ -0,0 +1,87 @@
+// src/proto/transcript.rs
+// 이 모듈은 실제 세션에서 주고받은 프레임을 파일에 기록(record)하고 다시 읽는 일을 담당합니다. (chatconform replay 로 회귀 테스트)
+// 기록 형식: 한 줄에 프레임 하나인 JSON
+//   {"ms":1760862000123,"dir":"send","layer":"room","text":"{\"id\":...}"}
+//   - ms: 기록한 시각 (유닉스 밀리초). 재생할 때 같은 간격으로 보내고, 여러 기록을 함께 재생할 때 순서를 맞추는 데 사용
+//   - dir: send(이쪽이 보냄) / recv(이쪽이 받음)
+//   - layer: raw = 줄 그대로, sealed = 세션 키로 암호화한 줄의 평문(로그인 요청/응답), room = Room Key로 암호화한 줄의 평문
+//   - from: room 수신이면 서버가 알려준 보낸 사람
+// 암호문 대신 평문을 남겨야 새 세션(새 키)으로 다시 암호화해서 재생할 수 있습니다. 키는 기록하지 않습니다.
+// 기록 파일에는 대화 내용이 그대로 들어 있으므로 본인만 읽을 수 있게 만들고, 비밀번호 로그인 세션은 기록하지 않습니다.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Dir {
+    Send,
+    Recv,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Layer {
+    Raw,
+    Sealed,
+    Room,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub ms: u64,
+    pub dir: Dir,
+    pub layer: Layer,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    pub text: String,
+}
+
+pub struct Recorder {
+    out: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        use std::os::unix::fs::OpenOptionsExt;
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .map_err(|e| format!("기록 파일을 만들 수 없습니다: {}", e))?;
+        Ok(Self { out: BufWriter::new(file) })
+    }
+
+    // 한 프레임 기록. 기록에 실패해도 세션은 계속 진행 (한 줄씩 바로 파일에 씀)
+    pub fn write(&mut self, dir: Dir, layer: Layer, from: Option<&str>, text: &str) {
+        let event = Event {
+            ms: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64),
+            dir,
+            layer,
+            from: from.map(str::to_string),
+            text: text.to_string(),
+        };
+        let line = serde_json::to_string(&event).expect("기록 직렬화는 실패하지 않음");
+        let _ = writeln!(self.out, "{}", line).and_then(|_| self.out.flush());
+    }
+}
+
+pub fn load(path: &Path) -> Result<Vec<Event>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut events = vec![];
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("{}: {}", path.display(), e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = serde_json::from_str(&line).map_err(|e| format!("{}:{}: {}", path.display(), number + 1, e))?;
+        events.push(event);
+    }
+    Ok(events)
+}
@@ -0,0 +1,522 @@
+// src/ratchet/double_ratchet.rs
+// 이 모듈은 1:1 귓속말(DM)에 쓰는 Double Ratchet (Signal 방식)을 담당합니다.
+//   - 대칭 래칫: 메시지마다 체인 키를 HMAC으로 한 칸씩 굴려서 새 메시지 키를 만들고, 쓴 키는 버림
+//   - DH 래칫: 상대의 새 래칫 공개키를 받을 때마다 P-256 ECDH 결과를 루트 키에 섞음
+// 첫 공유 비밀은 두 사람의 prekey(신원 키로 서명한 ECDH 키) ECDH에서 유도하고, 응답자의 첫 래칫 키로는 prekey를 그대로 사용합니다.
+// 그래서 먼저 보내는 쪽(개시자)은 상대의 신원 공지에 실린 prekey만 알면 바로 첫 메시지를 보낼 수 있습니다.
+// 신원 키(서명 키)는 ECDH에 쓰지 않으므로, 신원 키가 새어도 지난 세션의 메시지는 풀 수 없습니다.
+
+use std::collections::VecDeque;
+
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Nonce,
+};
+use base64::{engine::general_purpose, Engine as _};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use p256::{elliptic_curve::sec1::ToEncodedPoint, PublicKey, SecretKey};
+use rand::{rngs::OsRng, RngCore};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::identity::PreKey;
+use crate::secret::AesKey;
+
+// 한 번에 건너뛸 수 있는(아직 도착하지 않은) 메시지 수의 상한
+const MAX_SKIP: u32 = 1000;
+// 저장해 두는 건너뛴 메시지 키의 총 개수 상한 (넘으면 오래된 것부터 버림)
+const MAX_SKIPPED_KEYS: usize = 2000;
+// 이미 받은 세션 시작 메시지의 래칫 키를 기억해 두는 개수 (오래된 시작 메시지 재전송으로 세션이 초기화되지 않도록)
+const MAX_SEEN_INITS: usize = 32;
+
+// 래칫 공개키는 SEC1 압축 형식(33바이트)
+const PUBLIC_KEY_LEN: usize = 33;
+const HEADER_LEN: usize = 1 + PUBLIC_KEY_LEN + 4 + 4;
+const NONCE_LEN: usize = 12;
+const FLAG_INIT: u8 = 1;
+
+type Key = Zeroizing<[u8; 32]>;
+
+// 메시지 헤더: 보낸 쪽의 현재 래칫 공개키와 메시지 번호
+// init은 보낸 쪽이 아직 상대에게서 아무것도 받지 못한 개시자라는 표시 (받는 쪽이 세션을 새로 만들 때 사용)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub init: bool,
+    pub dh: Vec<u8>,
+    pub pn: u32,
+    pub n: u32,
+}
+
+impl Header {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_LEN);
+        out.push(if self.init { FLAG_INIT } else { 0 });
+        out.extend_from_slice(&self.dh);
+        out.extend_from_slice(&self.pn.to_be_bytes());
+        out.extend_from_slice(&self.n.to_be_bytes());
+        out
+    }
+
+    // 메시지 앞부분에서 헤더를 읽음
+    pub fn parse(message: &[u8]) -> Result<Header, String> {
+        if message.len() < HEADER_LEN + NONCE_LEN {
+            return Err("DM 메시지가 너무 짧습니다.".to_string());
+        }
+        Ok(Header {
+            init: message[0] & FLAG_INIT != 0,
+            dh: message[1..1 + PUBLIC_KEY_LEN].to_vec(),
+            pn: u32::from_be_bytes(message[1 + PUBLIC_KEY_LEN..HEADER_LEN - 4].try_into().unwrap()),
+            n: u32::from_be_bytes(message[HEADER_LEN - 4..HEADER_LEN].try_into().unwrap()),
+        })
+    }
+}
+
+#[derive(Clone)]
+struct SkippedKey {
+    dh: Vec<u8>,
+    n: u32,
+    mk: Key,
+}
+
+#[derive(Clone)]
+pub struct Ratchet {
+    // 이 세션을 만들 때 쓴 상대 신원 공개키 (바뀌면 세션을 새로 시작해야 함)
+    peer_identity: Vec<u8>,
+    // 내 현재 래칫 키. 응답자는 첫 DH 래칫 전까지 내 prekey를 씀
+    dhs: SecretKey,
+    dhr: Option<Vec<u8>>,
+    rk: Key,
+    cks: Option<Key>,
+    ckr: Option<Key>,
+    ns: u32,
+    nr: u32,
+    pn: u32,
+    received_any: bool,
+    skipped: VecDeque<SkippedKey>,
+    // 상대가 세션을 시작할 때 쓴 래칫 공개키 목록 (이전 세션 것까지 이어받음)
+    seen_inits: VecDeque<Vec<u8>>,
+}
+
+// DM 세션을 시작하는 한쪽의 공개값: 닉네임, 신원 공개키, 그 신원 키로 서명한 prekey 공개키
+pub struct Party<'a> {
+    pub nick: &'a str,
+    pub identity: &'a [u8],
+    pub prekey: &'a [u8],
+}
+
+// 두 prekey의 ECDH로 첫 루트 키 유도. 양쪽의 닉네임과 공개키를 정렬해서 넣으므로 양쪽에서 같은 값이 나옴
+// (prekey 서명은 신원 공지를 받을 때 이미 확인했으므로, 신원 공개키를 넣어 두면 세션이 그 신원에 묶임)
+pub fn initial_secret(prekey: &PreKey, me: &Party, peer: &Party) -> Result<Key, String> {
+    let dh = prekey.diffie_hellman(peer.prekey)?;
+    let mine = (me.nick.as_bytes(), me.identity, me.prekey);
+    let theirs = (peer.nick.as_bytes(), peer.identity, peer.prekey);
+    let (first, second) = if mine <= theirs { (mine, theirs) } else { (theirs, mine) };
+
+    let mut info = b"chat-dr-init-v2".to_vec();
+    for part in [first.0, first.1, first.2, second.0, second.1, second.2] {
+        info.extend_from_slice(&(part.len() as u16).to_be_bytes());
+        info.extend_from_slice(part);
+    }
+    let mut sk = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(None, dh.as_slice())
+        .expand(&info, sk.as_mut())
+        .map_err(|_| "키 유도 실패".to_string())?;
+    Ok(sk)
+}
+
+impl Ratchet {
+    // 개시자: 새 래칫 키를 만들고 상대 prekey(= 상대의 첫 래칫 키)로 바로 보내기 체인을 만듦
+    pub fn initiate(sk: Key, peer_identity: &[u8], peer_prekey: &[u8]) -> Result<Self, String> {
+        Self::initiate_with_key(sk, peer_identity, peer_prekey, SecretKey::random(&mut OsRng))
+    }
+
+    // 정해진 첫 래칫 키로 시작 (프로토콜 테스트 벡터 전용)
+    pub fn initiate_with_key(sk: Key, peer_identity: &[u8], peer_prekey: &[u8], dhs: SecretKey) -> Result<Self, String> {
+        let (rk, cks) = kdf_rk(&sk, &dh(&dhs, peer_prekey)?)?;
+        Ok(Self {
+            peer_identity: peer_identity.to_vec(),
+            dhs,
+            dhr: Some(peer_prekey.to_vec()),
+            rk,
+            cks: Some(cks),
+            ckr: None,
+            ns: 0,
+            nr: 0,
+            pn: 0,
+            received_any: false,
+            skipped: VecDeque::new(),
+            seen_inits: VecDeque::new(),
+        })
+    }
+
+    // 응답자: 첫 메시지를 받을 때 DH 래칫이 일어나므로 그 전까지는 보낼 수 없음
+    pub fn respond(sk: Key, peer_identity: &[u8], prekey: &PreKey) -> Self {
+        Self {
+            peer_identity: peer_identity.to_vec(),
+            dhs: prekey.ratchet_key(),
+            dhr: None,
+            rk: sk,
+            cks: None,
+            ckr: None,
+            ns: 0,
+            nr: 0,
+            pn: 0,
+            received_any: false,
+            skipped: VecDeque::new(),
+            seen_inits: VecDeque::new(),
+        }
+    }
+
+    pub fn peer_identity(&self) -> &[u8] {
+        &self.peer_identity
+    }
+
+    // 이 래칫 키로 시작하는 세션을 이미 받은 적이 있는지 (재전송된 시작 메시지 판별용)
+    pub fn has_seen_init(&self, dh: &[u8]) -> bool {
+        self.seen_inits.iter().any(|seen| seen == dh)
+    }
+
+    // 이전 세션에서 본 시작 메시지 목록을 이어받고, 이번 시작 메시지도 기록
+    pub fn inherit_seen_inits(&mut self, previous: Option<&Ratchet>, dh: &[u8]) {
+        if let Some(previous) = previous {
+            self.seen_inits = previous.seen_inits.clone();
+        }
+        if self.seen_inits.len() >= MAX_SEEN_INITS {
+            self.seen_inits.pop_front();
+        }
+        self.seen_inits.push_back(dh.to_vec());
+    }
+
+    // 상대의 메시지를 아직 하나도 받지 못한 개시자인지 (동시에 대화를 시작한 경우 판별용)
+    pub fn is_unconfirmed(&self) -> bool {
+        !self.received_any
+    }
+
+    // 메시지 암호화: 헤더 || nonce || AES-GCM 암호문. ad는 양쪽이 같은 값을 넣어야 하는 추가 인증 데이터
+    pub fn encrypt(&mut self, ad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        self.encrypt_with_nonce(ad, plaintext, nonce)
+    }
+
+    // 정해진 nonce로 암호화 (프로토콜 테스트 벡터 전용. 메시지 키가 매번 다르므로 nonce가 겹쳐도 키와 함께 겹치지는 않음)
+    pub fn encrypt_with_nonce(&mut self, ad: &[u8], plaintext: &[u8], nonce: [u8; NONCE_LEN]) -> Result<Vec<u8>, String> {
+        let cks = self
+            .cks
+            .as_ref()
+            .ok_or_else(|| "상대의 첫 메시지를 받기 전에는 보낼 수 없습니다.".to_string())?;
+        let (next, mk) = kdf_ck(cks);
+        let header = Header {
+            init: !self.received_any,
+            dh: self.public_key(),
+            pn: self.pn,
+            n: self.ns,
+        };
+        self.cks = Some(next);
+        self.ns += 1;
+
+        let mut out = header.encode();
+        let ciphertext = seal(&mk, &nonce, &associated_data(ad, &out), plaintext)?;
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    // 메시지 복호화. 실패하면 상태를 바꾸지 않음 (위조된 메시지로 세션이 깨지지 않도록 사본에서 진행)
+    // 평문은 Zeroizing으로 돌려줘서 다 쓰면 메모리에서 지워짐
+    pub fn decrypt(&mut self, ad: &[u8], message: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        let mut next = self.clone();
+        let plaintext = next.decrypt_in_place(ad, message)?;
+        *self = next;
+        Ok(plaintext)
+    }
+
+    fn decrypt_in_place(&mut self, ad: &[u8], message: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        let header = Header::parse(message)?;
+        let (header_bytes, rest) = message.split_at(HEADER_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let ad = associated_data(ad, header_bytes);
+
+        // 1. 순서가 뒤바뀌어 늦게 도착한 메시지면 저장해 둔 키 사용
+        if let Some(pos) = self.skipped.iter().position(|k| k.dh == header.dh && k.n == header.n) {
+            let key = self.skipped.remove(pos).unwrap();
+            let plaintext = open(&key.mk, nonce, &ad, ciphertext)?;
+            self.received_any = true;
+            return Ok(plaintext);
+        }
+
+        // 2. 새 래칫 공개키면 이전 체인에서 남은 키를 저장해 두고 DH 래칫
+        if self.dhr.as_deref() != Some(header.dh.as_slice()) {
+            self.skip_message_keys(header.pn)?;
+            self.dh_ratchet(&header)?;
+        }
+
+        // 3. 이번 체인에서 건너뛴 메시지의 키를 저장하고 이번 메시지 키 계산
+        self.skip_message_keys(header.n)?;
+        let ckr = self.ckr.as_ref().ok_or_else(|| "받기 체인이 없습니다.".to_string())?;
+        let (next, mk) = kdf_ck(ckr);
+        self.ckr = Some(next);
+        self.nr += 1;
+
+        let plaintext = open(&mk, nonce, &ad, ciphertext)?;
+        self.received_any = true;
+        Ok(plaintext)
+    }
+
+    fn skip_message_keys(&mut self, until: u32) -> Result<(), String> {
+        let Some(mut ckr) = self.ckr.clone() else {
+            return Ok(());
+        };
+        if until > self.nr.saturating_add(MAX_SKIP) {
+            return Err("건너뛴 메시지가 너무 많습니다.".to_string());
+        }
+        let dh = self.dhr.clone().unwrap_or_default();
+        while self.nr < until {
+            let (next, mk) = kdf_ck(&ckr);
+            if self.skipped.len() >= MAX_SKIPPED_KEYS {
+                self.skipped.pop_front();
+            }
+            self.skipped.push_back(SkippedKey { dh: dh.clone(), n: self.nr, mk });
+            ckr = next;
+            self.nr += 1;
+        }
+        self.ckr = Some(ckr);
+        Ok(())
+    }
+
+    fn dh_ratchet(&mut self, header: &Header) -> Result<(), String> {
+        // 받기 체인: 내 현재 래칫 키 x 상대의 새 래칫 키
+        let (rk, ckr) = kdf_rk(&self.rk, &dh(&self.dhs, &header.dh)?)?;
+        // 보내기 체인: 내 새 래칫 키 x 상대의 새 래칫 키
+        let dhs = SecretKey::random(&mut OsRng);
+        let (rk, cks) = kdf_rk(&rk, &dh(&dhs, &header.dh)?)?;
+
+        self.pn = self.ns;
+        self.ns = 0;
+        self.nr = 0;
+        self.dhr = Some(header.dh.clone());
+        self.dhs = dhs;
+        self.rk = rk;
+        self.ckr = Some(ckr);
+        self.cks = Some(cks);
+        Ok(())
+    }
+
+    fn public_key(&self) -> Vec<u8> {
+        self.dhs.public_key().to_encoded_point(true).as_bytes().to_vec()
+    }
+
+    // 파일 저장용 텍스트 (key value 한 줄씩). 비밀값이 들어 있으므로 Zeroizing으로 돌려줌
+    pub fn to_text(&self) -> Zeroizing<String> {
+        let b64 = |bytes: &[u8]| general_purpose::STANDARD.encode(bytes);
+        let opt = |key: &Option<Key>| key.as_ref().map_or("-".to_string(), |k| b64(k.as_slice()));
+        let mut out = Zeroizing::new(String::new());
+        out.push_str(&format!("peer_identity {}\n", b64(&self.peer_identity)));
+        let dhs: Key = Zeroizing::new(self.dhs.to_bytes().into());
+        out.push_str(&format!("dhs {}\n", b64(dhs.as_slice())));
+        out.push_str(&format!("dhr {}\n", self.dhr.as_ref().map_or("-".to_string(), |k| b64(k))));
+        out.push_str(&format!("rk {}\n", b64(self.rk.as_slice())));
+        out.push_str(&format!("cks {}\n", opt(&self.cks)));
+        out.push_str(&format!("ckr {}\n", opt(&self.ckr)));
+        out.push_str(&format!("counters {} {} {} {}\n", self.ns, self.nr, self.pn, self.received_any as u8));
+        for key in &self.skipped {
+            out.push_str(&format!("skipped {} {} {}\n", b64(&key.dh), key.n, b64(key.mk.as_slice())));
+        }
+        for dh in &self.seen_inits {
+            out.push_str(&format!("seen_init {}\n", b64(dh)));
+        }
+        out
+    }
+
+    // to_text의 역
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let bad = || "DM 세션 파일 형식이 잘못되었습니다.".to_string();
+        let bytes = |s: &str| general_purpose::STANDARD.decode(s).map_err(|_| bad());
+        let key = |s: &str| -> Result<Key, String> {
+            let raw = Zeroizing::new(bytes(s)?);
+            Ok(Zeroizing::new(raw.as_slice().try_into().map_err(|_| bad())?))
+        };
+        let opt_key = |s: &str| if s == "-" { Ok(None) } else { key(s).map(Some) };
+
+        // 래칫 키는 파일의 "dhs" 줄로 채우므로, 그 줄이 없으면 형식 오류
+        let mut ratchet = Self::respond(Zeroizing::new([0u8; 32]), &[], &PreKey::generate());
+        let mut has_dhs = false;
+        for line in text.lines() {
+            let (name, value) = line.split_once(' ').ok_or_else(bad)?;
+            match name {
+                "peer_identity" => ratchet.peer_identity = bytes(value)?,
+                // 예전 형식(신원 키를 래칫 키로 쓰던 세션)은 읽지 않음
+                "dhs" => {
+                    ratchet.dhs = SecretKey::from_slice(key(value)?.as_slice()).map_err(|_| bad())?;
+                    has_dhs = true;
+                }
+                "dhr" => ratchet.dhr = if value == "-" { None } else { Some(bytes(value)?) },
+                "rk" => ratchet.rk = key(value)?,
+                "cks" => ratchet.cks = opt_key(value)?,
+                "ckr" => ratchet.ckr = opt_key(value)?,
+                "counters" => {
+                    let nums: Vec<u32> = value.split(' ').map(|n| n.parse().map_err(|_| bad())).collect::<Result<_, _>>()?;
+                    let [ns, nr, pn, received] = nums[..] else { return Err(bad()) };
+                    (ratchet.ns, ratchet.nr, ratchet.pn, ratchet.received_any) = (ns, nr, pn, received != 0);
+                }
+                "skipped" => {
+                    let mut fields = value.split(' ');
+                    let (Some(dh), Some(n), Some(mk)) = (fields.next(), fields.next(), fields.next()) else {
+                        return Err(bad());
+                    };
+                    ratchet.skipped.push_back(SkippedKey {
+                        dh: bytes(dh)?,
+                        n: n.parse().map_err(|_| bad())?,
+                        mk: key(mk)?,
+                    });
+                }
+                "seen_init" => ratchet.seen_inits.push_back(bytes(value)?),
+                _ => return Err(bad()),
+            }
+        }
+        if !has_dhs {
+            return Err(bad());
+        }
+        Ok(ratchet)
+    }
+}
+
+fn dh(secret: &SecretKey, peer: &[u8]) -> Result<Key, String> {
+    let peer = PublicKey::from_sec1_bytes(peer).map_err(|_| "상대 래칫 공개키 형식이 잘못되었습니다.".to_string())?;
+    let shared = p256::ecdh::diffie_hellman(secret.to_nonzero_scalar(), peer.as_affine());
+    Ok(Zeroizing::new((*shared.raw_secret_bytes()).into()))
+}
+
+// 루트 키 래칫: HKDF(salt = 루트 키, ikm = DH 결과) → (새 루트 키, 새 체인 키)
+fn kdf_rk(rk: &Key, dh_out: &Key) -> Result<(Key, Key), String> {
+    let mut okm = Zeroizing::new([0u8; 64]);
+    Hkdf::<Sha256>::new(Some(rk.as_slice()), dh_out.as_slice())
+        .expand(b"chat-dr-root-v1", okm.as_mut())
+        .map_err(|_| "키 유도 실패".to_string())?;
+    let mut root = Zeroizing::new([0u8; 32]);
+    let mut chain = Zeroizing::new([0u8; 32]);
+    root.copy_from_slice(&okm[..32]);
+    chain.copy_from_slice(&okm[32..]);
+    Ok((root, chain))
+}
+
+// 체인 키 래칫: HMAC(체인 키, 0x02) → 다음 체인 키, HMAC(체인 키, 0x01) → 메시지 키
+fn kdf_ck(ck: &Key) -> (Key, Key) {
+    let step = |byte: u8| {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(ck.as_slice()).expect("HMAC은 모든 키 길이를 허용");
+        mac.update(&[byte]);
+        Zeroizing::new(<[u8; 32]>::from(mac.finalize().into_bytes()))
+    };
+    (step(0x02), step(0x01))
+}
+
+fn associated_data(ad: &[u8], header: &[u8]) -> Vec<u8> {
+    let mut out = ad.to_vec();
+    out.extend_from_slice(header);
+    out
+}
+
+fn seal(mk: &Key, nonce: &[u8; NONCE_LEN], ad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    AesKey::from_bytes(mk.clone())
+        .cipher()
+        .encrypt(&Nonce::from(*nonce), Payload { msg: plaintext, aad: ad })
+        .map_err(|_| "DM 암호화 실패".to_string())
+}
+
+fn open(mk: &Key, nonce: &[u8], ad: &[u8], ciphertext: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    let nonce: [u8; NONCE_LEN] = nonce.try_into().map_err(|_| "DM 메시지가 너무 짧습니다.".to_string())?;
+    AesKey::from_bytes(mk.clone())
+        .cipher()
+        .decrypt(&Nonce::from(nonce), Payload { msg: ciphertext, aad: ad })
+        .map(Zeroizing::new)
+        .map_err(|_| "DM 복호화 실패".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::IdentityKey;
+
+    const AD: &[u8] = b"alice->bob";
+
+    // alice가 세션을 시작하고 bob이 응답자로 받는 한 쌍
+    fn pair() -> (Ratchet, Ratchet) {
+        let (alice, bob) = (IdentityKey::generate(), IdentityKey::generate());
+        let (alice_prekey, bob_prekey) = (PreKey::generate(), PreKey::generate());
+        let (alice_public, bob_public) = (alice.public_key_bytes(), bob.public_key_bytes());
+        let (alice_pre, bob_pre) = (alice_prekey.public_key_bytes(), bob_prekey.public_key_bytes());
+        let a = Party { nick: "alice", identity: &alice_public, prekey: &alice_pre };
+        let b = Party { nick: "bob", identity: &bob_public, prekey: &bob_pre };
+
+        let sk = initial_secret(&alice_prekey, &a, &b).unwrap();
+        let sending = Ratchet::initiate(sk, &bob_public, &bob_pre).unwrap();
+        let sk = initial_secret(&bob_prekey, &b, &a).unwrap();
+        let receiving = Ratchet::respond(sk, &alice_public, &bob_prekey);
+        (sending, receiving)
+    }
+
+    #[test]
+    fn out_of_order_messages_decrypt_once() {
+        let (mut a, mut b) = pair();
+        let first: Vec<Vec<u8>> = (0..3).map(|i| a.encrypt(AD, format!("m{}", i).as_bytes()).unwrap()).collect();
+
+        assert_eq!(b.decrypt(AD, &first[2]).unwrap().as_slice(), b"m2");
+        assert_eq!(b.decrypt(AD, &first[0]).unwrap().as_slice(), b"m0");
+        // 저장해 둔 키는 한 번 쓰면 지워지므로 같은 메시지를 다시 받으면 실패
+        assert!(b.decrypt(AD, &first[0]).is_err());
+
+        // bob의 답장으로 DH 래칫이 한 번 돈 뒤에도, 이전 체인에서 늦게 온 메시지는 풀림
+        let reply = b.encrypt(AD, b"reply").unwrap();
+        assert_eq!(a.decrypt(AD, &reply).unwrap().as_slice(), b"reply");
+        let next = a.encrypt(AD, b"m3").unwrap();
+        assert_eq!(b.decrypt(AD, &next).unwrap().as_slice(), b"m3");
+        assert_eq!(b.decrypt(AD, &first[1]).unwrap().as_slice(), b"m1");
+    }
+
+    #[test]
+    fn skipping_more_than_max_skip_is_rejected() {
+        let (mut a, mut b) = pair();
+        let messages: Vec<Vec<u8>> = (0..=MAX_SKIP + 1).map(|_| a.encrypt(AD, b"x").unwrap()).collect();
+
+        let before = b.to_text();
+        assert!(b.decrypt(AD, &messages[MAX_SKIP as usize + 1]).is_err());
+        // 실패한 복호화는 상태를 바꾸지 않음
+        assert_eq!(*b.to_text(), *before);
+
+        // 정확히 MAX_SKIP개를 건너뛰는 것은 허용
+        assert_eq!(b.decrypt(AD, &messages[MAX_SKIP as usize]).unwrap().as_slice(), b"x");
+        assert_eq!(b.decrypt(AD, &messages[0]).unwrap().as_slice(), b"x");
+    }
+
+    #[test]
+    fn text_round_trip_keeps_session() {
+        let (mut a, mut b) = pair();
+        let late = a.encrypt(AD, b"late").unwrap();
+        let now = a.encrypt(AD, b"now").unwrap();
+        assert_eq!(b.decrypt(AD, &now).unwrap().as_slice(), b"now");
+        let reply = b.encrypt(AD, b"reply").unwrap();
+        assert_eq!(a.decrypt(AD, &reply).unwrap().as_slice(), b"reply");
+
+        // 건너뛴 키, 래칫 키, 카운터까지 그대로 저장되고 읽혀야 함
+        let mut a = Ratchet::from_text(&a.to_text()).unwrap();
+        let mut restored = Ratchet::from_text(&b.to_text()).unwrap();
+        assert_eq!(*restored.to_text(), *b.to_text());
+
+        assert_eq!(restored.decrypt(AD, &late).unwrap().as_slice(), b"late");
+        let next = a.encrypt(AD, b"after reload").unwrap();
+        assert_eq!(restored.decrypt(AD, &next).unwrap().as_slice(), b"after reload");
+        assert!(Ratchet::from_text("rk AAAA\n").is_err());
+    }
+
+    #[test]
+    fn first_message_needs_responder_prekey() {
+        // 루트 키가 같아도 응답자의 prekey 비밀키가 없으면 첫 메시지를 풀 수 없어야 함 (신원 키는 ECDH에 쓰지 않음)
+        let (mut a, mut b) = pair();
+        let first = a.encrypt(AD, b"secret").unwrap();
+        let mut without_prekey = b.clone();
+        without_prekey.dhs = PreKey::generate().ratchet_key();
+        assert!(without_prekey.decrypt(AD, &first).is_err());
+        assert_eq!(b.decrypt(AD, &first).unwrap().as_slice(), b"secret");
+    }
+}
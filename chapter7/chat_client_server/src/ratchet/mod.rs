@@ -0,0 +1,2 @@
+pub mod double_ratchet;
+pub mod sessions;
\ No newline at end of file
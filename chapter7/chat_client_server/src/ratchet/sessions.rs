@@ -0,0 +1,169 @@
+// src/ratchet/sessions.rs
+// 이 모듈은 상대별 Double Ratchet 세션을 모아 관리하고 파일에 저장하는 일을 담당합니다.
+// 파일 형식: "session <닉네임>" 줄로 시작해서 "end" 줄로 끝나는 블록이 상대마다 하나씩 (Ratchet::to_text 참고)
+// 메시지를 보내거나 받을 때마다 저장하므로, 클라이언트를 다시 시작해도 같은 세션으로 대화를 이어갈 수 있습니다.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use zeroize::Zeroizing;
+
+use crate::double_ratchet::{self, Header, Party, Ratchet};
+use crate::identity::PreKey;
+
+pub struct DmSessions {
+    path: Option<PathBuf>,
+    sessions: BTreeMap<String, Ratchet>,
+    // 새 세션을 만들 때 쓰는 내 닉네임, 신원 공개키, prekey
+    nick: String,
+    identity: Vec<u8>,
+    prekey: PreKey,
+}
+
+impl DmSessions {
+    // path가 None이면 메모리에만 보관 (일회용 신원 키를 쓸 때)
+    pub fn load(path: Option<PathBuf>, nick: &str, identity: Vec<u8>, prekey: PreKey) -> Result<Self, String> {
+        let mut sessions = BTreeMap::new();
+        let text = match &path {
+            None => Zeroizing::new(String::new()),
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(text) => Zeroizing::new(text),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Zeroizing::new(String::new()),
+                Err(e) => return Err(format!("DM 세션 파일을 읽을 수 없습니다: {}", e)),
+            },
+        };
+
+        let mut current: Option<(String, Zeroizing<String>)> = None;
+        for line in text.lines() {
+            if let Some(peer) = line.strip_prefix("session ") {
+                current = Some((peer.to_string(), Zeroizing::new(String::new())));
+            } else if line == "end" {
+                let (peer, block) = current.take().ok_or("DM 세션 파일 형식이 잘못되었습니다.")?;
+                sessions.insert(peer, Ratchet::from_text(&block)?);
+            } else if let Some((_, block)) = current.as_mut() {
+                block.push_str(line);
+                block.push('\n');
+            }
+        }
+
+        Ok(Self { path, sessions, nick: nick.to_string(), identity, prekey })
+    }
+
+    // 신원 공지에 실어 보낼 내 prekey 공개키
+    pub fn prekey_public(&self) -> Vec<u8> {
+        self.prekey.public_key_bytes()
+    }
+
+    // 상대와의 첫 루트 키 (상대 prekey를 받지 못했으면 새 세션을 시작할 수 없음)
+    fn initial_secret(&self, peer: &str, peer_identity: &[u8], peer_prekey: Option<&[u8]>) -> Result<Zeroizing<[u8; 32]>, String> {
+        let peer_prekey = peer_prekey.ok_or_else(|| format!("{}의 prekey를 받지 못해 DM 세션을 시작할 수 없습니다.", peer))?;
+        let my_prekey = self.prekey.public_key_bytes();
+        let me = Party { nick: &self.nick, identity: &self.identity, prekey: &my_prekey };
+        let them = Party { nick: peer, identity: peer_identity, prekey: peer_prekey };
+        double_ratchet::initial_secret(&self.prekey, &me, &them)
+    }
+
+    // 상대에게 보낼 메시지 암호화. 세션이 없거나 상대 신원 키가 바뀌었으면 개시자로 새 세션 시작
+    pub fn encrypt(&mut self, peer: &str, peer_identity: &[u8], peer_prekey: Option<&[u8]>, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let reusable = self.sessions.get(peer).is_some_and(|s| s.peer_identity() == peer_identity);
+        if !reusable {
+            let sk = self.initial_secret(peer, peer_identity, peer_prekey)?;
+            let peer_prekey = peer_prekey.expect("initial_secret이 확인함");
+            self.sessions.insert(peer.to_string(), Ratchet::initiate(sk, peer_identity, peer_prekey)?);
+        }
+        let ad = associated_data(&self.nick, peer);
+        let session = self.sessions.get_mut(peer).unwrap();
+        let message = session.encrypt(&ad, plaintext)?;
+        self.save()?;
+        Ok(message)
+    }
+
+    // 상대에게서 받은 메시지 복호화
+    pub fn decrypt(
+        &mut self,
+        peer: &str,
+        peer_identity: &[u8],
+        peer_prekey: Option<&[u8]>,
+        message: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, String> {
+        let ad = associated_data(peer, &self.nick);
+        let header = Header::parse(message)?;
+
+        // 1. 지금 세션으로 풀리면 그대로 사용
+        let current = self.sessions.get_mut(peer).filter(|s| s.peer_identity() == peer_identity);
+        let current_error = match current {
+            Some(session) => match session.decrypt(&ad, message) {
+                Ok(plaintext) => {
+                    self.save()?;
+                    return Ok(plaintext);
+                }
+                Err(e) => Some((e, session.is_unconfirmed())),
+            },
+            None => None,
+        };
+
+        // 2. 상대가 새 세션을 시작한 메시지가 아니면 실패
+        if !header.init {
+            return Err(current_error.map_or("DM 세션이 없습니다.".to_string(), |(e, _)| e));
+        }
+        // 3. 예전에 받은 시작 메시지를 누군가 다시 보낸 것이면 거부 (세션 초기화 공격 방지)
+        let previous = self.sessions.get(peer);
+        if previous.is_some_and(|s| s.has_seen_init(&header.dh)) {
+            return Err(current_error.map_or("이미 받은 메시지입니다.".to_string(), |(e, _)| e));
+        }
+        // 4. 둘이 동시에 새 세션을 시작했으면 닉네임이 앞서는 쪽의 세션을 남김
+        if let Some((_, true)) = current_error
+            && self.nick.as_str() < peer
+        {
+            return Err("동시에 대화를 시작해서 상대의 첫 메시지를 버렸습니다. 상대가 다시 보내야 합니다.".to_string());
+        }
+
+        // 5. 응답자로 새 세션을 만들어 시도 (성공할 때만 기존 세션을 교체)
+        let sk = self.initial_secret(peer, peer_identity, peer_prekey)?;
+        let mut session = Ratchet::respond(sk, peer_identity, &self.prekey);
+        let plaintext = session.decrypt(&ad, message)?;
+        session.inherit_seen_inits(previous, &header.dh);
+        if let Some((_, unconfirmed)) = current_error {
+            say!(
+                "🔄 {}와의 DM 세션이 새로 시작되었습니다.{}",
+                peer,
+                if unconfirmed { " 내가 먼저 보낸 메시지는 전달되지 않았을 수 있습니다." } else { "" }
+            );
+        }
+        self.sessions.insert(peer.to_string(), session);
+        self.save()?;
+        Ok(plaintext)
+    }
+
+    // 임시 파일에 쓴 뒤 이름을 바꿔서 중간에 깨진 파일이 남지 않도록 저장
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut text = Zeroizing::new(String::from("# chatclient DM sessions (Double Ratchet)\n"));
+        for (peer, session) in &self.sessions {
+            text.push_str(&format!("session {}\n", peer));
+            text.push_str(&session.to_text());
+            text.push_str("end\n");
+        }
+
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, text.as_bytes()).map_err(|e| format!("DM 세션 저장 실패: {}", e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600));
+        }
+        std::fs::rename(&tmp, path).map_err(|e| format!("DM 세션 저장 실패: {}", e))
+    }
+}
+
+// 보낸 사람과 받는 사람을 인증 데이터에 넣어서, 다른 사람 사이의 메시지로 바꿔치기할 수 없게 함
+fn associated_data(sender: &str, recipient: &str) -> Vec<u8> {
+    let mut ad = b"chat-dm-v1".to_vec();
+    for part in [sender, recipient] {
+        ad.extend_from_slice(&(part.len() as u16).to_be_bytes());
+        ad.extend_from_slice(part.as_bytes());
+    }
+    ad
+}
@@ -0,0 +1,175 @@
+// src/room/keystore.rs
+// 이 모듈은 서버를 다시 시작해도 같은 Room Key와 서버 신원 키(Noise 정적 키)를 쓰도록 키를 암호화해서 보관하는 키 저장소(--keystore)를 담당합니다.
+// 파일 형식: {"version":1,"salt":"<Base64>","sealed":"<패킷>"}
+//   - 키: Argon2id(암호 또는 KEK 파일 내용, 솔트) → HKDF로 저장소 키 유도
+//   - sealed: 저장소 키로 AES-256-GCM 암호화한 JSON
+//       {"noise_key":"<감싼 키>","rooms":{"room":{"key":"<감싼 키>","next_sender":1025,"next_epoch":1}}}
+//     키는 원시 바이트를 내주지 않으므로 저장소 키로 한 번 더 감싼(wrap) 패킷으로 넣습니다.
+// 같은 Room Key를 다시 쓰면 nonce(발신자 번호 || 세대 || 카운터)가 지난 실행과 겹치지 않아야 합니다.
+//   - next_sender: 이 번호부터는 아직 아무에게도 주지 않은 발신자 번호. 로그인마다 저장하지 않도록 SENDER_BLOCK개씩 미리 예약
+//   - next_epoch: 서버 자신(발신자 번호 0)이 아직 쓰지 않은 세대. 새 세대로 암호화하기 전에 먼저 저장
+// 그래서 저장에 실패하면 그 번호나 세대를 쓰지 않습니다. 파일은 본인만 읽을 수 있게 만듭니다.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::KeyInit;
+use aes_gcm::Aes256Gcm;
+use argon2::Argon2;
+use base64::{engine::general_purpose, Engine as _};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::cipherstate::SERVER_SENDER;
+use crate::noise::StaticKey;
+use crate::packet;
+use crate::secret::AesKey;
+
+const VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+// 한 번에 예약하는 발신자 번호 수
+const SENDER_BLOCK: u32 = 1024;
+
+// 디스크에 저장하는 형식
+#[derive(Serialize, Deserialize)]
+struct File {
+    version: u32,
+    salt: String,
+    sealed: String,
+}
+
+// 암호화되는 내용
+#[derive(Serialize, Deserialize, Default)]
+struct Contents {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    noise_key: Option<String>,
+    rooms: BTreeMap<String, RoomKeys>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RoomKeys {
+    key: String,
+    next_sender: u32,
+    next_epoch: u32,
+}
+
+pub struct Keystore {
+    path: PathBuf,
+    salt: Vec<u8>,
+    cipher: Aes256Gcm,
+    contents: Contents,
+}
+
+impl Keystore {
+    // 키 저장소를 열거나 (파일이 없으면) 빈 저장소를 만듦. 암호가 다르면 오류
+    pub fn open(path: &Path, secret: &[u8]) -> Result<Self, String> {
+        let file = match std::fs::read_to_string(path) {
+            Ok(text) => Some(serde_json::from_str::<File>(&text).map_err(|e| format!("키 저장소 형식이 잘못되었습니다: {}", e))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("키 저장소를 읽을 수 없습니다: {}", e)),
+        };
+        let salt = match &file {
+            Some(file) if file.version != VERSION => return Err(format!("지원하지 않는 키 저장소 버전입니다: {}", file.version)),
+            Some(file) => general_purpose::STANDARD.decode(&file.salt).map_err(|_| "키 저장소의 솔트 형식이 잘못되었습니다.".to_string())?,
+            None => {
+                let mut salt = vec![0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                salt
+            }
+        };
+
+        let mut master = Zeroizing::new([0u8; 32]);
+        Argon2::default()
+            .hash_password_into(secret, &salt, master.as_mut())
+            .map_err(|e| format!("Argon2 키 유도 실패: {}", e))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Hkdf::<Sha256>::new(Some(&salt), master.as_slice())
+            .expand(b"chat-keystore-v1", key.as_mut())
+            .expect("HKDF 출력 길이");
+        let cipher = Aes256Gcm::new(&(*key).into());
+
+        let contents = match &file {
+            Some(file) => {
+                let plaintext = Zeroizing::new(packet::open(&cipher, &file.sealed).map_err(|_| "키 저장소 암호가 맞지 않습니다.".to_string())?);
+                serde_json::from_slice(&plaintext).map_err(|e| format!("키 저장소 내용이 잘못되었습니다: {}", e))?
+            }
+            None => Contents::default(),
+        };
+        Ok(Self { path: path.to_path_buf(), salt, cipher, contents })
+    }
+
+    // 저장된 서버 신원 키. 없으면 새로 만들어 저장
+    pub fn noise_key(&mut self) -> Result<StaticKey, String> {
+        if let Some(wrapped) = &self.contents.noise_key {
+            return StaticKey::unwrap(&self.cipher, wrapped);
+        }
+        let key = StaticKey::generate()?;
+        self.contents.noise_key = Some(key.wrap(&self.cipher));
+        self.save()?;
+        Ok(key)
+    }
+
+    // 방의 Room Key와, 이번 실행에서 처음 줄 발신자 번호, 서버가 쓸 첫 세대
+    // 처음 여는 방이면 새 키를 만들고, 돌려준 번호와 세대는 쓴 것으로 저장한 뒤 돌려줌
+    pub fn open_room(&mut self, name: &str) -> Result<(AesKey, u32, u32), String> {
+        if !self.contents.rooms.contains_key(name) {
+            let room = RoomKeys { key: AesKey::random().wrap(&self.cipher), next_sender: SERVER_SENDER + 1, next_epoch: 0 };
+            self.contents.rooms.insert(name.to_string(), room);
+        }
+        let room = self.contents.rooms.get_mut(name).expect("방을 방금 확인함");
+        let key = AesKey::unwrap(&self.cipher, &room.key)?;
+        let (first_sender, epoch) = (room.next_sender, room.next_epoch);
+        room.next_sender = first_sender.checked_add(SENDER_BLOCK).ok_or("발신자 번호를 모두 썼습니다. 키 저장소의 방 키를 새로 만들어야 합니다.")?;
+        room.next_epoch = epoch.checked_add(1).ok_or("Room Key의 키 갱신 한도를 모두 썼습니다. 키 저장소의 방 키를 새로 만들어야 합니다.")?;
+        self.save()?;
+        Ok((key, first_sender, epoch))
+    }
+
+    // 로그인한 연결에 발신자 번호를 주기 전에 호출. 예약한 범위를 넘으면 다음 묶음을 예약해서 저장
+    pub fn reserve_sender(&mut self, name: &str, sender: u32) -> Result<(), String> {
+        let room = self.contents.rooms.get_mut(name).ok_or_else(|| format!("키 저장소에 {} 방이 없습니다.", name))?;
+        if sender >= room.next_sender {
+            room.next_sender = sender.saturating_add(SENDER_BLOCK);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    // 서버가 이 세대로 암호화하기 전에 호출. 아직 저장하지 않은 세대면 저장
+    pub fn reserve_epoch(&mut self, name: &str, epoch: u32) -> Result<(), String> {
+        let room = self.contents.rooms.get_mut(name).ok_or_else(|| format!("키 저장소에 {} 방이 없습니다.", name))?;
+        if epoch >= room.next_epoch {
+            room.next_epoch = epoch.checked_add(1).ok_or("Room Key의 키 갱신 한도를 모두 썼습니다.")?;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    // 임시 파일에 쓰고 이름을 바꿔서, 저장 중에 멈춰도 이전 내용이 남게 함
+    fn save(&self) -> Result<(), String> {
+        use std::os::unix::fs::OpenOptionsExt;
+        use std::io::Write;
+
+        let plaintext = Zeroizing::new(serde_json::to_vec(&self.contents).expect("키 저장소 직렬화는 실패하지 않음"));
+        let file = File {
+            version: VERSION,
+            salt: general_purpose::STANDARD.encode(&self.salt),
+            sealed: packet::seal(&self.cipher, &plaintext),
+        };
+        let text = serde_json::to_string(&file).expect("키 저장소 직렬화는 실패하지 않음");
+        let tmp = self.path.with_extension("tmp");
+        let error = |e: std::io::Error| format!("키 저장소 저장 실패: {}", e);
+        let mut out = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp)
+            .map_err(error)?;
+        writeln!(out, "{}", text).and_then(|_| out.sync_all()).map_err(error)?;
+        std::fs::rename(&tmp, &self.path).map_err(error)
+    }
+}
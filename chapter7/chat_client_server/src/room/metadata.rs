@@ -0,0 +1,127 @@
+// src/room/metadata.rs
+// 이 모듈은 방의 메타데이터(토픽, 만든 시각, 접속자 수, 암호화 여부)와 서버 공지(MOTD)를 주고받는 형식, 그리고 방 설정 파일을 담당합니다.
+// 서버의 안내는 다른 방 메시지처럼 Room Key로 암호화해서 "[*]: <패킷> t=<시각>"으로 보냅니다. ("*"는 닉네임으로 쓸 수 없으므로 서버를 뜻함)
+//   "\0MOTD <본문>"   접속 직후 한 번 (--motd)
+//   "\0ROOM <JSON>"   접속 직후와 토픽이 바뀔 때
+//                     예) {"name":"room","topic":{"text":"배포 주간","set_by":"alice","set_at":1760862000123},"created":1760000000000,"members":3,"encrypted":true}
+// 운영자(--operator)는 "\0TOPIC <토픽>"을 방 메시지로 보내서 토픽을 바꿉니다 (빈 토픽이면 지움). 서버는 이 요청을 다른 사람에게 전달하지 않습니다.
+// 방 설정 파일(--rooms)은 JSON이고, 방 이름마다 만든 시각, 토픽, 그 방의 설정(패딩 정책, 키 갱신 한도, 암호화 여부)을 저장합니다.
+//   {"room":{"created":1760000000000,"topic":{...},"padding":"pow2","rekey_after":16777216,"encrypted":true}}
+// 연합된 서버들은 Room Key와 마찬가지로 토픽도 서버마다 따로 가집니다.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+// 서버가 보내는 방 메시지의 보낸 사람
+pub const SERVER_NICK: &str = "*";
+// 서버의 유일한 채팅방 이름
+pub const MAIN_ROOM: &str = "room";
+pub const MOTD_PREFIX: &str = "\u{0}MOTD ";
+pub const ROOM_PREFIX: &str = "\u{0}ROOM ";
+pub const TOPIC_PREFIX: &str = "\u{0}TOPIC ";
+// 토픽의 최대 글자 수
+pub const MAX_TOPIC_CHARS: usize = 300;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Topic {
+    pub text: String,
+    pub set_by: String,
+    pub set_at: i64,
+}
+
+// 클라이언트에게 보내는 방 정보
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RoomInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<Topic>,
+    pub created: i64,
+    pub members: usize,
+    // 방 메시지가 Room Key로 암호화되는지 (방 설정에 기록된 값)
+    pub encrypted: bool,
+}
+
+impl RoomInfo {
+    pub fn to_control(&self) -> String {
+        format!("{}{}", ROOM_PREFIX, serde_json::to_string(self).expect("방 정보 직렬화는 실패하지 않음"))
+    }
+
+    pub fn from_control(text: &str) -> Option<Self> {
+        serde_json::from_str(text.strip_prefix(ROOM_PREFIX)?).ok()
+    }
+}
+
+// 설정 파일에 저장하는 방 하나
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct RoomConfig {
+    created: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    topic: Option<Topic>,
+    padding: String,
+    rekey_after: u32,
+    // 예전 설정 파일에는 없지만, 방을 열 때마다 이번 실행의 값으로 갱신됨
+    #[serde(default)]
+    encrypted: bool,
+}
+
+pub struct Rooms {
+    // None이면 메모리에만 보관 (서버를 다시 시작하면 토픽이 사라짐)
+    path: Option<PathBuf>,
+    rooms: BTreeMap<String, RoomConfig>,
+}
+
+impl Rooms {
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let rooms = match path.map(std::fs::read_to_string) {
+            None => BTreeMap::new(),
+            Some(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Some(Err(e)) => return Err(format!("방 설정 파일을 읽을 수 없습니다: {}", e)),
+            Some(Ok(text)) => serde_json::from_str(&text).map_err(|e| format!("방 설정 파일 형식이 잘못되었습니다: {}", e))?,
+        };
+        Ok(Self { path: path.map(Path::to_path_buf), rooms })
+    }
+
+    // 방을 열 때 (처음이면 만든 시각을 기록). 설정은 이번 실행의 값으로 갱신
+    pub fn open(&mut self, name: &str, padding: &str, rekey_after: u32, encrypted: bool, now: i64) -> Result<(), String> {
+        let room = self.rooms.entry(name.to_string()).or_insert_with(|| RoomConfig {
+            created: now,
+            topic: None,
+            padding: String::new(),
+            rekey_after,
+            encrypted,
+        });
+        room.padding = padding.to_string();
+        room.rekey_after = rekey_after;
+        room.encrypted = encrypted;
+        self.save()
+    }
+
+    // 토픽 변경 (빈 토픽이면 지움)
+    pub fn set_topic(&mut self, name: &str, text: &str, set_by: &str, now: i64) -> Result<(), String> {
+        let text = text.trim();
+        if text.chars().count() > MAX_TOPIC_CHARS {
+            return Err(format!("토픽은 {}자까지 쓸 수 있습니다.", MAX_TOPIC_CHARS));
+        }
+        if text.chars().any(char::is_control) {
+            return Err("토픽에는 줄바꿈이나 제어 문자를 쓸 수 없습니다.".to_string());
+        }
+        let room = self.rooms.get_mut(name).ok_or_else(|| format!("{} 방이 없습니다.", name))?;
+        room.topic = (!text.is_empty()).then(|| Topic { text: text.to_string(), set_by: set_by.to_string(), set_at: now });
+        self.save()
+    }
+
+    pub fn info(&self, name: &str, members: usize) -> Option<RoomInfo> {
+        let room = self.rooms.get(name)?;
+        Some(RoomInfo { name: name.to_string(), topic: room.topic.clone(), created: room.created, members, encrypted: room.encrypted })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+        let text = serde_json::to_string_pretty(&self.rooms).expect("방 설정 직렬화는 실패하지 않음");
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, text + "\n").map_err(|e| format!("방 설정 저장 실패: {}", e))?;
+        std::fs::rename(&tmp, path).map_err(|e| format!("방 설정 저장 실패: {}", e))
+    }
+}
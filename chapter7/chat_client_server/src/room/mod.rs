@@ -0,0 +1,2 @@
+pub mod keystore;
+pub mod metadata;
\ No newline at end of file
@@ -0,0 +1,200 @@
+// src/transport/listener.rs
+// 이 모듈은 chatserver가 접속을 받는 리스너(TCP, 유닉스 도메인 소켓, systemd 소켓 활성화)를 담당합니다.
+// 어떤 리스너로 들어온 접속이든 같은 스트림 타입(Box<dyn ChatStream>)으로 돌려주므로, 접속 처리 코드는 하나만 있으면 됩니다.
+//   - 유닉스 소켓은 파일 권한(기본 0660)으로 접속할 수 있는 로컬 사용자를 제한
+//   - 소켓 활성화: LISTEN_PID가 이 프로세스이면 fd 3부터 LISTEN_FDS개의 리스닝 소켓을 넘겨받아 사용
+//   - QUIC: 접속마다 상대가 여는 "room" 스트림 하나하나를 TCP 접속 하나처럼 넘겨줌
+
+use std::net::SocketAddr;
+use std::os::fd::{FromRawFd, IntoRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::quic;
+
+// systemd가 넘겨주는 첫 번째 fd 번호 (sd_listen_fds의 SD_LISTEN_FDS_START)
+const LISTEN_FDS_START: RawFd = 3;
+
+// 유닉스 소켓 접속에 붙이는 일련번호 (상대 주소가 없으므로 로그와 전달 메시지 구분에 사용)
+static UNIX_PEERS: AtomicU64 = AtomicU64::new(1);
+
+// TCP 소켓, 유닉스 소켓, TLS 스트림을 같은 타입으로 다루기 위한 트레이트
+pub trait ChatStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ChatStream for T {}
+
+// 접속한 상대의 주소. 유닉스 소켓은 일련번호로 구분
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PeerAddr {
+    Tcp(SocketAddr),
+    Unix(u64),
+    // QUIC 연결의 (접속할 때의) 상대 주소와 스트림 번호
+    Quic(SocketAddr, u64),
+}
+
+impl std::fmt::Display for PeerAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PeerAddr::Tcp(addr) => write!(f, "{}", addr),
+            PeerAddr::Unix(n) => write!(f, "unix#{}", n),
+            PeerAddr::Quic(addr, stream) => write!(f, "quic {}#{}", addr, stream),
+        }
+    }
+}
+
+pub enum Listener {
+    Tcp(TcpListener),
+    // 직접 만든 유닉스 소켓이면 종료 후 남는 소켓 파일 경로도 기억
+    Unix(UnixListener, Option<PathBuf>),
+    // QUIC 엔드포인트에서 받은 스트림들 (연결마다 돌아가는 태스크가 채워 넣음)
+    Quic(quinn::Endpoint, Mutex<mpsc::Receiver<Accepted>>),
+}
+
+type Accepted = (Box<dyn ChatStream>, PeerAddr);
+
+impl Listener {
+    pub async fn bind_tcp(addr: &str) -> Result<Self, String> {
+        TcpListener::bind(addr)
+            .await
+            .map(Listener::Tcp)
+            .map_err(|e| format!("{}에 바인딩할 수 없습니다: {}", addr, e))
+    }
+
+    // 유닉스 소켓 생성. 이전 실행이 남긴 소켓 파일은 지우고, 만든 뒤 권한을 mode로 설정
+    pub fn bind_unix(path: &Path, mode: u32) -> Result<Self, String> {
+        use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+        if let Ok(meta) = std::fs::symlink_metadata(path) {
+            if !meta.file_type().is_socket() {
+                return Err(format!("{}이(가) 이미 있고 소켓 파일이 아닙니다.", path.display()));
+            }
+            std::fs::remove_file(path).map_err(|e| format!("이전 소켓 파일을 지울 수 없습니다: {}", e))?;
+        }
+        let listener = UnixListener::bind(path).map_err(|e| format!("{}에 바인딩할 수 없습니다: {}", path.display(), e))?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+            .map_err(|e| format!("소켓 파일 권한을 설정할 수 없습니다: {}", e))?;
+        Ok(Listener::Unix(listener, Some(path.to_path_buf())))
+    }
+
+    // systemd 소켓 활성화로 넘겨받은 리스닝 소켓들. 환경 변수가 없거나 다른 프로세스 것이면 빈 목록
+    pub fn from_systemd() -> Result<Vec<Self>, String> {
+        let for_me = std::env::var("LISTEN_PID")
+            .ok()
+            .and_then(|pid| pid.parse::<u32>().ok())
+            .is_some_and(|pid| pid == std::process::id());
+        if !for_me {
+            return Ok(vec![]);
+        }
+        let count: RawFd = std::env::var("LISTEN_FDS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .ok_or("LISTEN_FDS 값이 잘못되었습니다.")?;
+
+        (LISTEN_FDS_START..LISTEN_FDS_START + count).map(Self::from_fd).collect()
+    }
+
+    // 넘겨받은 fd가 TCP 소켓인지 유닉스 소켓인지는 주소를 읽어 봐서 판단
+    fn from_fd(fd: RawFd) -> Result<Self, String> {
+        // SAFETY: systemd가 이 프로세스에 넘겨준 fd이고, 여기서 한 번만 소유권을 가져감
+        let tcp = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+        if tcp.local_addr().is_ok() {
+            tcp.set_nonblocking(true).map_err(|e| format!("fd {}: {}", fd, e))?;
+            return TcpListener::from_std(tcp).map(Listener::Tcp).map_err(|e| format!("fd {}: {}", fd, e));
+        }
+
+        // SAFETY: 위에서 꺼낸 같은 fd를 유닉스 소켓으로 다시 소유
+        let unix = unsafe { std::os::unix::net::UnixListener::from_raw_fd(tcp.into_raw_fd()) };
+        if unix.local_addr().is_err() {
+            return Err(format!("fd {}는 TCP나 유닉스 리스닝 소켓이 아닙니다.", fd));
+        }
+        unix.set_nonblocking(true).map_err(|e| format!("fd {}: {}", fd, e))?;
+        UnixListener::from_std(unix).map(|l| Listener::Unix(l, None)).map_err(|e| format!("fd {}: {}", fd, e))
+    }
+
+    // QUIC 엔드포인트로 접속을 받음. 연결과 스트림 수락은 별도 태스크에서 진행
+    pub fn quic(endpoint: quinn::Endpoint) -> Self {
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(accept_quic(endpoint.clone(), tx));
+        Listener::Quic(endpoint, Mutex::new(rx))
+    }
+
+    // 로그에 보여줄 리스너 설명
+    pub fn describe(&self) -> String {
+        match self {
+            Listener::Tcp(listener) => match listener.local_addr() {
+                Ok(addr) => format!("tcp {}", addr),
+                Err(_) => "tcp".to_string(),
+            },
+            Listener::Unix(listener, _) => match listener.local_addr().ok().and_then(|a| a.as_pathname().map(Path::to_path_buf)) {
+                Some(path) => format!("unix {}", path.display()),
+                None => "unix".to_string(),
+            },
+            Listener::Quic(endpoint, _) => match endpoint.local_addr() {
+                Ok(addr) => format!("quic {}", addr),
+                Err(_) => "quic".to_string(),
+            },
+        }
+    }
+
+    pub async fn accept(&self) -> std::io::Result<(Box<dyn ChatStream>, PeerAddr)> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (socket, addr) = listener.accept().await?;
+                Ok((Box::new(socket), PeerAddr::Tcp(addr)))
+            }
+            Listener::Unix(listener, _) => {
+                let (socket, _) = listener.accept().await?;
+                Ok((Box::new(socket), PeerAddr::Unix(UNIX_PEERS.fetch_add(1, Ordering::Relaxed))))
+            }
+            Listener::Quic(_, rx) => rx
+                .lock()
+                .await
+                .recv()
+                .await
+                .ok_or_else(|| std::io::Error::other("QUIC 엔드포인트가 닫혔습니다.")),
+        }
+    }
+}
+
+impl Drop for Listener {
+    // 직접 만든 소켓 파일은 서버가 끝날 때 지움 (넘겨받은 소켓은 systemd가 관리)
+    fn drop(&mut self) {
+        if let Listener::Unix(_, Some(path)) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+// QUIC 접속을 받고, 접속마다 태스크를 띄워 상대가 여는 스트림을 넘겨줌
+async fn accept_quic(endpoint: quinn::Endpoint, tx: mpsc::Sender<Accepted>) {
+    while let Some(incoming) = endpoint.accept().await {
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let connecting = match incoming.accept() {
+                Ok(connecting) => connecting,
+                Err(e) => return eprintln!("QUIC 접속 실패: {}", e),
+            };
+            // 핸드셰이크가 끝난 뒤에만 스트림을 받음 (0-RTT 데이터는 받지 않음)
+            let connection = match connecting.await {
+                Ok(connection) => connection,
+                Err(e) => return eprintln!("QUIC 핸드셰이크 실패: {}", e),
+            };
+            let remote = connection.remote_address();
+            while let Some(result) = quic::accept_stream(&connection).await {
+                match result {
+                    Ok((purpose, stream)) if purpose == quic::ROOM_STREAM => {
+                        let peer = PeerAddr::Quic(remote, stream.reader().id().index());
+                        if tx.send((Box::new(stream), peer)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Ok((purpose, _)) => eprintln!("QUIC [{}] 알 수 없는 스트림 용도: {}", remote, purpose),
+                    Err(e) => eprintln!("QUIC [{}] {}", remote, e),
+                }
+            }
+        });
+    }
+}
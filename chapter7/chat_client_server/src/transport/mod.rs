@@ -0,0 +1,3 @@
+pub mod listener;
+pub mod quic;
+pub mod tls;
\ No newline at end of file
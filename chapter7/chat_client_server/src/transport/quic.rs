@@ -0,0 +1,208 @@
+// src/transport/quic.rs
+// 이 모듈은 선택적으로 쓰는 QUIC 전송 계층(quinn)을 담당합니다.
+// TCP는 한 연결의 바이트가 한 줄로 흐르므로 큰 데이터 하나가 뒤의 채팅 메시지를 붙잡지만(head-of-line blocking),
+// QUIC는 한 연결 안에 독립된 스트림을 여러 개 열 수 있습니다.
+//   - 스트림을 연 쪽이 첫 줄에 용도를 적음. 그 뒤는 TCP와 똑같은 줄 단위 프로토콜
+//     지금은 채팅방이 하나라서 용도는 "room" 하나뿐이고, chatclient는 연결마다 이 스트림 하나만 엶
+//     (방이나 파일 전송이 생기면 용도를 늘려서 각자 스트림을 따로 쓰면 됨)
+//   - TLS 1.3이 QUIC에 포함되어 있으므로 서버 인증서가 필요 (`chatserver gen-certs`로 만든 테스트용 인증서 사용)
+//   - 접속이 끊겨서 다시 접속할 때는 지난 접속에서 받은 TLS 세션 티켓으로 세션을 재개(resumption)함
+//     인증서를 다시 주고받고 검증하지 않아도 되지만, 왕복 횟수는 보통 접속과 같음(1-RTT)
+//   - 0-RTT(세션 티켓으로 핸드셰이크가 끝나기 전에 보내는 데이터)는 쓰지 않음
+//     0-RTT 데이터는 네트워크의 공격자가 녹화했다가 그대로 다시 보낼 수 있고(재전송 공격), 다시 접속해도 로그인부터 다시 하므로 아끼는 왕복도 크지 않음
+//   - 클라이언트는 keep-alive를 보내고 유휴 시간 한도를 짧게 잡아서, 끊긴 연결을 양쪽 모두 빨리 알아챔
+//   - 클라이언트 주소가 바뀌어도(와이파이 ↔ 유선 등) 연결 ID로 같은 연결을 계속 사용(connection migration)
+
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use quinn::crypto::rustls::{QuicClientConfig, QuicServerConfig};
+use quinn::{Connection, Endpoint, RecvStream, SendStream};
+use rustls::client::{ClientSessionMemoryCache, ClientSessionStore, Resumption, Tls12ClientSessionValue, Tls13ClientSessionValue};
+use rustls::pki_types::ServerName;
+use rustls::NamedGroup;
+use tokio::io::{AsyncReadExt, Join};
+
+use crate::tls;
+
+// TLS ALPN으로 협상하는 응용 프로토콜 이름
+const ALPN: &[u8] = b"chat-line/1";
+// 스트림 첫 줄(용도)의 최대 길이
+const MAX_HEADER_LEN: usize = 64;
+// 채팅방 스트림의 용도 표시
+pub const ROOM_STREAM: &str = "room";
+// 클라이언트가 keep-alive를 보내는 간격과, 아무것도 오지 않으면 연결이 끊긴 것으로 보는 시간
+const KEEP_ALIVE: Duration = Duration::from_secs(5);
+const IDLE_TIMEOUT: Duration = Duration::from_secs(15);
+// 기억해 둘 서버별 세션 티켓 수
+const TICKET_CACHE: usize = 16;
+
+// 한 QUIC 스트림을 TCP 소켓처럼 읽고 쓸 수 있게 묶은 타입
+pub type QuicStream = Join<RecvStream, SendStream>;
+
+// 서버: 인증서 체인과 비밀키(PEM)로 QUIC 엔드포인트 생성
+pub fn server_endpoint(addr: &str, cert_path: &Path, key_path: &Path) -> Result<Endpoint, String> {
+    let addr: SocketAddr = addr.parse().map_err(|_| format!("QUIC 주소가 잘못되었습니다: {}", addr))?;
+    let mut config = tls::server_config(cert_path, key_path)?;
+    config.alpn_protocols = vec![ALPN.to_vec()];
+    // 0-RTT 거부 (QUIC에서는 0과 u32::MAX(무제한)만 쓸 수 있어서 그 사이의 작은 한도는 둘 수 없음)
+    config.max_early_data_size = 0;
+    let crypto = QuicServerConfig::try_from(config).map_err(|e| format!("QUIC TLS 설정 실패: {}", e))?;
+    Endpoint::server(quinn::ServerConfig::with_crypto(Arc::new(crypto)), addr)
+        .map_err(|e| format!("{}에 QUIC 바인딩할 수 없습니다: {}", addr, e))
+}
+
+// 클라이언트: 한 서버에 접속하고, 끊기면 같은 엔드포인트와 세션 티켓으로 다시 접속
+pub struct QuicClient {
+    endpoint: Endpoint,
+    server: SocketAddr,
+    server_name: String,
+    tickets: Arc<TicketStore>,
+}
+
+impl QuicClient {
+    // TLS 설정(CA 검증 또는 인증서 고정)으로 QUIC 엔드포인트 생성
+    pub fn new(server: SocketAddr, server_name: &str, mut config: rustls::ClientConfig) -> Result<Self, String> {
+        let tickets = Arc::new(TicketStore::default());
+        config.alpn_protocols = vec![ALPN.to_vec()];
+        config.resumption = Resumption::store(tickets.clone());
+        let crypto = QuicClientConfig::try_from(config).map_err(|e| format!("QUIC TLS 설정 실패: {}", e))?;
+        let mut transport = quinn::TransportConfig::default();
+        transport.keep_alive_interval(Some(KEEP_ALIVE));
+        transport.max_idle_timeout(Some(IDLE_TIMEOUT.try_into().map_err(|_| "QUIC 유휴 시간 한도가 잘못되었습니다.".to_string())?));
+        let mut client_config = quinn::ClientConfig::new(Arc::new(crypto));
+        client_config.transport_config(Arc::new(transport));
+
+        let local: SocketAddr = if server.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" }.parse().unwrap();
+        let mut endpoint = Endpoint::client(local).map_err(|e| format!("QUIC 엔드포인트를 만들 수 없습니다: {}", e))?;
+        endpoint.set_default_client_config(client_config);
+        Ok(Self { endpoint, server, server_name: server_name.to_string(), tickets })
+    }
+
+    // 서버에 접속하고 용도를 적은 스트림 하나를 엶. 세션 티켓을 내밀어 재개를 요청했으면 true
+    // (서버가 다시 시작해서 티켓을 모르면 보통 핸드셰이크로 진행하므로 접속 자체는 그대로 됨)
+    pub async fn connect(&self, purpose: &str) -> Result<(Connection, QuicStream, bool), String> {
+        self.tickets.offered.store(false, Ordering::SeqCst);
+        let connecting = self.endpoint.connect(self.server, &self.server_name).map_err(|e| format!("QUIC 접속 실패: {}", e))?;
+        let connection = connecting.await.map_err(|e| format!("QUIC 접속 실패: {}", e))?;
+        let offered_ticket = self.tickets.offered.load(Ordering::SeqCst);
+        let (mut send, recv) = connection.open_bi().await.map_err(|e| format!("QUIC 스트림을 열 수 없습니다: {}", e))?;
+        // 데이터를 보내야 상대가 스트림이 열린 것을 알 수 있으므로 용도 줄을 바로 보냄
+        send.write_all(format!("{}\n", purpose).as_bytes())
+            .await
+            .map_err(|e| format!("QUIC 스트림에 쓸 수 없습니다: {}", e))?;
+        Ok((connection, tokio::io::join(recv, send), offered_ticket))
+    }
+
+    // 종료할 때 연결을 정상적으로 닫고, 닫힘이 상대에게 전달될 때까지 기다림
+    pub async fn close(&self, connection: &Connection) {
+        connection.close(0u32.into(), b"bye");
+        self.endpoint.wait_idle().await;
+    }
+}
+
+// 세션 티켓 저장소: rustls의 메모리 캐시를 그대로 쓰고, 접속할 때 티켓을 꺼내 썼는지만 기록
+#[derive(Debug)]
+struct TicketStore {
+    cache: ClientSessionMemoryCache,
+    offered: AtomicBool,
+}
+
+impl Default for TicketStore {
+    fn default() -> Self {
+        Self { cache: ClientSessionMemoryCache::new(TICKET_CACHE), offered: AtomicBool::new(false) }
+    }
+}
+
+impl ClientSessionStore for TicketStore {
+    fn set_kx_hint(&self, server_name: ServerName<'static>, group: NamedGroup) {
+        self.cache.set_kx_hint(server_name, group)
+    }
+
+    fn kx_hint(&self, server_name: &ServerName<'_>) -> Option<NamedGroup> {
+        self.cache.kx_hint(server_name)
+    }
+
+    fn set_tls12_session(&self, server_name: ServerName<'static>, value: Tls12ClientSessionValue) {
+        self.cache.set_tls12_session(server_name, value)
+    }
+
+    fn tls12_session(&self, server_name: &ServerName<'_>) -> Option<Tls12ClientSessionValue> {
+        self.cache.tls12_session(server_name)
+    }
+
+    fn remove_tls12_session(&self, server_name: &ServerName<'static>) {
+        self.cache.remove_tls12_session(server_name)
+    }
+
+    fn insert_tls13_ticket(&self, server_name: ServerName<'static>, value: Tls13ClientSessionValue) {
+        self.cache.insert_tls13_ticket(server_name, value)
+    }
+
+    fn take_tls13_ticket(&self, server_name: &ServerName<'static>) -> Option<Tls13ClientSessionValue> {
+        let ticket = self.cache.take_tls13_ticket(server_name);
+        if ticket.is_some() {
+            self.offered.store(true, Ordering::SeqCst);
+        }
+        ticket
+    }
+}
+
+// 서버: 상대가 연 스트림을 받아서 용도 줄을 읽음. 연결이 끝나면 None
+pub async fn accept_stream(connection: &Connection) -> Option<Result<(String, QuicStream), String>> {
+    let (send, mut recv) = connection.accept_bi().await.ok()?;
+    // 용도 줄 뒤의 데이터는 줄 단위 처리 쪽에서 읽어야 하므로 한 바이트씩 읽음
+    let mut header = Vec::new();
+    loop {
+        match recv.read_u8().await {
+            Ok(b'\n') => break,
+            Ok(_) if header.len() >= MAX_HEADER_LEN => return Some(Err("QUIC 스트림 용도 줄이 너무 깁니다.".to_string())),
+            Ok(byte) => header.push(byte),
+            Err(e) => return Some(Err(format!("QUIC 스트림 용도를 읽을 수 없습니다: {}", e))),
+        }
+    }
+    let purpose = String::from_utf8_lossy(&header).into_owned();
+    Some(Ok((purpose, tokio::io::join(recv, send))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    #[tokio::test]
+    async fn reconnect_resumes_tls_session() {
+        let dir = std::env::temp_dir().join(format!("chat-quic-test-{}", std::process::id()));
+        tls::generate_certs(&dir, &["localhost".to_string()]).unwrap();
+        let server = server_endpoint("127.0.0.1:0", &dir.join("server.pem"), &dir.join("server.key")).unwrap();
+        let addr = server.local_addr().unwrap();
+        // 서버: 스트림마다 용도 줄을 그대로 되돌려 보냄
+        tokio::spawn(async move {
+            while let Some(incoming) = server.accept().await {
+                let Ok(connection) = incoming.await else { continue };
+                tokio::spawn(async move {
+                    while let Some(Ok((purpose, mut stream))) = accept_stream(&connection).await {
+                        let _ = stream.write_all(format!("{}\n", purpose).as_bytes()).await;
+                    }
+                });
+            }
+        });
+
+        // 처음에는 티켓이 없으므로 보통 핸드셰이크, 끊고 다시 접속하면 받아 둔 티켓으로 재개
+        let client = QuicClient::new(addr, "localhost", tls::client_config_with_ca(&dir.join("ca.pem")).unwrap()).unwrap();
+        let mut offered = Vec::new();
+        for _ in 0..2 {
+            let (connection, stream, offered_ticket) = client.connect(ROOM_STREAM).await.unwrap();
+            let mut line = String::new();
+            BufReader::new(stream).read_line(&mut line).await.unwrap();
+            assert_eq!(line, "room\n");
+            offered.push(offered_ticket);
+            connection.close(0u32.into(), b"bye");
+        }
+        assert_eq!(offered, [false, true]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,174 @@
+// src/transport/tls.rs
+// 이 모듈은 선택적으로 쓰는 TLS 전송 계층(rustls + tokio-rustls)을 담당합니다.
+// TLS는 바깥 포장일 뿐이고, 그 안에서 하는 ECDH/Noise 핸드셰이크와 Room Key 암호화는 그대로 유지됩니다.
+//   - 서버: PEM 인증서/비밀키로 TlsAcceptor 생성
+//   - 클라이언트: CA 인증서로 검증하거나, 서버 인증서 자체를 고정(pin)해서 검증
+//   - 테스트용: rcgen으로 자체 서명 CA와 그 CA가 서명한 서버 인증서를 오프라인으로 생성
+
+use std::path::Path;
+use std::sync::Arc;
+
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair, KeyUsagePurpose};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, WebPkiSupportedAlgorithms};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, ServerConfig, SignatureScheme};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("인증서를 읽을 수 없습니다 ({}): {}", path.display(), e))?;
+    if certs.is_empty() {
+        return Err(format!("인증서 파일에 인증서가 없습니다: {}", path.display()));
+    }
+    Ok(certs)
+}
+
+// 서버: 인증서 체인과 비밀키(PEM)로 TLS 수락기 생성
+pub fn acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor, String> {
+    Ok(TlsAcceptor::from(Arc::new(server_config(cert_path, key_path)?)))
+}
+
+// 서버 TLS 설정 (QUIC 리스너도 같은 설정에 ALPN 등을 더해서 사용)
+pub fn server_config(cert_path: &Path, key_path: &Path) -> Result<ServerConfig, String> {
+    let certs = load_certs(cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| format!("TLS 비밀키를 읽을 수 없습니다 ({}): {}", key_path.display(), e))?;
+    let config = ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e| format!("TLS 설정 실패: {}", e))?;
+    Ok(config)
+}
+
+// 클라이언트: 주어진 CA 인증서로 서버 인증서 체인과 이름을 검증
+pub fn connector_with_ca(ca_path: &Path) -> Result<TlsConnector, String> {
+    Ok(TlsConnector::from(Arc::new(client_config_with_ca(ca_path)?)))
+}
+
+pub fn client_config_with_ca(ca_path: &Path) -> Result<ClientConfig, String> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(ca_path)? {
+        roots.add(cert).map_err(|e| format!("CA 인증서를 추가할 수 없습니다: {}", e))?;
+    }
+    let config = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("TLS 설정 실패: {}", e))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(config)
+}
+
+// 클라이언트: 서버가 보낸 인증서가 파일의 인증서와 바이트 단위로 같을 때만 허용 (CA/이름/만료 검사 없음)
+pub fn connector_with_pin(cert_path: &Path) -> Result<TlsConnector, String> {
+    Ok(TlsConnector::from(Arc::new(client_config_with_pin(cert_path)?)))
+}
+
+pub fn client_config_with_pin(cert_path: &Path) -> Result<ClientConfig, String> {
+    let pinned = load_certs(cert_path)?.swap_remove(0);
+    let provider = provider();
+    let verifier = PinnedCert { pinned, algorithms: provider.signature_verification_algorithms };
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("TLS 설정 실패: {}", e))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Ok(config)
+}
+
+// "host:port" 주소에서 TLS 서버 이름(SNI, 인증서 검증용)을 뽑아냄
+pub fn server_name(addr: &str) -> Result<ServerName<'static>, String> {
+    let host = host(addr);
+    ServerName::try_from(host.to_string()).map_err(|_| format!("TLS 서버 이름으로 쓸 수 없습니다: {}", host))
+}
+
+// "host:port" 주소의 호스트 부분 (IPv6는 대괄호를 뗌)
+pub fn host(addr: &str) -> &str {
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+#[derive(Debug)]
+struct PinnedCert {
+    pinned: CertificateDer<'static>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinnedCert {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if end_entity.as_ref() == self.pinned.as_ref() {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General("서버 인증서가 고정한 인증서와 다릅니다.".to_string()))
+        }
+    }
+
+    // 인증서는 고정값과 비교하지만, 핸드셰이크 서명은 그 인증서의 키로 정상 검증해야 함
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+// 테스트용 인증서 생성: out_dir에 ca.pem, server.pem, server.key를 씀
+// CA 비밀키는 저장하지 않으므로, 서버 인증서를 새로 만들려면 CA부터 다시 생성해야 함
+pub fn generate_certs(out_dir: &Path, names: &[String]) -> Result<(), String> {
+    let err = |e: rcgen::Error| format!("인증서 생성 실패: {}", e);
+
+    let ca_key = KeyPair::generate().map_err(err)?;
+    let mut ca_params = CertificateParams::new(Vec::<String>::new()).map_err(err)?;
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    ca_params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    ca_params.distinguished_name.push(DnType::CommonName, "chatserver test CA");
+    let ca_cert = ca_params.self_signed(&ca_key).map_err(err)?;
+
+    let server_key = KeyPair::generate().map_err(err)?;
+    let mut server_params = CertificateParams::new(names.to_vec()).map_err(err)?;
+    server_params.distinguished_name.push(DnType::CommonName, "chatserver");
+    let server_cert = server_params.signed_by(&server_key, &ca_cert, &ca_key).map_err(err)?;
+
+    std::fs::create_dir_all(out_dir).map_err(|e| format!("디렉터리를 만들 수 없습니다: {}", e))?;
+    let write = |name: &str, text: String| {
+        std::fs::write(out_dir.join(name), text).map_err(|e| format!("{} 저장 실패: {}", name, e))
+    };
+    write("ca.pem", ca_cert.pem())?;
+    write("server.pem", server_cert.pem())?;
+    write("server.key", server_key.serialize_pem())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(out_dir.join("server.key"), std::fs::Permissions::from_mode(0o600));
+    }
+    Ok(())
+}
@@ -0,0 +1,105 @@
+// src/ui/markdown.rs
+// 이 모듈은 받은 메시지 본문의 간단한 마크다운을 터미널 색(ANSI)으로 그리는 일을 담당합니다.
+//   ```로 감싼 코드 블록 → 줄마다 "│ "를 붙이고 청록색,  `코드` → 청록색,  **굵게** → 굵게,  @닉네임 → 노란색
+//   나를 부르는 멘션과 관심 키워드(mentions 모듈) → 굵은 노란색
+// 다른 사람이 보낸 본문에 들어 있는 제어 문자(터미널 제어 코드 등)는 그리기 전에 모두 지웁니다.
+// 터미널이 아니거나 NO_COLOR 환경 변수가 있으면 색 없이 원래 본문(제어 문자만 지운)을 돌려줍니다.
+
+use std::io::IsTerminal;
+
+use crate::mentions::{self, Highlight};
+
+const FENCE: &str = "```";
+// 탭은 글자 폭을 셀 수 없으므로 공백으로 바꿈
+const TAB: &str = "    ";
+
+const BOLD: &str = "\x1b[1m";
+const NORMAL: &str = "\x1b[22m";
+const CODE: &str = "\x1b[36m";
+const MENTION: &str = "\x1b[33m";
+const MENTION_ME: &str = "\x1b[1;33m";
+const RESET_COLOR: &str = "\x1b[39m";
+const DIM: &str = "\x1b[2m";
+
+pub fn render(body: &str, highlight: &Highlight) -> String {
+    let body = sanitize(body);
+    if !std::io::stdout().is_terminal() || std::env::var_os("NO_COLOR").is_some() {
+        return body;
+    }
+
+    let mut lines = vec![];
+    let mut in_code = false;
+    for line in body.split('\n') {
+        if line.trim_start().starts_with(FENCE) {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            lines.push(format!("{}│{} {}{}{}", DIM, NORMAL, CODE, line, RESET_COLOR));
+        } else {
+            lines.push(inline(line, highlight));
+        }
+    }
+    lines.join("\n")
+}
+
+// 보낸 사람이 넣은 제어 문자를 지움 (줄바꿈은 유지하고 탭은 공백으로)
+pub fn sanitize(body: &str) -> String {
+    body.replace('\t', TAB)
+        .chars()
+        .filter(|c| *c == '\n' || !c.is_control())
+        .collect()
+}
+
+// 한 줄 안의 `코드`, **굵게**, @멘션, 키워드
+fn inline(line: &str, highlight: &Highlight) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    let mut bold = false;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('`')
+            && let Some(end) = after.find('`')
+        {
+            out.push_str(&format!("{}{}{}", CODE, &after[..end], RESET_COLOR));
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix("**")
+            && (bold || after.contains("**"))
+        {
+            bold = !bold;
+            out.push_str(if bold { BOLD } else { NORMAL });
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('@')
+            && word_start(&out)
+            && let nick_len = after.find(|c: char| !mentions::is_nick_char(c)).unwrap_or(after.len())
+            && nick_len > 0
+        {
+            let nick = &after[..nick_len];
+            let style = if nick.eq_ignore_ascii_case(&highlight.nick) { MENTION_ME } else { MENTION };
+            out.push_str(&format!("{}@{}{}", style, nick, reset(style, bold)));
+            rest = &after[nick_len..];
+        } else if word_start(&out)
+            && let Some(len) = highlight.keyword_at(rest)
+        {
+            out.push_str(&format!("{}{}{}", MENTION_ME, &rest[..len], reset(MENTION_ME, bold)));
+            rest = &rest[len..];
+        } else {
+            let c = rest.chars().next().unwrap();
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if bold {
+        out.push_str(NORMAL);
+    }
+    out
+}
+
+// 단어가 시작되는 자리인지 (지금까지 그린 내용의 마지막 글자가 글자/숫자가 아님)
+fn word_start(out: &str) -> bool {
+    !out.ends_with(char::is_alphanumeric)
+}
+
+// 강조를 끝내는 코드. 굵게 안이면 색만 되돌려서 굵게는 유지
+fn reset(style: &str, bold: bool) -> String {
+    if style == MENTION_ME && !bold { format!("{}{}", NORMAL, RESET_COLOR) } else { RESET_COLOR.to_string() }
+}
@@ -0,0 +1,165 @@
+// src/ui/mentions.rs
+// 이 모듈은 나를 부른 메시지(@닉네임, 관심 키워드)를 찾아 강조하고, 방마다 안 읽은 멘션 수를 세고, 알림 명령을 실행하는 일을 담당합니다.
+//   - 멘션과 키워드는 대소문자를 가리지 않고 단어 단위로 찾음 ("@bob"은 "@bobby"에 걸리지 않음)
+//   - 멘션이나 귓속말이 오면 터미널 벨을 울리고, 터미널 제목에 안 읽은 멘션 수를 표시
+//   - 그 방에 무언가 입력하면 그 방의 멘션은 읽은 것으로 봄
+// 알림 명령은 sh -c 로 실행하고, 메시지 내용은 셸 명령에 끼워 넣지 않고 환경 변수로만 넘깁니다 (내용으로 명령을 주입할 수 없게).
+//   CHAT_KIND (mention | keyword | dm), CHAT_ROOM, CHAT_SENDER, CHAT_BODY
+//   예) --notify 'notify-send "$CHAT_SENDER ($CHAT_ROOM)" "$CHAT_BODY"'
+
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+
+use crate::markdown;
+
+// 알림으로 넘기는 본문의 최대 글자 수
+const NOTIFY_BODY_CHARS: usize = 200;
+
+// 강조할 내 닉네임과 관심 키워드
+#[derive(Clone, Debug)]
+pub struct Highlight {
+    pub nick: String,
+    keywords: Vec<String>,
+}
+
+impl Highlight {
+    pub fn new(nick: &str, keywords: &[String]) -> Self {
+        Self { nick: nick.to_string(), keywords: keywords.iter().filter(|k| !k.is_empty()).cloned().collect() }
+    }
+
+    // text의 맨 앞이 키워드로 시작하면 그 길이 (단어 경계는 호출한 쪽에서 앞을, 여기서 뒤를 확인)
+    pub fn keyword_at(&self, text: &str) -> Option<usize> {
+        self.keywords
+            .iter()
+            .find(|keyword| {
+                text.get(..keyword.len()).is_some_and(|head| head.eq_ignore_ascii_case(keyword))
+                    && !text[keyword.len()..].starts_with(is_word_char)
+            })
+            .map(|keyword| keyword.len())
+    }
+
+    // 본문이 나를 부르는지: 멘션이면 "mention", 키워드면 "keyword"
+    pub fn matches(&self, body: &str) -> Option<&'static str> {
+        let mention = format!("@{}", self.nick);
+        let mut mentioned = false;
+        let mut keyword = false;
+        for (i, _) in body.char_indices() {
+            if i > 0 && body[..i].ends_with(is_word_char) {
+                continue;
+            }
+            let rest = &body[i..];
+            mentioned |= rest.get(..mention.len()).is_some_and(|head| head.eq_ignore_ascii_case(&mention))
+                && !rest[mention.len()..].starts_with(is_nick_char);
+            keyword |= self.keyword_at(rest).is_some();
+        }
+        if mentioned {
+            Some("mention")
+        } else if keyword {
+            Some("keyword")
+        } else {
+            None
+        }
+    }
+}
+
+pub fn is_nick_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+pub struct Mentions {
+    highlight: Highlight,
+    // 알림 명령 (sh -c 로 실행)
+    notify: Option<String>,
+    // 방 이름 → 안 읽은 멘션 수 (채팅방 "room", 귓속말 "@<상대>", 그룹 "#<그룹>")
+    unread: BTreeMap<String, usize>,
+}
+
+impl Mentions {
+    pub fn new(highlight: Highlight, notify: Option<String>) -> Self {
+        Self { highlight, notify, unread: BTreeMap::new() }
+    }
+
+    // 방/그룹 메시지를 받았을 때. 나를 부른 메시지면 세고 알림
+    pub fn received(&mut self, room: &str, sender: &str, body: &str) {
+        if sender == self.highlight.nick {
+            return;
+        }
+        if let Some(kind) = self.highlight.matches(body) {
+            self.hit(kind, room, sender, body);
+        }
+    }
+
+    // 귓속말은 항상 나에게 온 것이므로 모두 세고 알림
+    pub fn direct(&mut self, sender: &str, body: &str) {
+        self.hit("dm", &format!("@{}", sender), sender, body);
+    }
+
+    // 그 방에 무언가 입력했으면 그 방의 멘션은 읽은 것으로 봄
+    pub fn read(&mut self, room: &str) {
+        if self.unread.remove(room).is_some() {
+            self.update_title(false);
+        }
+    }
+
+    // /mentions 명령: 방별 안 읽은 멘션 수 출력 ("/mentions clear"면 모두 읽음으로)
+    pub fn print(&mut self, clear: bool) {
+        if self.unread.is_empty() {
+            say!("🔔 안 읽은 멘션이 없습니다.");
+        } else {
+            let rooms: Vec<String> = self.unread.iter().map(|(room, n)| format!("{} {}", room, n)).collect();
+            say!("🔔 안 읽은 멘션: {}", rooms.join(", "));
+        }
+        if clear {
+            self.unread.clear();
+            self.update_title(false);
+        }
+    }
+
+    fn hit(&mut self, kind: &str, room: &str, sender: &str, body: &str) {
+        *self.unread.entry(room.to_string()).or_default() += 1;
+        self.update_title(true);
+        if let Some(command) = &self.notify {
+            run_notify(command, kind, room, sender, body);
+        }
+    }
+
+    // 터미널 벨과 제목 (행을 차지하지 않으므로 say! 를 거치지 않음)
+    fn update_title(&self, bell: bool) {
+        let mut stdout = std::io::stdout();
+        if !stdout.is_terminal() {
+            return;
+        }
+        let total: usize = self.unread.values().sum();
+        let title = if total == 0 { "chatclient".to_string() } else { format!("chatclient 🔔{}", total) };
+        let bell = if bell { "\x07" } else { "" };
+        let _ = write!(stdout, "\x1b]0;{}\x07{}", title, bell).and_then(|_| stdout.flush());
+    }
+}
+
+// 알림 명령 실행 (기다리지 않음). 실패하면 경고만 출력
+fn run_notify(command: &str, kind: &str, room: &str, sender: &str, body: &str) {
+    let body: String = markdown::sanitize(body).chars().take(NOTIFY_BODY_CHARS).collect();
+    let child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("CHAT_KIND", kind)
+        .env("CHAT_ROOM", room)
+        .env("CHAT_SENDER", sender)
+        .env("CHAT_BODY", body)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+    match child {
+        Ok(mut child) => {
+            tokio::spawn(async move {
+                let _ = child.wait().await;
+            });
+        }
+        Err(e) => say!("⚠️ 알림 명령을 실행할 수 없습니다: {}", e),
+    }
+}
@@ -0,0 +1,3 @@
+pub mod markdown;
+pub mod screen;
+pub mod timeline;
\ No newline at end of file
@@ -0,0 +1,88 @@
+// src/ui/screen.rs
+// 이 모듈은 chatclient의 터미널 출력을 담당합니다.
+// 지금까지 출력한 줄이 화면에서 몇 행을 차지했는지 세어 두었다가, 수정/삭제된 메시지를 그 자리에서 다시 그릴 때 사용합니다.
+// 행 수가 맞아야 하므로 chatclient의 모든 출력은 println! 대신 say! 를 거쳐야 합니다.
+
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use terminal_size::{terminal_size, Height, Width};
+use unicode_width::UnicodeWidthStr;
+
+// 터미널 크기를 알 수 없을 때 가정하는 너비
+const DEFAULT_WIDTH: usize = 80;
+
+static ROWS: AtomicUsize = AtomicUsize::new(0);
+
+// println! 과 같지만 출력한 행 수를 기록
+macro_rules! say {
+    ($($arg:tt)*) => {{
+        $crate::screen::say(&format!($($arg)*));
+    }};
+}
+
+// 한 줄 출력하고, 그 줄이 시작된 행 위치를 돌려줌
+pub fn say(text: &str) -> usize {
+    println!("{}", text);
+    ROWS.fetch_add(rows(text), Ordering::Relaxed)
+}
+
+// 사용자가 입력한 줄은 터미널이 직접 보여주므로 행 수만 더함
+pub fn count_input(text: &str) -> usize {
+    ROWS.fetch_add(rows(text.trim_end_matches('\n')), Ordering::Relaxed)
+}
+
+// 글자 폭(한글은 2칸)과 터미널 너비로 계산한, 이 텍스트가 차지하는 행 수
+pub fn rows(text: &str) -> usize {
+    let width = terminal_size().map_or(DEFAULT_WIDTH, |(Width(w), _)| w.max(1) as usize);
+    text.split('\n').map(|line| visible_width(line).div_ceil(width).max(1)).sum()
+}
+
+// 색을 입히는 ANSI 제어 코드(ESC [ ... 문자)는 화면에서 칸을 차지하지 않으므로 빼고 셈
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut rest = line;
+    while let Some(start) = rest.find('\x1b') {
+        width += rest[..start].width();
+        let code = &rest[start + 1..];
+        rest = match code.strip_prefix('[') {
+            Some(params) => params
+                .find(|c: char| ('@'..='~').contains(&c))
+                .map_or("", |end| &params[end + 1..]),
+            None => code,
+        };
+    }
+    width + rest.width()
+}
+
+// position에서 시작해 old_rows행을 차지하던 줄을 text로 바꿔 그림
+// 터미널이 아니거나, 이미 화면 밖으로 밀려났거나, 차지하는 행 수가 달라지면 false (호출한 쪽에서 새 줄로 출력)
+pub fn rewrite(position: usize, old_rows: usize, text: &str) -> bool {
+    let stdout = std::io::stdout();
+    if !stdout.is_terminal() || rows(text) != old_rows {
+        return false;
+    }
+    let Some((_, Height(height))) = terminal_size() else {
+        return false;
+    };
+    let up = ROWS.load(Ordering::Relaxed).saturating_sub(position);
+    if up == 0 || up >= height as usize {
+        return false;
+    }
+
+    // ESC 7: 커서 위치 저장 → n행 위로 → 차지하던 행들을 지우고 다시 씀 → ESC 8: 커서 복원 (입력 중인 내용은 그대로)
+    let mut out = stdout.lock();
+    let mut seq = format!("\x1b7\x1b[{}A\r", up);
+    for row in 0..old_rows {
+        seq.push_str("\x1b[2K");
+        if row + 1 < old_rows {
+            seq.push_str("\x1b[1B");
+        }
+    }
+    if old_rows > 1 {
+        seq.push_str(&format!("\x1b[{}A", old_rows - 1));
+    }
+    seq.push_str(&text.replace('\n', "\r\n"));
+    seq.push_str("\x1b8");
+    out.write_all(seq.as_bytes()).and_then(|_| out.flush()).is_ok()
+}
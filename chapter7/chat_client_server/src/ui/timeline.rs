@@ -0,0 +1,305 @@
+// src/ui/timeline.rs
+// 이 모듈은 채팅방 메시지를 ID별로 기억해 두고, 수정/삭제/반응이 오면 원래 메시지를 그 자리에서 다시 그리는 일을 담당합니다.
+// 한 줄 형식: [14:03:27] alice: 안녕하세요 (수정됨) 👍×2  ·3f2a9c
+// 여러 줄 메시지는 "[14:03:27] alice:" 다음 줄부터 본문을 들여 쓰고, 본문은 markdown 모듈로 색을 입혀 그립니다.
+// 내가 보낸 메시지에는 전달 상태를 붙입니다: ✓ 서버가 받음, ✓✓n n명이 받음, 👀n n명이 읽음
+// 화면 밖으로 밀려난 메시지는 다시 그릴 수 없으므로 새 줄로 출력합니다.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+
+use crate::envelope::{Envelope, Kind};
+use crate::markdown;
+use crate::mentions::Highlight;
+use crate::screen;
+
+// 화면에 보여주는 메시지 ID 길이 (명령에서는 이보다 짧은 앞부분도 허용)
+const SHORT_ID_LEN: usize = 6;
+// 같은 사람의 입력 중 표시를 다시 보여주기까지의 간격
+const TYPING_QUIET: Duration = Duration::from_secs(10);
+// 여러 줄 메시지의 본문 들여쓰기
+const INDENT: &str = "  ";
+
+struct Entry {
+    sender: String,
+    ts: i64,
+    body: String,
+    edited: bool,
+    deleted: bool,
+    reactions: BTreeMap<String, BTreeSet<String>>,
+    // 내 메시지의 전달 상태 (서버 확인, 받은 사람, 읽은 사람)
+    acked: bool,
+    delivered: BTreeSet<String>,
+    read: BTreeSet<String>,
+    // 화면에서 이 메시지가 시작된 행과 차지한 행 수
+    position: usize,
+    rows: usize,
+}
+
+pub struct Timeline {
+    nick: String,
+    highlight: Highlight,
+    entries: HashMap<String, Entry>,
+    // 내가 마지막으로 보낸 메시지 ("last"로 가리킬 때)
+    last_mine: Option<String>,
+    typing: HashMap<String, Instant>,
+    // 아직 읽음 확인을 보내지 않은 다른 사람의 메시지 (보낸 사람, ID)
+    unread: Vec<(String, String)>,
+    // 이미 반영한 봉투 ID (오프라인 큐로 같은 메시지가 다시 오면 무시)
+    seen: HashSet<String>,
+}
+
+impl Timeline {
+    pub fn new(highlight: &Highlight) -> Self {
+        Self {
+            nick: highlight.nick.clone(),
+            highlight: highlight.clone(),
+            entries: HashMap::new(),
+            last_mine: None,
+            typing: HashMap::new(),
+            unread: vec![],
+            seen: HashSet::new(),
+        }
+    }
+
+    // 내가 보낸 메시지 반영. 텍스트면 방금 입력한 줄(input_position부터 input_rows행)을 메시지 모양으로 다시 그림
+    pub fn sent(&mut self, envelope: &Envelope, input_position: usize, input_rows: usize) {
+        let ts = crate::envelope::now_millis();
+        match &envelope.kind {
+            Kind::Text { body } => {
+                let mut entry = Entry {
+                    sender: self.nick.clone(),
+                    ts,
+                    body: body.clone(),
+                    edited: false,
+                    deleted: false,
+                    reactions: BTreeMap::new(),
+                    acked: false,
+                    delivered: BTreeSet::new(),
+                    read: BTreeSet::new(),
+                    position: input_position,
+                    rows: input_rows,
+                };
+                let line = render(&envelope.id, &entry, &self.highlight);
+                if screen::rewrite(entry.position, entry.rows, &line) {
+                    entry.rows = screen::rows(&line);
+                }
+                self.entries.insert(envelope.id.clone(), entry);
+                self.last_mine = Some(envelope.id.clone());
+            }
+            _ => self.apply(&self.nick.clone(), envelope),
+        }
+    }
+
+    // 받은 메시지 반영. sender는 서버가 알려준 보낸 사람. 이미 받은 메시지면 false
+    pub fn receive(&mut self, sender: &str, mut envelope: Envelope) -> bool {
+        if !self.seen.insert(envelope.id.clone()) {
+            return false;
+        }
+        if envelope.sender != sender {
+            say!("⚠️ {}가 보낸 메시지에 다른 보낸 사람({})이 적혀 있습니다.", sender, envelope.sender);
+            envelope.sender = sender.to_string();
+        }
+        if let Kind::Text { body } = &envelope.kind {
+            self.typing.remove(sender);
+            let mut entry = Entry {
+                sender: sender.to_string(),
+                ts: envelope.ts.unwrap_or_else(crate::envelope::now_millis),
+                body: body.clone(),
+                edited: false,
+                deleted: false,
+                reactions: BTreeMap::new(),
+                acked: false,
+                delivered: BTreeSet::new(),
+                read: BTreeSet::new(),
+                position: 0,
+                rows: 0,
+            };
+            let line = render(&envelope.id, &entry, &self.highlight);
+            entry.position = screen::say(&line);
+            entry.rows = screen::rows(&line);
+            self.unread.push((sender.to_string(), envelope.id.clone()));
+            self.entries.insert(envelope.id, entry);
+        } else {
+            self.apply(sender, &envelope);
+        }
+        true
+    }
+
+    // 봉투가 아닌 평문 (예전 클라이언트가 보낸 메시지)
+    pub fn receive_plain(&mut self, sender: &str, ts: Option<i64>, text: &str) {
+        say!("[{}] {}: {}", clock(ts.unwrap_or_else(crate::envelope::now_millis)), sender, markdown::sanitize(text));
+    }
+
+    // 명령에서 쓴 ID(앞부분만 써도 됨) 또는 "last"를 전체 ID로 바꿈
+    pub fn resolve(&self, id: &str) -> Result<String, String> {
+        if id == "last" {
+            return self.last_mine.clone().ok_or_else(|| "아직 보낸 메시지가 없습니다.".to_string());
+        }
+        let mut matches = self.entries.keys().filter(|full| full.starts_with(id));
+        match (matches.next(), matches.next()) {
+            (Some(full), None) => Ok(full.clone()),
+            (Some(_), Some(_)) => Err(format!("{}로 시작하는 메시지가 여러 개입니다. 더 길게 입력하세요.", id)),
+            (None, _) => Err(format!("{} 메시지를 찾을 수 없습니다.", id)),
+        }
+    }
+
+    pub fn is_mine(&self, id: &str) -> bool {
+        self.entries.get(id).is_some_and(|e| e.sender == self.nick)
+    }
+
+    // 서버가 내 메시지를 받아들였음. 시각도 서버 기준으로 바꿈
+    pub fn ack(&mut self, id: &str, ts: Option<i64>) {
+        if let Some(entry) = self.entries.get_mut(id).filter(|e| e.sender == self.nick) {
+            entry.acked = true;
+            entry.ts = ts.unwrap_or(entry.ts);
+            redraw(id, entry, &self.highlight, true);
+        }
+    }
+
+    // 다른 사람이 보낸 수신/읽음 확인 반영 (내 메시지에 대한 것만)
+    pub fn receipt(&mut self, from: &str, targets: &[String], read: bool) {
+        for id in targets {
+            let Some(entry) = self.entries.get_mut(id).filter(|e| e.sender == self.nick) else {
+                continue;
+            };
+            let mut changed = entry.delivered.insert(from.to_string());
+            if read {
+                changed |= entry.read.insert(from.to_string());
+            }
+            if changed {
+                redraw(id, entry, &self.highlight, true);
+            }
+        }
+    }
+
+    // 아직 읽음 확인을 보내지 않은 메시지들을 보낸 사람별로 모아서 꺼냄
+    pub fn take_unread(&mut self) -> BTreeMap<String, Vec<String>> {
+        let mut by_sender: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (sender, id) in self.unread.drain(..) {
+            by_sender.entry(sender).or_default().push(id);
+        }
+        by_sender
+    }
+
+    // /receipts 명령: 내 메시지를 누가 받고 읽었는지 출력
+    pub fn print_receipts(&self, id: &str) {
+        let Some(entry) = self.entries.get(id).filter(|e| e.sender == self.nick) else {
+            say!("⚠️ 내가 보낸 메시지만 전달 상태를 볼 수 있습니다.");
+            return;
+        };
+        let names = |set: &BTreeSet<String>| {
+            if set.is_empty() { "-".to_string() } else { set.iter().cloned().collect::<Vec<_>>().join(", ") }
+        };
+        say!(
+            "📨 ·{} 서버 확인: {} | 받음: {} | 읽음: {}",
+            short(id),
+            if entry.acked { "✓" } else { "아직" },
+            names(&entry.delivered),
+            names(&entry.read)
+        );
+    }
+
+    // 수정/삭제/반응/입력 중 표시 반영
+    fn apply(&mut self, sender: &str, envelope: &Envelope) {
+        let (target, describe) = match &envelope.kind {
+            Kind::Text { .. } | Kind::Receipt { .. } => return,
+            Kind::Typing => {
+                let now = Instant::now();
+                if self.typing.get(sender).is_none_or(|last| now.duration_since(*last) >= TYPING_QUIET) {
+                    self.typing.insert(sender.to_string(), now);
+                    say!("✍️ {}님이 입력 중입니다...", sender);
+                }
+                return;
+            }
+            Kind::Edit { target, body } => (target, format!("✏️ {}가 메시지를 수정했습니다: {}", sender, markdown::render(body, &self.highlight))),
+            Kind::Delete { target } => (target, format!("🗑️ {}가 메시지를 삭제했습니다.", sender)),
+            Kind::Reaction { target, emoji } => (target, format!("{} {}가 반응했습니다.", emoji, sender)),
+        };
+
+        // 이번 실행에서 본 적 없는 메시지면 설명만 출력
+        let Some(entry) = self.entries.get_mut(target) else {
+            say!("{} (·{})", describe, short(target));
+            return;
+        };
+        match &envelope.kind {
+            // 수정과 삭제는 원래 보낸 사람만 가능
+            Kind::Edit { .. } | Kind::Delete { .. } if entry.sender != sender => {
+                say!("⚠️ {}가 {}의 메시지를 바꾸려 했습니다. 무시합니다.", sender, entry.sender);
+                return;
+            }
+            Kind::Edit { body, .. } if !entry.deleted => {
+                entry.body = body.clone();
+                entry.edited = true;
+            }
+            Kind::Delete { .. } => entry.deleted = true,
+            Kind::Reaction { emoji, .. } => {
+                let who = entry.reactions.entry(emoji.clone()).or_default();
+                // 같은 반응을 다시 보내면 취소
+                if !who.remove(sender) {
+                    who.insert(sender.to_string());
+                }
+                if who.is_empty() {
+                    entry.reactions.remove(emoji);
+                }
+            }
+            _ => return,
+        }
+
+        redraw(target, entry, &self.highlight, false);
+    }
+}
+
+// 바뀐 메시지를 제자리에 다시 그리고, 못 하면 새 줄로 출력
+// 전달 상태만 바뀐 경우에는 새 줄을 만들지 않음 (/receipts 로 확인)
+fn redraw(id: &str, entry: &mut Entry, highlight: &Highlight, status_only: bool) {
+    let line = render(id, entry, highlight);
+    if !screen::rewrite(entry.position, entry.rows, &line) && !status_only {
+        entry.position = screen::say(&line);
+        entry.rows = screen::rows(&line);
+    }
+}
+
+// 여러 줄 메시지는 보낸 사람 다음 줄부터 들여 써서 그림
+fn render(id: &str, entry: &Entry, highlight: &Highlight) -> String {
+    let body = if entry.deleted { "(삭제된 메시지)".to_string() } else { markdown::render(&entry.body, highlight) };
+    let mut line = if body.contains('\n') {
+        format!("[{}] {}:\n{}", clock(entry.ts), entry.sender, indent(&body))
+    } else {
+        format!("[{}] {}: {}", clock(entry.ts), entry.sender, body)
+    };
+    if entry.edited && !entry.deleted {
+        line.push_str(" (수정됨)");
+    }
+    for (emoji, who) in &entry.reactions {
+        line.push_str(&format!(" {}×{}", emoji, who.len()));
+    }
+    if entry.acked {
+        line.push_str(" ✓");
+    }
+    if !entry.delivered.is_empty() {
+        line.push_str(&format!(" ✓✓{}", entry.delivered.len()));
+    }
+    if !entry.read.is_empty() {
+        line.push_str(&format!(" 👀{}", entry.read.len()));
+    }
+    line.push_str(&format!("  ·{}", short(id)));
+    line
+}
+
+fn indent(body: &str) -> String {
+    body.split('\n').map(|line| format!("{}{}", INDENT, line)).collect::<Vec<_>>().join("\n")
+}
+
+fn short(id: &str) -> &str {
+    id.get(..SHORT_ID_LEN).unwrap_or(id)
+}
+
+// 유닉스 밀리초 → 내 컴퓨터 시간대의 "시:분:초"
+fn clock(ts: i64) -> String {
+    DateTime::from_timestamp_millis(ts)
+        .map(|t| t.with_timezone(&Local).format("%H:%M:%S").to_string())
+        .unwrap_or_else(|| "--:--:--".to_string())
+}